@@ -0,0 +1,132 @@
+//! Sets up the rendering engine without ever blocking the event loop.
+//!
+//! The window is created synchronously in `resumed`, while the rest of the
+//! setup ([`RenderingEngine::for_window`]) is an ordinary future. This example
+//! drives that future from the event loop itself: it's polled once right away,
+//! and whenever it's woken up again, its waker sends the event loop a user
+//! event to poll it once more.
+//!
+//! With an async runtime, the same future can be spawned instead (the future
+//! is `Send + 'static`), e.g., with `tokio`:
+//!
+//! ```ignore
+//! let proxy = event_loop_proxy.clone();
+//! runtime.spawn(async move {
+//!     let rendering_engine = RenderingEngine::for_window(window, color, features).await;
+//!     let _ = proxy.send_event(rendering_engine);
+//! });
+//! ```
+//!
+//! and the finished engine picked up in `user_event`.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use euclid::default::Point2D;
+use metallic::{
+    primitives::{Brush, ShapeBuilder},
+    rendering_engine::RenderingEngine,
+    MetallicResult,
+};
+use wgpu::{Color, Features};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    window::{Window, WindowId},
+};
+
+type Setup = Pin<Box<dyn Future<Output = MetallicResult<RenderingEngine>> + Send>>;
+
+/// Asks the event loop to poll the setup again.
+struct ProxyWaker(Mutex<EventLoopProxy<()>>);
+
+impl Wake for ProxyWaker {
+    fn wake(self: Arc<Self>) {
+        let _ = self.0.lock().unwrap().send_event(());
+    }
+}
+
+enum App {
+    Suspended { waker: Waker },
+    SettingUp { waker: Waker, setup: Setup },
+    Running(Box<RenderingEngine>),
+    Exited,
+}
+
+impl App {
+    /// Polls the setup once, and switches over to running once it's done.
+    fn poll_setup(&mut self) {
+        let App::SettingUp { waker, setup } = self else {
+            return;
+        };
+        let Poll::Ready(rendering_engine) = setup.as_mut().poll(&mut Context::from_waker(waker))
+        else {
+            return;
+        };
+        let mut rendering_engine = rendering_engine.expect("Error creating the rendering engine");
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .circle(Point2D::new(160.0, 120.0), 80.0)
+                .fill(Brush::Solid(Color::GREEN)),
+        );
+        rendering_engine.redraw();
+        *self = App::Running(Box::new(rendering_engine));
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let App::Suspended { waker } = self else {
+            return;
+        };
+        let window = event_loop
+            .create_window(Window::default_attributes())
+            .expect("Error creating the window");
+        let setup = RenderingEngine::for_window(Arc::new(window), Color::BLACK, Features::empty());
+        *self = App::SettingUp {
+            waker: waker.clone(),
+            setup: Box::pin(setup),
+        };
+        // On native platforms, wgpu usually finishes right away.
+        self.poll_setup();
+    }
+
+    fn user_event(&mut self, _: &ActiveEventLoop, (): ()) {
+        self.poll_setup();
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let App::Running(rendering_engine) = self else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                *self = App::Exited;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the circle"),
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::with_user_event().build()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    let waker = Waker::from(Arc::new(ProxyWaker(Mutex::new(event_loop.create_proxy()))));
+    event_loop.run_app(&mut App::Suspended { waker })?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Draws a few shapes on top of a graph-paper background image.
+//!
+//! The background is generated on the fly and stretched over the whole
+//! window, no matter how it is resized.
+
+use std::sync::Arc;
+
+use euclid::default::Point2D;
+use image::{DynamicImage, Rgba, RgbaImage};
+use metallic::{
+    primitives::{Brush, ShapeBuilder},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+/// The side length (in pixels) of one square of the graph paper.
+const CELL: u32 = 16;
+
+#[derive(Default)]
+struct GraphPaper(Option<RenderingEngine>);
+
+impl ApplicationHandler for GraphPaper {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::WHITE))
+            .expect("Error creating the rendering engine");
+        rendering_engine.set_background_image(Some(Arc::new(graph_paper(32, 32))));
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .move_to(Point2D::new(64.0, 64.0))
+                .line_to(Point2D::new(256.0, 64.0))
+                .line_to(Point2D::new(160.0, 224.0))
+                .close()
+                .fill(Brush::Solid(Color {
+                    r: 0.9,
+                    g: 0.3,
+                    b: 0.2,
+                    a: 0.8,
+                })),
+        );
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .circle(Point2D::new(320.0, 192.0), 48.0)
+                .fill(Brush::Solid(Color::BLUE)),
+        );
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the graph paper"),
+            _ => (),
+        };
+    }
+}
+
+/// Returns a `columns` by `rows` grid of light-blue lines on an off-white
+/// background.
+fn graph_paper(columns: u32, rows: u32) -> DynamicImage {
+    let (paper, line) = (Rgba([250, 248, 240, 255]), Rgba([160, 190, 230, 255]));
+    let image = RgbaImage::from_fn(columns * CELL, rows * CELL, |x, y| {
+        match x % CELL == 0 || y % CELL == 0 {
+            true => line,
+            false => paper,
+        }
+    });
+    DynamicImage::ImageRgba8(image)
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut GraphPaper::default())?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Paints a headline with a left-to-right gradient.
+//!
+//! Every glyph is colored with the gradient's color at its center, so the
+//! headline fades from one end to the other one glyph at a time. The gradient
+//! spans exactly the headline's measured width.
+
+use euclid::default::Point2D;
+use metallic::{
+    primitives::{Brush, Text},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+/// Where the headline's top-left corner is.
+const ORIGIN: Point2D<f32> = Point2D::new(40.0, 80.0);
+
+const ORANGE: Color = Color {
+    r: 1.0,
+    g: 0.4,
+    b: 0.0,
+    a: 1.0,
+};
+
+const VIOLET: Color = Color {
+    r: 0.5,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+#[derive(Default)]
+struct GradientText(Option<RenderingEngine>);
+
+impl ApplicationHandler for GradientText {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let headline = Text::new("Gradient headline")
+            .with_font_size(48.0)
+            .with_line_height(60.0)
+            .with_position(ORIGIN);
+        let width = rendering_engine.measure_text(&headline).width;
+        rendering_engine.add_object(headline.fill(Brush::LinearGradient {
+            start: ORIGIN,
+            end: Point2D::new(ORIGIN.x + width, ORIGIN.y),
+            stops: vec![(0.0, ORANGE), (1.0, VIOLET)],
+        }));
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the headline"),
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut GradientText::default())?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Draws a PNG logo a few times: once as-is, once scaled up and once tinted.
+//!
+//! All three share the same `Arc`, so the image is only uploaded to the GPU
+//! once.
+
+use std::sync::Arc;
+
+use euclid::default::{Box2D, Point2D};
+use metallic::{
+    primitives::{Brush, Image},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+const LOGO: &[u8] = include_bytes!("../assets/logo.png");
+
+#[derive(Default)]
+struct Logo(Option<RenderingEngine>);
+
+impl ApplicationHandler for Logo {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let logo = Arc::new(image::load_from_memory(LOGO).expect("Error decoding the logo"));
+        let rect =
+            |x, y, length| Box2D::new(Point2D::new(x, y), Point2D::new(x + length, y + length));
+        rendering_engine.add_object(
+            Image::new(logo.clone(), rect(20.0, 20.0, 64.0)).fill(Brush::Solid(Color::WHITE)),
+        );
+        rendering_engine.add_object(
+            Image::new(logo.clone(), rect(104.0, 20.0, 128.0)).fill(Brush::Solid(Color::WHITE)),
+        );
+        let tint = Color {
+            r: 1.0,
+            g: 0.5,
+            b: 0.2,
+            a: 0.8,
+        };
+        rendering_engine
+            .add_object(Image::new(logo, rect(252.0, 20.0, 64.0)).fill(Brush::Solid(tint)));
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                rendering_engine.render().expect("Error rendering the logo")
+            }
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Logo::default())?;
+    Ok(())
+}
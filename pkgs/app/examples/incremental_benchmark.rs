@@ -0,0 +1,70 @@
+//! Benchmarks a scene of 5,000 squares in which a single square is recolored
+//! every frame, and prints the report.
+//!
+//! Unchanged squares are neither re-tessellated nor re-uploaded, so only a
+//! few bytes of geometry should be written to the GPU per frame. Run with
+//! `--features unorm8-colors` to compare against vertices with byte colors,
+//! which are half the size.
+
+use euclid::default::{Point2D, Size2D};
+use metallic::{
+    primitives::{Brush, ShapeBuilder},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+const COLUMNS: usize = 100;
+const ROWS: usize = 50;
+const FRAMES: usize = 500;
+
+#[derive(Default)]
+struct Benchmark;
+
+impl ApplicationHandler for Benchmark {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let handles = (0..ROWS)
+            .flat_map(|row| (0..COLUMNS).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let origin = Point2D::new(column as f32 * 8.0, row as f32 * 8.0);
+                rendering_engine.add_object(
+                    ShapeBuilder::new()
+                        .rect(origin, Size2D::new(6.0, 6.0))
+                        .fill(Brush::Solid(Color::WHITE)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let report = rendering_engine
+            .benchmark_with(FRAMES, |rendering_engine, frame| {
+                let color = match (frame / handles.len()) % 2 {
+                    0 => Color::RED,
+                    _ => Color::WHITE,
+                };
+                rendering_engine.set_color(handles[frame % handles.len()], color);
+            })
+            .expect("Error running the benchmark");
+        println!("{report:#?}");
+        println!(
+            "{} bytes of geometry uploaded per frame",
+            report.uploaded_bytes / report.frames.max(1) as u64
+        );
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Benchmark)?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Scales up a tiny pixel-art sprite, once with nearest filtering and once
+//! with (the default) linear filtering.
+//!
+//! Nearest filtering keeps every pixel a crisp square, while linear filtering
+//! blurs them into each other.
+
+use std::sync::Arc;
+
+use euclid::default::{Box2D, Point2D};
+use image::{DynamicImage, Rgba, RgbaImage};
+use metallic::{
+    primitives::{Brush, Image},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::{Color, FilterMode};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+/// An 8x8 smiley, one row per line: `#` is ink, `o` is the face and `.` is
+/// transparent.
+#[rustfmt::skip]
+const SPRITE: [&str; 8] = [
+    "..####..",
+    ".#oooo#.",
+    "#o#oo#o#",
+    "#oooooo#",
+    "#o#oo#o#",
+    "#oo##oo#",
+    ".#oooo#.",
+    "..####..",
+];
+
+/// How many times larger than the sprite each copy is drawn.
+const SCALE: f32 = 24.0;
+
+fn sprite() -> DynamicImage {
+    let mut image = RgbaImage::new(8, 8);
+    for (y, row) in SPRITE.iter().enumerate() {
+        for (x, pixel) in row.bytes().enumerate() {
+            let color = match pixel {
+                b'#' => Rgba([40, 24, 16, 255]),
+                b'o' => Rgba([255, 208, 64, 255]),
+                _ => Rgba([0, 0, 0, 0]),
+            };
+            image.put_pixel(x as _, y as _, color);
+        }
+    }
+    DynamicImage::ImageRgba8(image)
+}
+
+#[derive(Default)]
+struct PixelArt(Option<RenderingEngine>);
+
+impl ApplicationHandler for PixelArt {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let sprite = Arc::new(sprite());
+        let length = 8.0 * SCALE;
+        let rect = |x| {
+            Box2D::new(
+                Point2D::new(x, 20.0),
+                Point2D::new(x + length, 20.0 + length),
+            )
+        };
+        rendering_engine.add_object(
+            Image::new(sprite.clone(), rect(20.0))
+                .with_filter_mode(FilterMode::Nearest)
+                .fill(Brush::Solid(Color::WHITE)),
+        );
+        rendering_engine
+            .add_object(Image::new(sprite, rect(40.0 + length)).fill(Brush::Solid(Color::WHITE)));
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the sprite"),
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut PixelArt::default())?;
+    Ok(())
+}
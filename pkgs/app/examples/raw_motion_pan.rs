@@ -0,0 +1,139 @@
+//! Pans a grid of shapes by dragging with the middle mouse button.
+//!
+//! While dragging, the cursor is grabbed and the camera follows the raw mouse
+//! motion instead of the cursor's position, so the drag never gets stuck at
+//! the edge of the window (or of the screen).
+
+use euclid::default::Point2D;
+use metallic::{
+    primitives::{Brush, ShapeBuilder},
+    rendering_engine::{io::IoEvent, RenderingEngine},
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::{DeviceEvent, DeviceId, ElementState, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::{CursorGrabMode, WindowId},
+};
+
+/// The distance (in pixels) between the centers of neighbouring dots.
+const SPACING: f32 = 48.0;
+
+#[derive(Default)]
+struct Pan {
+    rendering_engine: Option<RenderingEngine>,
+    /// Where the camera's top-left corner is, in scene coordinates.
+    camera: Point2D<f32>,
+    size: PhysicalSize<u32>,
+    panning: bool,
+}
+
+impl ApplicationHandler for Pan {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        // The scene is built once the window reports its size.
+        self.rendering_engine = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.rendering_engine.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.rendering_engine = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                self.size = new_size;
+                build_scene(rendering_engine, self.camera, self.size);
+                rendering_engine.redraw();
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Middle,
+                ..
+            } => {
+                self.panning = state == ElementState::Pressed;
+                let mode = match self.panning {
+                    true => CursorGrabMode::Locked,
+                    false => CursorGrabMode::None,
+                };
+                // Not every platform can lock the cursor; confining it to the
+                // window is the next best thing.
+                if rendering_engine.set_cursor_grab(mode).is_err() && self.panning {
+                    let _ = rendering_engine.set_cursor_grab(CursorGrabMode::Confined);
+                };
+                // Drop whatever moved before the drag started.
+                rendering_engine.take_mouse_motion();
+            }
+            WindowEvent::RedrawRequested => {
+                rendering_engine.render().expect("Error rendering the grid")
+            }
+            _ => (),
+        };
+    }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        let Some(rendering_engine) = self.rendering_engine.as_mut() else {
+            return;
+        };
+        let Some(io_event) = IoEvent::from_device_event(&event) else {
+            return;
+        };
+        rendering_engine.register_io_event(io_event);
+        if !self.panning {
+            return;
+        };
+        let (x, y) = rendering_engine.take_mouse_motion();
+        // Dragging moves the scene along with the mouse, i.e., the camera the
+        // other way.
+        self.camera.x -= x as f32;
+        self.camera.y -= y as f32;
+        build_scene(rendering_engine, self.camera, self.size);
+        rendering_engine.redraw();
+    }
+}
+
+/// Rebuilds the scene as seen through a `size`d camera at `camera`: an
+/// endless grid of dots, with a red dot at the origin.
+fn build_scene(
+    rendering_engine: &mut RenderingEngine,
+    camera: Point2D<f32>,
+    size: PhysicalSize<u32>,
+) {
+    rendering_engine.clear();
+    let first = |camera: f32| (camera / SPACING).floor() as i32;
+    let last = |camera: f32, length: f32| ((camera + length) / SPACING).ceil() as i32;
+    for row in first(camera.y)..=last(camera.y, size.height as f32) {
+        for column in first(camera.x)..=last(camera.x, size.width as f32) {
+            let center = Point2D::new(
+                column as f32 * SPACING - camera.x,
+                row as f32 * SPACING - camera.y,
+            );
+            let color = match (column, row) {
+                (0, 0) => Color::RED,
+                _ => Color::WHITE,
+            };
+            rendering_engine.add_object(
+                ShapeBuilder::new()
+                    .circle(center, 6.0)
+                    .fill(Brush::Solid(color)),
+            );
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Pan::default())?;
+    Ok(())
+}
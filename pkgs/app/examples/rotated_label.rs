@@ -0,0 +1,87 @@
+//! Labels a chart's vertical axis with text which reads from bottom to top.
+//!
+//! The label is rotated a quarter turn counter-clockwise around the start of
+//! its baseline, which sits just to the left of the axis, and is centered
+//! along it using its measured (unrotated) width.
+
+use std::f32::consts::FRAC_PI_2;
+
+use euclid::default::Point2D;
+use metallic::{
+    primitives::{Brush, ShapeBuilder, Text, TextAnchor},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+/// Where the axis starts (at the top) and ends (at the bottom).
+const AXIS_TOP: Point2D<f32> = Point2D::new(80.0, 60.0);
+const AXIS_BOTTOM: Point2D<f32> = Point2D::new(80.0, 360.0);
+
+/// How far left of the axis the label's baseline is.
+const LABEL_GAP: f32 = 16.0;
+
+#[derive(Default)]
+struct RotatedLabel(Option<RenderingEngine>);
+
+impl ApplicationHandler for RotatedLabel {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let label = Text::new("Revenue (USD)")
+            .with_font_size(20.0)
+            .with_line_height(24.0)
+            .with_anchor(TextAnchor::Baseline);
+        let width = rendering_engine.measure_text(&label).width;
+        let middle = (AXIS_TOP.y + AXIS_BOTTOM.y) / 2.0;
+        let label = label
+            .with_position(Point2D::new(AXIS_TOP.x - LABEL_GAP, middle + width / 2.0))
+            .with_angle(-FRAC_PI_2);
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .move_to(AXIS_TOP)
+                .line_to(AXIS_BOTTOM)
+                .line_to(Point2D::new(AXIS_BOTTOM.x + 1.0, AXIS_BOTTOM.y))
+                .line_to(Point2D::new(AXIS_TOP.x + 1.0, AXIS_TOP.y))
+                .close()
+                .fill(Brush::Solid(Color::WHITE)),
+        );
+        rendering_engine.add_object(label.fill(Brush::Solid(Color::WHITE)));
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the label"),
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut RotatedLabel::default())?;
+    Ok(())
+}
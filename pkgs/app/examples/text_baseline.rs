@@ -0,0 +1,88 @@
+//! Places 12px and 24px text side by side on a shared baseline.
+//!
+//! Both pieces of text are anchored at their baselines (instead of at their
+//! top-left corners), so they sit on the same line despite their different
+//! sizes. The baseline itself is drawn as a thin line underneath.
+
+use euclid::default::Point2D;
+use metallic::{
+    primitives::{Brush, ShapeBuilder, Text, TextAnchor},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+/// Where the baseline is, and where the first piece of text starts.
+const ORIGIN: Point2D<f32> = Point2D::new(40.0, 120.0);
+
+#[derive(Default)]
+struct Baseline(Option<RenderingEngine>);
+
+impl ApplicationHandler for Baseline {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::BLACK))
+            .expect("Error creating the rendering engine");
+        let text = |content: &str, font_size: f32, position| {
+            Text::new(content)
+                .with_font_size(font_size)
+                .with_line_height(font_size * 1.25)
+                .with_position(position)
+                .with_anchor(TextAnchor::Baseline)
+        };
+        let small = text("Small text, ", 12.0, ORIGIN);
+        let width = rendering_engine.measure_text(&small).width;
+        let large = text(
+            "large text.",
+            24.0,
+            Point2D::new(ORIGIN.x + width, ORIGIN.y),
+        );
+        let end = ORIGIN.x + width + rendering_engine.measure_text(&large).width;
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .move_to(ORIGIN)
+                .line_to(Point2D::new(end, ORIGIN.y))
+                .line_to(Point2D::new(end, ORIGIN.y + 1.0))
+                .line_to(Point2D::new(ORIGIN.x, ORIGIN.y + 1.0))
+                .close()
+                .fill(Brush::Solid(Color::RED)),
+        );
+        rendering_engine.add_object(small.fill(Brush::Solid(Color::WHITE)));
+        rendering_engine.add_object(large.fill(Brush::Solid(Color::WHITE)));
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                rendering_engine.render().expect("Error rendering the text")
+            }
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Baseline::default())?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Draws a few shapes into a see-through window, so that only the shapes
+//! themselves are visible over the desktop.
+//!
+//! Whether the window is actually transparent depends on the platform (and,
+//! on Linux, on a compositor running).
+
+use euclid::default::{Point2D, Size2D};
+use metallic::{
+    primitives::{Brush, ShapeBuilder, Text},
+    rendering_engine::RenderingEngine,
+};
+use pollster::block_on;
+use wgpu::Color;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::WindowId,
+};
+
+#[derive(Default)]
+struct Overlay(Option<RenderingEngine>);
+
+impl ApplicationHandler for Overlay {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let mut rendering_engine = block_on(RenderingEngine::new(event_loop, Color::TRANSPARENT))
+            .expect("Error creating the rendering engine");
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .rect(Point2D::new(20.0, 20.0), Size2D::new(200.0, 60.0))
+                .fill(Brush::Solid(Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 0.6,
+                })),
+        );
+        rendering_engine.add_object(
+            ShapeBuilder::new()
+                .circle(Point2D::new(300.0, 50.0), 30.0)
+                .fill(Brush::Solid(Color::RED)),
+        );
+        rendering_engine.add_object(
+            Text::new("Overlay")
+                .with_position(Point2D::new(36.0, 36.0))
+                .with_font_size(24.0)
+                .with_line_height(28.0)
+                .fill(Brush::Solid(Color::WHITE)),
+        );
+        self.0 = Some(rendering_engine);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(rendering_engine) = self.0.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                self.0 = None;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                rendering_engine
+                    .resize(new_size)
+                    .expect("Error resizing the surface");
+                rendering_engine.redraw();
+            }
+            WindowEvent::RedrawRequested => rendering_engine
+                .render()
+                .expect("Error rendering the overlay"),
+            _ => (),
+        };
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Overlay::default())?;
+    Ok(())
+}
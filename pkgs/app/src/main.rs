@@ -1,6 +1,13 @@
 use euclid::{default::Point2D, Box2D, Size2D};
-use lyon::path::{Path, Winding};
-use metallic::{primitives::Shape, rendering_engine::RenderingEngine};
+use glyphon::Shaping;
+use lyon::{
+    path::{Path, Winding},
+    tessellation::FillRule,
+};
+use metallic::{
+    primitives::{Brush, CoordinateSpace, DrawStyle, Object, Shape, Text},
+    rendering_engine::{io::IoEvent, RenderingEngine},
+};
 use pollster::block_on;
 use wgpu::Color;
 use winit::{
@@ -25,11 +32,23 @@ impl ApplicationHandler for App {
             panic!("Error handling window event: {:?}", error);
         }
     }
+
+    fn about_to_wait(&mut self, _: &ActiveEventLoop) {
+        if let Some(rendering_engine) = self.0.as_mut() {
+            rendering_engine.about_to_wait();
+        };
+    }
+
+    fn memory_warning(&mut self, _: &ActiveEventLoop) {
+        if let Some(rendering_engine) = self.0.as_mut() {
+            rendering_engine.memory_warning();
+        };
+    }
 }
 
 async fn resume(app: &mut App, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
     let mut rendering_engine = RenderingEngine::new(event_loop, Color::BLACK).await?;
-    build_initial_scene(&mut rendering_engine);
+    build_initial_scene(&mut rendering_engine)?;
     app.0 = Some(rendering_engine);
     Ok(())
 }
@@ -40,15 +59,25 @@ fn handle_window_event(
     event: WindowEvent,
 ) -> anyhow::Result<()> {
     if let Some(rendering_engine) = app.0.as_mut() {
+        // Cursor moves are frequent; only redraw once they actually hit
+        // something different.
+        let io_event = IoEvent::from_window_event(&event);
+        if io_event.is_some_and(|io_event| rendering_engine.register_io_event(io_event)) {
+            rendering_engine.redraw();
+        };
         match event {
             WindowEvent::CloseRequested | WindowEvent::Destroyed => {
                 app.0 = None;
                 event_loop.exit();
             }
             WindowEvent::Resized(new_size) => {
-                rendering_engine.resize(new_size);
+                rendering_engine.resize(new_size)?;
                 rendering_engine.redraw();
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                rendering_engine.set_scale_factor(scale_factor)?;
+            }
+            WindowEvent::Occluded(occluded) => rendering_engine.set_occluded(occluded),
             WindowEvent::RedrawRequested => rendering_engine.render()?,
             _ => (),
         };
@@ -56,32 +85,53 @@ fn handle_window_event(
     Ok(())
 }
 
-fn build_initial_scene(rendering_engine: &mut RenderingEngine) {
+fn build_initial_scene(rendering_engine: &mut RenderingEngine) -> anyhow::Result<()> {
     {
-        rendering_engine.push_layer();
-        rendering_engine.add_shape({
+        rendering_engine.push_layer()?;
+        rendering_engine.add_object({
             let mut builder = Path::builder();
             builder.add_rectangle(
                 &Box2D::from_origin_and_size(Point2D::new(0.0, 20.0), Size2D::new(150.0, 150.0)),
                 Winding::Positive,
             );
             let path = builder.build();
-            Shape {
-                path,
-                color: Color::RED,
-            }
+            Object::shape(
+                Shape {
+                    path,
+                    coordinate_space: CoordinateSpace::Absolute,
+                    fill_rule: FillRule::NonZero,
+                    style: DrawStyle::Fill,
+                    on_mouse_input: None,
+                },
+                Brush::Solid(Color::RED),
+            )
         });
         rendering_engine.pop_layer();
     }
-    rendering_engine.add_shape({
+    rendering_engine.add_object({
         let mut builder = Path::builder();
         builder.add_circle(Point2D::new(100.0, 100.0), 100.0, Winding::Positive);
         let path = builder.build();
-        Shape {
-            path,
-            color: Color::WHITE,
-        }
+        Object::shape(
+            Shape {
+                path,
+                coordinate_space: CoordinateSpace::Absolute,
+                fill_rule: FillRule::NonZero,
+                style: DrawStyle::Fill,
+                on_mouse_input: None,
+            },
+            Brush::Solid(Color::WHITE),
+        )
     });
+    rendering_engine.add_object(
+        Text::new("Hello, metallic!")
+            .with_position(Point2D::new(220.0, 40.0))
+            .with_font_size(32.0)
+            .with_line_height(40.0)
+            .with_shaping(Shaping::Advanced)
+            .fill(Brush::Solid(Color::WHITE)),
+    );
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
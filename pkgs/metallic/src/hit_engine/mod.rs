@@ -0,0 +1,421 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+
+use euclid::{
+    default::{Box2D, Point2D, Rotation2D, Vector2D},
+    Angle,
+};
+use rstar::{
+    primitives::{GeomWithData, Rectangle},
+    RTree, AABB,
+};
+use uuid::Uuid;
+
+/// An axis-aligned box which can be hit-tested, identified by `id`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub id: Uuid,
+    pub bounds: Box2D<f32>,
+    /// Boxes on higher layers are in front of those on lower ones (see
+    /// [`HitEngine::hit_search_topmost`]).
+    pub layer: usize,
+}
+
+/// A box which is rotated by `angle` around its `center`, identified by `id`.
+///
+/// Hit-testing one of these against its axis-aligned [`bounds`] would also hit
+/// the empty corners around it, so the [`HitEngine`] keeps the oriented box
+/// around to check candidates against precisely.
+///
+/// [`bounds`]: OrientedBox::bounds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientedBox {
+    pub id: Uuid,
+    pub center: Point2D<f32>,
+    /// Half of the box's width and height, before it's rotated.
+    pub half_extents: Vector2D<f32>,
+    /// Clockwise (in screen coordinates, where y points down).
+    pub angle: Angle<f32>,
+    /// See [`BoundingBox::layer`].
+    pub layer: usize,
+}
+
+impl OrientedBox {
+    /// Returns the smallest axis-aligned box which contains this one.
+    pub fn bounds(&self) -> Box2D<f32> {
+        let (sin, cos) = self.angle.sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let Vector2D { x, y, .. } = self.half_extents;
+        let extents = Vector2D::new(cos * x + sin * y, sin * x + cos * y);
+        Box2D::new(self.center - extents, self.center + extents)
+    }
+
+    /// Returns whether `point` is within `tolerance` of the box (edges
+    /// included).
+    ///
+    /// Unlike the axis-aligned boxes' tolerance, this one rounds the box's
+    /// corners off, which keeps it within the tolerance around its
+    /// [`bounds`](OrientedBox::bounds).
+    pub fn contains(&self, point: Point2D<f32>, tolerance: f32) -> bool {
+        let local = Rotation2D::new(-self.angle).transform_vector(point - self.center);
+        let outside = Vector2D::new(
+            (local.x.abs() - self.half_extents.x).max(0.0),
+            (local.y.abs() - self.half_extents.y).max(0.0),
+        );
+        outside.length() <= tolerance.max(0.0)
+    }
+}
+
+/// The data structure which a [`HitEngine`] uses to look boxes up.
+///
+/// Both backends return exactly the same results; they only differ in
+/// performance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HitBackend {
+    /// The start and end coordinates of every box are kept in four lists (one
+    /// per box edge), each sorted by coordinate. A query binary-searches each
+    /// list for the boxes on the correct side of that edge, and then
+    /// intersects the four resulting sets of ids.
+    ///
+    /// Cheap to update, but queries slow down as more boxes overlap along
+    /// either axis.
+    #[default]
+    SortedLists,
+    /// An R-tree, which only visits the boxes near the queried point.
+    ///
+    /// Better suited to large scenes with many overlapping boxes.
+    RTree,
+}
+
+/// Finds the boxes which contain a given point.
+#[derive(Debug, Default)]
+pub struct HitEngine {
+    boxes: HashMap<Uuid, Box2D<f32>>,
+    /// The boxes which were inserted as [`OrientedBox`]es; `boxes` (and the
+    /// index) only hold their axis-aligned bounds.
+    oriented_boxes: HashMap<Uuid, OrientedBox>,
+    /// Where every box is in the front-to-back order.
+    depths: HashMap<Uuid, Depth>,
+    /// The insertion order which the next inserted box gets.
+    next_order: u64,
+    index: Index,
+}
+
+/// A box's layer, followed by when it was inserted: boxes compare greater
+/// than the ones behind them.
+type Depth = (usize, u64);
+
+#[derive(Debug)]
+enum Index {
+    SortedLists(SortedLists),
+    RTree(RTree<RTreeEntry>),
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Self::SortedLists(SortedLists::default())
+    }
+}
+
+type RTreeEntry = GeomWithData<Rectangle<[f32; 2]>, Uuid>;
+
+impl HitEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_backend(backend: HitBackend) -> Self {
+        Self::from_boxes_with_backend([], backend)
+    }
+
+    pub fn backend(&self) -> HitBackend {
+        match self.index {
+            Index::SortedLists(_) => HitBackend::SortedLists,
+            Index::RTree(_) => HitBackend::RTree,
+        }
+    }
+
+    /// Builds an engine out of many boxes at once.
+    ///
+    /// Rather than inserting the boxes one by one (which shifts each list on
+    /// every insert), all entries are collected first and each list is then
+    /// sorted once. If several boxes share an id, the last one wins. Within a
+    /// layer, later boxes are in front of earlier ones.
+    pub fn from_boxes<I>(boxes: I) -> Self
+    where
+        I: IntoIterator<Item = BoundingBox>,
+    {
+        Self::from_boxes_with_backend(boxes, HitBackend::default())
+    }
+
+    /// Like [`HitEngine::from_boxes`], but with the given backend (which, in
+    /// the case of [`HitBackend::RTree`], is bulk-loaded).
+    pub fn from_boxes_with_backend<I>(boxes: I, backend: HitBackend) -> Self
+    where
+        I: IntoIterator<Item = BoundingBox>,
+    {
+        let mut depths = HashMap::new();
+        let mut next_order = 0;
+        let boxes = boxes
+            .into_iter()
+            .map(|BoundingBox { id, bounds, layer }| {
+                depths.insert(id, (layer, next_order));
+                next_order += 1;
+                (id, bounds)
+            })
+            .collect::<HashMap<_, _>>();
+        let index = match backend {
+            HitBackend::SortedLists => Index::SortedLists(SortedLists::from_boxes(&boxes)),
+            HitBackend::RTree => Index::RTree(RTree::bulk_load(
+                boxes
+                    .iter()
+                    .map(|(&id, &bounds)| to_rtree_entry(id, bounds))
+                    .collect(),
+            )),
+        };
+        Self {
+            boxes,
+            oriented_boxes: HashMap::new(),
+            depths,
+            next_order,
+            index,
+        }
+    }
+
+    /// Inserts a box, replacing any box which was already inserted with the
+    /// same id. It goes in front of every other box on its layer.
+    pub fn insert(&mut self, bounding_box: BoundingBox) {
+        let BoundingBox { id, bounds, layer } = bounding_box;
+        self.remove(id);
+        self.depths.insert(id, (layer, self.next_order));
+        self.next_order += 1;
+        match &mut self.index {
+            Index::SortedLists(sorted_lists) => sorted_lists.insert(id, bounds),
+            Index::RTree(rtree) => rtree.insert(to_rtree_entry(id, bounds)),
+        };
+        self.boxes.insert(id, bounds);
+    }
+
+    /// Inserts a rotated box, replacing any box which was already inserted
+    /// with the same id.
+    ///
+    /// Its axis-aligned bounds are indexed like any other box's, and every
+    /// point which hits them is then checked against the rotated box itself.
+    pub fn insert_oriented(&mut self, oriented_box: OrientedBox) {
+        self.insert(BoundingBox {
+            id: oriented_box.id,
+            bounds: oriented_box.bounds(),
+            layer: oriented_box.layer,
+        });
+        self.oriented_boxes.insert(oriented_box.id, oriented_box);
+    }
+
+    /// Removes the box with the given id, returning its bounds (or `None` if
+    /// there was no such box).
+    pub fn remove(&mut self, id: Uuid) -> Option<Box2D<f32>> {
+        let bounds = self.boxes.remove(&id)?;
+        self.oriented_boxes.remove(&id);
+        self.depths.remove(&id);
+        match &mut self.index {
+            Index::SortedLists(sorted_lists) => sorted_lists.remove(id, bounds),
+            Index::RTree(rtree) => {
+                rtree.remove(&to_rtree_entry(id, bounds));
+            }
+        };
+        Some(bounds)
+    }
+
+    /// Moves the box with the given id to `bounds`, returning its previous
+    /// bounds.
+    ///
+    /// A box which was inserted as an [`OrientedBox`] becomes an axis-aligned
+    /// one. Its place in the front-to-back order is kept.
+    ///
+    /// With [`HitBackend::SortedLists`], only the entries of edges which
+    /// actually moved are repositioned. If there is no box with the given id,
+    /// nothing happens and `None` is returned.
+    pub fn update(&mut self, id: Uuid, bounds: Box2D<f32>) -> Option<Box2D<f32>> {
+        let previous = self.boxes.get_mut(&id)?;
+        let previous = std::mem::replace(previous, bounds);
+        self.oriented_boxes.remove(&id);
+        match &mut self.index {
+            Index::SortedLists(sorted_lists) => sorted_lists.update(id, previous, bounds),
+            Index::RTree(rtree) => {
+                rtree.remove(&to_rtree_entry(id, previous));
+                rtree.insert(to_rtree_entry(id, bounds));
+            }
+        };
+        Some(previous)
+    }
+
+    /// Removes every box, keeping the current backend.
+    pub fn clear(&mut self) {
+        *self = Self::with_backend(self.backend());
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// Returns the ids of every box which contains `point` (edges included).
+    pub fn hit_search(&self, point: Point2D<f32>) -> HashSet<Uuid> {
+        self.hit_search_with_tolerance(point, 0.0)
+    }
+
+    /// Like [`HitEngine::hit_search`], but with every box expanded by
+    /// `tolerance` on all sides first, so that points slightly outside of a
+    /// (e.g., thin) box still hit it.
+    pub fn hit_search_with_tolerance(&self, point: Point2D<f32>, tolerance: f32) -> HashSet<Uuid> {
+        let tolerance = tolerance.max(0.0);
+        let mut hits = match &self.index {
+            Index::SortedLists(sorted_lists) => sorted_lists.hit_search(point, tolerance),
+            Index::RTree(rtree) => rtree
+                .locate_in_envelope_intersecting(&AABB::from_corners(
+                    (point - Vector2D::splat(tolerance)).to_array(),
+                    (point + Vector2D::splat(tolerance)).to_array(),
+                ))
+                .map(|entry| entry.data)
+                .collect(),
+        };
+        hits.retain(|id| match self.oriented_boxes.get(id) {
+            Some(oriented_box) => oriented_box.contains(point, tolerance),
+            None => true,
+        });
+        hits
+    }
+
+    /// Returns the id of the frontmost box which contains `point` (edges
+    /// included), if any.
+    ///
+    /// Boxes on higher layers are in front of those on lower ones, and within
+    /// a layer, the most recently inserted box is in front.
+    pub fn hit_search_topmost(&self, point: Point2D<f32>) -> Option<Uuid> {
+        self.hit_search_topmost_with_tolerance(point, 0.0)
+    }
+
+    /// Like [`HitEngine::hit_search_topmost`], but with the tolerance of
+    /// [`HitEngine::hit_search_with_tolerance`].
+    pub fn hit_search_topmost_with_tolerance(
+        &self,
+        point: Point2D<f32>,
+        tolerance: f32,
+    ) -> Option<Uuid> {
+        self.hit_search_with_tolerance(point, tolerance)
+            .into_iter()
+            .max_by_key(|id| self.depths[id])
+    }
+
+    /// Returns the ids of every box which contains `point`, front to back
+    /// (see [`HitEngine::hit_search_topmost`]).
+    pub fn hit_search_ordered(&self, point: Point2D<f32>) -> Vec<Uuid> {
+        let mut hits = self.hit_search(point).into_iter().collect::<Vec<_>>();
+        hits.sort_unstable_by_key(|id| std::cmp::Reverse(self.depths[id]));
+        hits
+    }
+}
+
+fn to_rtree_entry(id: Uuid, bounds: Box2D<f32>) -> RTreeEntry {
+    GeomWithData::new(
+        Rectangle::from_corners(bounds.min.to_array(), bounds.max.to_array()),
+        id,
+    )
+}
+
+#[derive(Debug, Default)]
+struct SortedLists {
+    x_start: Vec<(f32, Uuid)>,
+    x_end: Vec<(f32, Uuid)>,
+    y_start: Vec<(f32, Uuid)>,
+    y_end: Vec<(f32, Uuid)>,
+}
+
+impl SortedLists {
+    fn from_boxes(boxes: &HashMap<Uuid, Box2D<f32>>) -> Self {
+        let list = |coordinate: fn(&Box2D<f32>) -> f32| {
+            let mut list = boxes
+                .iter()
+                .map(|(&id, bounds)| (coordinate(bounds), id))
+                .collect::<Vec<_>>();
+            list.sort_unstable_by(|(a, _), (b, _)| a.total_cmp(b));
+            list
+        };
+        Self {
+            x_start: list(|bounds| bounds.min.x),
+            x_end: list(|bounds| bounds.max.x),
+            y_start: list(|bounds| bounds.min.y),
+            y_end: list(|bounds| bounds.max.y),
+        }
+    }
+
+    fn lists(&mut self, bounds: Box2D<f32>) -> [(&mut Vec<(f32, Uuid)>, f32); 4] {
+        [
+            (&mut self.x_start, bounds.min.x),
+            (&mut self.x_end, bounds.max.x),
+            (&mut self.y_start, bounds.min.y),
+            (&mut self.y_end, bounds.max.y),
+        ]
+    }
+
+    fn insert(&mut self, id: Uuid, bounds: Box2D<f32>) {
+        for (list, coordinate) in self.lists(bounds) {
+            insert_sorted(list, coordinate, id);
+        }
+    }
+
+    fn remove(&mut self, id: Uuid, bounds: Box2D<f32>) {
+        for (list, coordinate) in self.lists(bounds) {
+            remove_sorted(list, coordinate, id);
+        }
+    }
+
+    fn update(&mut self, id: Uuid, previous: Box2D<f32>, bounds: Box2D<f32>) {
+        let targets = self.lists(bounds).map(|(_, coordinate)| coordinate);
+        for ((list, from), to) in self.lists(previous).into_iter().zip(targets) {
+            if from != to {
+                remove_sorted(list, from, id);
+                insert_sorted(list, to, id);
+            };
+        }
+    }
+
+    fn hit_search(&self, point: Point2D<f32>, tolerance: f32) -> HashSet<Uuid> {
+        let (min, max) = (point.x - tolerance, point.x + tolerance);
+        let started_x = &self.x_start[..self.x_start.partition_point(|&(x, _)| x <= max)];
+        let not_ended_x = &self.x_end[self.x_end.partition_point(|&(x, _)| x < min)..];
+        let (min, max) = (point.y - tolerance, point.y + tolerance);
+        let started_y = &self.y_start[..self.y_start.partition_point(|&(y, _)| y <= max)];
+        let not_ended_y = &self.y_end[self.y_end.partition_point(|&(y, _)| y < min)..];
+        let mut candidates = [started_x, not_ended_x, started_y, not_ended_y];
+        candidates.sort_unstable_by_key(|candidates| candidates.len());
+        let [smallest, rest @ ..] = candidates;
+        let rest =
+            rest.map(|candidates| candidates.iter().map(|&(_, id)| id).collect::<HashSet<_>>());
+        smallest
+            .iter()
+            .map(|&(_, id)| id)
+            .filter(|id| rest.iter().all(|candidates| candidates.contains(id)))
+            .collect()
+    }
+}
+
+fn insert_sorted(list: &mut Vec<(f32, Uuid)>, coordinate: f32, id: Uuid) {
+    let index = list.partition_point(|&(curr_coordinate, _)| curr_coordinate <= coordinate);
+    list.insert(index, (coordinate, id));
+}
+
+fn remove_sorted(list: &mut Vec<(f32, Uuid)>, coordinate: f32, id: Uuid) {
+    let start = list.partition_point(|&(curr_coordinate, _)| curr_coordinate < coordinate);
+    if let Some(offset) = list[start..]
+        .iter()
+        .take_while(|&&(curr_coordinate, _)| curr_coordinate == coordinate)
+        .position(|&(_, curr_id)| curr_id == id)
+    {
+        list.remove(start + offset);
+    };
+}
@@ -0,0 +1,243 @@
+use super::*;
+
+fn bounding_box(x: f32, y: f32, width: f32, height: f32) -> BoundingBox {
+    BoundingBox {
+        id: Uuid::new_v4(),
+        bounds: Box2D::new(Point2D::new(x, y), Point2D::new(x + width, y + height)),
+        layer: 0,
+    }
+}
+
+/// Deterministically generates `count` pseudo-random boxes within a 1000x1000
+/// area.
+fn random_boxes(count: usize) -> Vec<BoundingBox> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % 1000) as f32
+    };
+    (0..count)
+        .map(|_| bounding_box(next(), next(), next() / 10.0, next() / 10.0))
+        .collect()
+}
+
+fn hits(hit_engine: &HitEngine, x: f32, y: f32) -> HashSet<Uuid> {
+    hit_engine.hit_search(Point2D::new(x, y))
+}
+
+#[test]
+fn test_hit_search() {
+    let mut hit_engine = HitEngine::new();
+    let a = bounding_box(0.0, 0.0, 20.0, 20.0);
+    let b = bounding_box(10.0, 10.0, 20.0, 20.0);
+    hit_engine.insert(a);
+    hit_engine.insert(b);
+    assert_eq!(hits(&hit_engine, 5.0, 5.0), HashSet::from([a.id]));
+    assert_eq!(hits(&hit_engine, 15.0, 15.0), HashSet::from([a.id, b.id]));
+    assert_eq!(hits(&hit_engine, 20.0, 20.0), HashSet::from([a.id, b.id]));
+    assert_eq!(hits(&hit_engine, 25.0, 5.0), HashSet::new());
+    assert_eq!(hits(&hit_engine, 40.0, 40.0), HashSet::new());
+
+    hit_engine.clear();
+    assert!(hit_engine.is_empty());
+    assert_eq!(hits(&hit_engine, 5.0, 5.0), HashSet::new());
+}
+
+#[test]
+fn test_hit_search_with_tolerance() {
+    for backend in [HitBackend::SortedLists, HitBackend::RTree] {
+        let mut hit_engine = HitEngine::with_backend(backend);
+        let a = bounding_box(10.0, 10.0, 20.0, 20.0);
+        hit_engine.insert(a);
+
+        let hits =
+            |x, y, tolerance| hit_engine.hit_search_with_tolerance(Point2D::new(x, y), tolerance);
+        assert!(hits(33.0, 20.0, 0.0).is_empty());
+        assert_eq!(hits(33.0, 20.0, 5.0), HashSet::from([a.id]));
+        assert_eq!(hits(7.0, 7.0, 5.0), HashSet::from([a.id]));
+        assert!(hits(36.0, 20.0, 5.0).is_empty());
+        assert!(hits(20.0, 20.0, -5.0).contains(&a.id));
+    }
+}
+
+#[test]
+fn test_remove() {
+    let mut hit_engine = HitEngine::new();
+    let a = bounding_box(0.0, 0.0, 20.0, 20.0);
+    let b = bounding_box(0.0, 0.0, 20.0, 20.0);
+    hit_engine.insert(a);
+    hit_engine.insert(b);
+
+    assert_eq!(hit_engine.remove(a.id), Some(a.bounds));
+    assert_eq!(hit_engine.remove(a.id), None);
+    assert_eq!(hit_engine.len(), 1);
+    assert_eq!(hits(&hit_engine, 10.0, 10.0), HashSet::from([b.id]));
+    let Index::SortedLists(sorted_lists) = &hit_engine.index else {
+        unreachable!();
+    };
+    for list in [
+        &sorted_lists.x_start,
+        &sorted_lists.x_end,
+        &sorted_lists.y_start,
+        &sorted_lists.y_end,
+    ] {
+        assert_eq!(list.len(), 1);
+    }
+}
+
+#[test]
+fn test_update() {
+    let mut hit_engine = HitEngine::new();
+    let a = bounding_box(0.0, 0.0, 20.0, 20.0);
+    let b = bounding_box(50.0, 0.0, 20.0, 20.0);
+    hit_engine.insert(a);
+    hit_engine.insert(b);
+
+    let moved = Box2D::new(Point2D::new(50.0, 10.0), Point2D::new(60.0, 30.0));
+    assert_eq!(hit_engine.update(a.id, moved), Some(a.bounds));
+    assert_eq!(hits(&hit_engine, 5.0, 5.0), HashSet::new());
+    assert_eq!(hits(&hit_engine, 55.0, 15.0), HashSet::from([a.id, b.id]));
+    assert_eq!(hits(&hit_engine, 55.0, 25.0), HashSet::from([a.id]));
+    assert_eq!(hits(&hit_engine, 55.0, 5.0), HashSet::from([b.id]));
+
+    let unknown = Uuid::new_v4();
+    assert_eq!(hit_engine.update(unknown, moved), None);
+    assert_eq!(hit_engine.len(), 2);
+}
+
+#[test]
+fn test_from_boxes_matches_incremental_inserts() {
+    let boxes = random_boxes(500);
+    let mut incremental = HitEngine::new();
+    boxes
+        .iter()
+        .for_each(|&bounding_box| incremental.insert(bounding_box));
+    let bulk = HitEngine::from_boxes(boxes);
+    assert_eq!(bulk.len(), incremental.len());
+    for x in (0..1_100).step_by(50) {
+        for y in (0..1_100).step_by(50) {
+            let (x, y) = (x as f32, y as f32);
+            assert_eq!(hits(&bulk, x, y), hits(&incremental, x, y));
+        }
+    }
+}
+
+#[test]
+fn test_backends_agree() {
+    let boxes = random_boxes(500);
+    let mut sorted_lists =
+        HitEngine::from_boxes_with_backend(boxes.clone(), HitBackend::SortedLists);
+    let mut rtree = HitEngine::with_backend(HitBackend::RTree);
+    boxes
+        .iter()
+        .for_each(|&bounding_box| rtree.insert(bounding_box));
+    assert_eq!(rtree.backend(), HitBackend::RTree);
+
+    for (index, bounding_box) in boxes.iter().enumerate().step_by(7) {
+        let bounds = bounding_box.bounds.translate([13.0, -5.0].into());
+        for hit_engine in [&mut sorted_lists, &mut rtree] {
+            match index % 2 {
+                0 => hit_engine.remove(bounding_box.id),
+                _ => hit_engine.update(bounding_box.id, bounds),
+            };
+        }
+    }
+    assert_eq!(sorted_lists.len(), rtree.len());
+    for x in (0..1_100).step_by(50) {
+        for y in (0..1_100).step_by(50) {
+            let (x, y) = (x as f32, y as f32);
+            assert_eq!(hits(&sorted_lists, x, y), hits(&rtree, x, y));
+        }
+    }
+
+    rtree.clear();
+    assert!(rtree.is_empty());
+    assert_eq!(rtree.backend(), HitBackend::RTree);
+}
+
+#[test]
+fn test_oriented_boxes_are_hit_precisely() {
+    for backend in [HitBackend::SortedLists, HitBackend::RTree] {
+        let mut hit_engine = HitEngine::with_backend(backend);
+        // A 20x20 square, rotated into a diamond reaching ~14.14 from its
+        // center in every direction.
+        let diamond = OrientedBox {
+            id: Uuid::new_v4(),
+            center: Point2D::new(50.0, 50.0),
+            half_extents: Vector2D::new(10.0, 10.0),
+            angle: Angle::degrees(45.0),
+            layer: 0,
+        };
+        hit_engine.insert_oriented(diamond);
+        let bounds = diamond.bounds();
+        assert!((bounds.min.x - (50.0 - 200.0_f32.sqrt())).abs() < 1e-4);
+
+        // The corners of the axis-aligned bounds are empty.
+        let corner = bounds.min + Vector2D::splat(1.0);
+        assert!(bounds.contains(corner));
+        assert!(hit_engine.hit_search(corner).is_empty());
+        assert_eq!(hits(&hit_engine, 50.0, 50.0), HashSet::from([diamond.id]));
+        assert_eq!(hits(&hit_engine, 50.0, 37.0), HashSet::from([diamond.id]));
+        // ~1.31 away from the diamond's edge.
+        assert!(hit_engine
+            .hit_search_with_tolerance(Point2D::new(42.0, 42.0), 1.0)
+            .is_empty());
+        assert_eq!(
+            hit_engine.hit_search_with_tolerance(Point2D::new(42.0, 42.0), 2.0),
+            HashSet::from([diamond.id])
+        );
+
+        // Moving it by its bounds makes it axis-aligned again.
+        hit_engine.update(diamond.id, bounds);
+        assert_eq!(hit_engine.hit_search(corner), HashSet::from([diamond.id]));
+        hit_engine.insert_oriented(diamond);
+        assert_eq!(hit_engine.remove(diamond.id), Some(bounds));
+        assert!(hit_engine.oriented_boxes.is_empty());
+    }
+}
+
+#[test]
+fn test_hit_search_topmost_prefers_higher_layers() {
+    for backend in [HitBackend::SortedLists, HitBackend::RTree] {
+        let mut hit_engine = HitEngine::with_backend(backend);
+        let front = BoundingBox {
+            layer: 1,
+            ..bounding_box(10.0, 10.0, 20.0, 20.0)
+        };
+        // Inserted last, but on a lower layer.
+        let back = bounding_box(0.0, 0.0, 20.0, 20.0);
+        hit_engine.insert(front);
+        hit_engine.insert(back);
+        let topmost = |x, y| hit_engine.hit_search_topmost(Point2D::new(x, y));
+        assert_eq!(topmost(15.0, 15.0), Some(front.id));
+        assert_eq!(topmost(5.0, 5.0), Some(back.id));
+        assert_eq!(topmost(40.0, 40.0), None);
+        assert_eq!(
+            hit_engine.hit_search_ordered(Point2D::new(15.0, 15.0)),
+            [front.id, back.id]
+        );
+        assert_eq!(
+            hit_engine.hit_search_topmost_with_tolerance(Point2D::new(32.0, 32.0), 5.0),
+            Some(front.id)
+        );
+    }
+}
+
+#[test]
+fn test_hit_search_topmost_prefers_later_boxes_within_a_layer() {
+    let a = bounding_box(0.0, 0.0, 20.0, 20.0);
+    let b = bounding_box(0.0, 0.0, 20.0, 20.0);
+    let mut hit_engine = HitEngine::from_boxes([a, b]);
+    let topmost = |hit_engine: &HitEngine| hit_engine.hit_search_topmost(Point2D::new(5.0, 5.0));
+    assert_eq!(topmost(&hit_engine), Some(b.id));
+
+    // Moving a box keeps its place, while reinserting it brings it forward.
+    hit_engine.update(a.id, a.bounds.translate(Vector2D::new(1.0, 1.0)));
+    assert_eq!(topmost(&hit_engine), Some(b.id));
+    hit_engine.insert(a);
+    assert_eq!(topmost(&hit_engine), Some(a.id));
+    hit_engine.remove(a.id);
+    assert_eq!(topmost(&hit_engine), Some(b.id));
+}
@@ -1,21 +1,55 @@
+pub mod hit_engine;
 pub mod primitives;
 pub mod rendering_engine;
 
+use std::path::PathBuf;
+
+use glyphon::{PrepareError, RenderError};
 use lyon::tessellation::TessellationError;
 use thiserror::Error;
-use wgpu::{CreateSurfaceError, RequestDeviceError, SurfaceError};
-use winit::error::OsError;
+use wgpu::{
+    BufferAsyncError, CreateSurfaceError, Features, Instance, Limits, RequestAdapterOptions,
+    RequestDeviceError, SurfaceError,
+};
+use winit::error::{ExternalError, OsError};
 
 pub type MetallicResult<T> = Result<T, MetallicError>;
 
+/// Returns the features and limits supported by the adapter which `options`
+/// selects, without creating a window or a device.
+///
+/// This lets callers check whether optional features (e.g.,
+/// [`Features::POLYGON_MODE_LINE`]) are available before requiring them
+/// through [`RenderingEngine::new_with_features`]. Note that the engine also
+/// requires its adapter to be compatible with its window's surface, so it may
+/// end up on a different adapter than one selected without a
+/// `compatible_surface`.
+///
+/// [`RenderingEngine::new_with_features`]: rendering_engine::RenderingEngine::new_with_features
+pub async fn adapter_features(
+    options: &RequestAdapterOptions<'_, '_>,
+) -> MetallicResult<(Features, Limits)> {
+    let adapter = Instance::default()
+        .request_adapter(options)
+        .await
+        .ok_or(MetallicError::NoAdapterFoundError)?;
+    Ok((adapter.features(), adapter.limits()))
+}
+
 #[derive(Error, Debug)]
 pub enum MetallicError {
     #[error("Surface error: {0:?}")]
     SurfaceError(#[from] SurfaceError),
 
+    #[error("Io error: {0:?}")]
+    IoError(#[from] std::io::Error),
+
     #[error("Os error: {0:?}")]
     OsError(#[from] OsError),
 
+    #[error("External error: {0:?}")]
+    ExternalError(#[from] ExternalError),
+
     #[error("Create surface error: {0:?}")]
     CreateSurfaceError(#[from] CreateSurfaceError),
 
@@ -25,9 +59,59 @@ pub enum MetallicError {
     #[error("Tessellation error: {0:?}")]
     TessellationError(#[from] TessellationError),
 
+    #[error("Text prepare error: {0:?}")]
+    PrepareError(#[from] PrepareError),
+
+    #[error("Text render error: {0:?}")]
+    RenderError(#[from] RenderError),
+
     #[error("No adapter found error")]
     NoAdapterFoundError,
 
+    #[error(
+        "Size exceeds limits error: {width}x{height} is larger than the maximum texture \
+         dimension of {max_dimension}"
+    )]
+    SizeExceedsLimitsError {
+        width: u32,
+        height: u32,
+        max_dimension: u32,
+    },
+
+    #[error("Too many layers error: at most {max_layers} layers are supported")]
+    TooManyLayersError { max_layers: usize },
+
+    #[error("Invalid z error: {z} is not a finite, non-negative z-order")]
+    InvalidZError { z: f32 },
+
+    #[error(
+        "Invalid texture error: {len} bytes don't make up a non-empty {width}x{height} RGBA8 \
+         texture"
+    )]
+    InvalidTextureError { width: u32, height: u32, len: usize },
+
+    #[error(
+        "Invalid mesh error: {index_count} indices (up to {max_index:?}) don't make up whole \
+         triangles of {vertex_count} vertices"
+    )]
+    InvalidMeshError {
+        vertex_count: usize,
+        index_count: usize,
+        max_index: Option<u32>,
+    },
+
+    #[error("Buffer map error: {0:?}")]
+    BufferAsyncError(#[from] BufferAsyncError),
+
+    #[error("Image error: {0:?}")]
+    ImageError(#[from] image::ImageError),
+
+    #[error("Font load error: {path:?} could not be loaded: {source:?}")]
+    FontLoadError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("Invalid configuration error: {0:?}")]
     InvalidConfigurationError(#[from] InvalidConfigurationError),
 }
@@ -37,8 +121,8 @@ pub enum InvalidConfigurationError {
     #[error("No 'srgb' texture-formats were found; at least one was expected")]
     NoTextureFormatFoundError,
 
-    #[error("No 'fifo' present-mode was found; this present mode is required")]
-    NoFifoPresentModeFoundError,
+    #[error("No present-modes were found; at least one was expected")]
+    NoPresentModeFoundError,
 
     #[error("No alpha-modes were found; at least one was expected")]
     NoAlphaModeFoundError,
@@ -1,31 +1,887 @@
 #[cfg(test)]
 mod tests;
 
+use std::{fmt, rc::Rc, sync::Arc};
+
 use bytemuck::{Pod, Zeroable};
-use euclid::default::Point2D;
+use euclid::{
+    default::{Box2D, Point2D, Rotation2D, Size2D},
+    Angle,
+};
+use glyphon::{cosmic_text::Align, Attrs, AttrsOwned, FamilyOwned, Shaping, TextBounds};
+use image::DynamicImage;
 use lyon::{
-    path::Path,
-    tessellation::{FillVertex, FillVertexConstructor},
+    algorithms::{aabb::bounding_box, hit_test::hit_test_path},
+    geom::LineSegment,
+    math::Transform,
+    path::{self, iterator::PathIterator, Event, Path, PathEvent, Winding},
+    tessellation::{
+        FillRule, FillVertex, FillVertexConstructor, LineCap, LineJoin, StrokeVertex,
+        StrokeVertexConstructor,
+    },
 };
-use wgpu::{vertex_attr_array, Color, VertexAttribute};
+use uuid::Uuid;
+use wgpu::{vertex_attr_array, Color, FilterMode, VertexAttribute};
 use winit::dpi::PhysicalSize;
 
+use crate::{rendering_engine::io::MouseInput, MetallicError, MetallicResult};
+
+/// Identifies a texture loaded with
+/// [`RenderingEngine::load_texture`](crate::rendering_engine::RenderingEngine::load_texture).
+pub type TextureId = Uuid;
+
+/// The color of a [`Vertex`]: four floats by default, or four normalized
+/// bytes with the `unorm8-colors` feature.
+///
+/// Bytes halve the size of every vertex (from 24 to 12 bytes), at the cost of
+/// only 256 levels per channel; gradients may show banding.
+#[cfg(not(feature = "unorm8-colors"))]
+pub type VertexColor = [f32; 4];
+#[cfg(feature = "unorm8-colors")]
+pub type VertexColor = [u8; 4];
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
 pub struct Vertex {
     pub point: [f32; 2],
-    pub color: [f32; 4],
+    pub color: VertexColor,
 }
 
 impl Vertex {
+    #[cfg(not(feature = "unorm8-colors"))]
     pub const VERTEX_ATTRS: [VertexAttribute; 2] =
         vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+    // Normalized bytes are read as floats by the shader, so it works with
+    // either format as-is.
+    #[cfg(feature = "unorm8-colors")]
+    pub const VERTEX_ATTRS: [VertexAttribute; 2] =
+        vertex_attr_array![0 => Float32x2, 1 => Unorm8x4];
 }
 
-#[derive(Debug, Clone)]
+/// Describes how an object is painted.
+///
+/// Gradients are approximated per vertex: every vertex is colored by where it
+/// lies along the gradient, and colors are interpolated across triangles from
+/// there. Shapes with few vertices (e.g., a rectangle) thus only show the
+/// colors at their corners, so multi-stop gradients are best used on shapes
+/// which are finely tessellated. Gradient points are in the same coordinate
+/// space as the object's own points. Images are tinted per corner, while text
+/// is painted per glyph, with the color at the glyph's center.
+///
+/// Gradient stops are `(offset, color)` pairs, and may be given in any order.
+/// Offsets are clamped into `[0, 1]`, and so are positions before the first
+/// or past the last stop (i.e., the outermost colors extend outwards). A
+/// single stop paints its color everywhere, like [`Brush::Solid`], while no
+/// stops at all paint nothing (i.e., transparent).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Brush {
+    Solid(Color),
+    /// Goes from the first stop at `start` to the last one at `end`, with
+    /// lines perpendicular to `start`-`end` sharing the same color.
+    LinearGradient {
+        start: Point2D<f32>,
+        end: Point2D<f32>,
+        stops: Vec<(f32, Color)>,
+    },
+    /// Goes from the first stop at `center` to the last one `radius` away from
+    /// it, in every direction.
+    RadialGradient {
+        center: Point2D<f32>,
+        radius: f32,
+        stops: Vec<(f32, Color)>,
+    },
+    /// Stretches the `uv_rect` part of a loaded texture over the object's
+    /// bounds, where `(0, 0)` is the texture's top-left corner and `(1, 1)`
+    /// its bottom-right one.
+    ///
+    /// Only shapes and meshes are textured (and drawn along with the images
+    /// of their layer); anything else is painted white. Objects whose
+    /// texture isn't loaded are skipped.
+    Texture {
+        id: TextureId,
+        uv_rect: Box2D<f32>,
+    },
+}
+
+impl Brush {
+    /// Returns the color which this brush paints `point` with.
+    pub fn color_at(&self, point: Point2D<f32>) -> Color {
+        match self {
+            Brush::Solid(color) => *color,
+            Brush::LinearGradient { start, end, stops } => {
+                let axis = *end - *start;
+                let offset = match axis.square_length() {
+                    0.0 => 0.0,
+                    length => (point - *start).dot(axis) / length,
+                };
+                sample_stops(stops, offset)
+            }
+            Brush::RadialGradient {
+                center,
+                radius,
+                stops,
+            } => {
+                let offset = match *radius > 0.0 {
+                    true => (point - *center).length() / radius,
+                    false => 1.0,
+                };
+                sample_stops(stops, offset)
+            }
+            Brush::Texture { .. } => Color::WHITE,
+        }
+    }
+}
+
+/// Interpolates between the (possibly unsorted) stops around `offset`.
+///
+/// Both `offset` and the stops' offsets are clamped into `[0, 1]` first. Stops
+/// which share an offset are ordered as given, so a pair of them makes for a
+/// hard edge.
+fn sample_stops(stops: &[(f32, Color)], offset: f32) -> Color {
+    let offset = offset.clamp(0.0, 1.0);
+    let stops = stops
+        .iter()
+        .map(|&(stop_offset, color)| (stop_offset.clamp(0.0, 1.0), color));
+    // The last stop at or before `offset`, and the first one at or after it.
+    let (mut before, mut after) = (None::<(f32, Color)>, None::<(f32, Color)>);
+    for stop @ (stop_offset, _) in stops {
+        if stop_offset <= offset && before.is_none_or(|(before, _)| stop_offset >= before) {
+            before = Some(stop);
+        };
+        if stop_offset >= offset && after.is_none_or(|(after, _)| stop_offset < after) {
+            after = Some(stop);
+        };
+    }
+    match (before, after) {
+        (None, None) => Color::TRANSPARENT,
+        (Some((_, color)), None) | (None, Some((_, color))) => color,
+        (Some((from, _)), Some((to, color))) if from == to => color,
+        (Some((from, a)), Some((to, b))) => {
+            let t = ((offset - from) / (to - from)) as f64;
+            let lerp = |a: f64, b: f64| a + (b - a) * t;
+            Color {
+                r: lerp(a.r, b.r),
+                g: lerp(a.g, b.g),
+                b: lerp(a.b, b.b),
+                a: lerp(a.a, b.a),
+            }
+        }
+    }
+}
+
+/// The space which a shape's points are given in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateSpace {
+    /// Absolute (logical) pixel coordinates, with the origin at the top-left
+    /// corner of the window and the y-axis pointing down.
+    #[default]
+    Absolute,
+    /// Normalized device coordinates, ranging from `-1.0` to `1.0` on both
+    /// axes, with the origin at the center of the window and the y-axis
+    /// pointing up. Points are passed through to the GPU unchanged.
+    Ndc,
+}
+
+impl CoordinateSpace {
+    /// Converts `point_2d` from this coordinate space into `to`, the same way
+    /// shapes are laid out in a `size`d window at the given scale factor (see
+    /// [`RenderingEngine::scale_factor`]).
+    ///
+    /// [`RenderingEngine::scale_factor`]: crate::rendering_engine::RenderingEngine::scale_factor
+    pub fn convert(
+        self,
+        point_2d: Point2D<f32>,
+        to: CoordinateSpace,
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+    ) -> Point2D<f32> {
+        let scale_factor = scale_factor as f32;
+        match (self, to) {
+            (CoordinateSpace::Absolute, CoordinateSpace::Ndc) => {
+                abs_to_ndc(point_2d * scale_factor, size)
+            }
+            (CoordinateSpace::Ndc, CoordinateSpace::Absolute) => {
+                ndc_to_abs(point_2d, size) / scale_factor
+            }
+            (CoordinateSpace::Absolute, CoordinateSpace::Absolute)
+            | (CoordinateSpace::Ndc, CoordinateSpace::Ndc) => point_2d,
+        }
+    }
+}
+
+/// Where lines of [`Text`] which are too long for the window (or for its
+/// [`wrap_width`](Text::wrap_width)) may be broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// Lines are never broken; anything too long overflows.
+    None,
+    /// Lines are broken between words; a single word which is too long
+    /// overflows.
+    #[default]
+    Word,
+    /// Lines are broken between any two glyphs (e.g., for code blocks).
+    Glyph,
+}
+
+/// How the lines of a [`Text`] are aligned within the window's width (or
+/// within its [`wrap_width`](Text::wrap_width)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Lines are stretched to the full width by widening the gaps between
+    /// words, save for the last line of every paragraph (which stays left
+    /// aligned).
+    Justify,
+}
+
+impl From<TextAlign> for Align {
+    fn from(align: TextAlign) -> Self {
+        match align {
+            TextAlign::Left => Self::Left,
+            TextAlign::Center => Self::Center,
+            TextAlign::Right => Self::Right,
+            TextAlign::Justify => Self::Justified,
+        }
+    }
+}
+
+/// Which point of a [`Text`] its [`position`](Text::position) refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAnchor {
+    /// The top-left corner of the text.
+    #[default]
+    Top,
+    /// The left end of the first line's baseline, so that text of different
+    /// sizes placed at the same height sits on the same line (instead of
+    /// hanging from the same top edge).
+    Baseline,
+}
+
+impl From<Wrap> for glyphon::Wrap {
+    fn from(wrap: Wrap) -> Self {
+        match wrap {
+            Wrap::None => Self::None,
+            Wrap::Word => Self::Word,
+            Wrap::Glyph => Self::Glyph,
+        }
+    }
+}
+
+/// Called with every mouse button press or release on top of a shape (see
+/// [`Shape::on_mouse_input`]).
+pub type MouseInputCallback = Rc<dyn Fn(&MouseInput)>;
+
+#[derive(Clone)]
 pub struct Shape {
     pub path: Path,
-    pub color: Color,
+    pub coordinate_space: CoordinateSpace,
+    /// Decides which regions of a multi-contour path count as "inside".
+    ///
+    /// E.g., a ring made of an outer counter-clockwise contour and an inner
+    /// clockwise one has a hole under both rules, while two contours with the
+    /// same winding only leave a hole under [`FillRule::EvenOdd`].
+    pub fill_rule: FillRule,
+    pub style: DrawStyle,
+    /// Called whenever a mouse button is pressed or released while the shape
+    /// is the topmost object under the cursor (see
+    /// [`RenderingEngine::register_io_event`]).
+    ///
+    /// [`RenderingEngine::register_io_event`]: crate::rendering_engine::RenderingEngine::register_io_event
+    pub on_mouse_input: Option<MouseInputCallback>,
+}
+
+impl fmt::Debug for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shape")
+            .field("path", &self.path)
+            .field("coordinate_space", &self.coordinate_space)
+            .field("fill_rule", &self.fill_rule)
+            .field("style", &self.style)
+            .field("on_mouse_input", &self.on_mouse_input.is_some())
+            .finish()
+    }
+}
+
+/// An empty shape (i.e., one which draws nothing), filled like the ones built
+/// by [`ShapeBuilder`].
+impl Default for Shape {
+    fn default() -> Self {
+        Self {
+            path: Path::default(),
+            coordinate_space: CoordinateSpace::default(),
+            fill_rule: FillRule::NonZero,
+            style: DrawStyle::default(),
+            on_mouse_input: None,
+        }
+    }
+}
+
+impl Shape {
+    /// Whether the shape draws nothing at all; i.e., it has no path, or it's
+    /// stroked with a line too thin to show up.
+    pub(crate) fn is_empty(&self) -> bool {
+        let too_thin = match self.style {
+            DrawStyle::Fill => false,
+            DrawStyle::Stroke { width, .. } => width <= 0.0 || width.is_nan(),
+        };
+        too_thin || self.path.iter().next().is_none()
+    }
+
+    /// Whether `point` lies inside the shape, once the shape has been moved
+    /// by `transform`.
+    ///
+    /// `transform` maps the shape's own coordinates to those of `point`; e.g.,
+    /// an object's transform followed by the view's (camera's) one. Rather
+    /// than transforming the path, `point` is mapped back through the
+    /// inverse, so that it can be tested against the path as-is. A transform
+    /// which can't be inverted (e.g., one which scales by zero) collapses the
+    /// shape, so nothing is inside of it.
+    ///
+    /// Curves are flattened to within `tolerance` (in the shape's own
+    /// coordinates). Stroked shapes only contain the points on their line,
+    /// whose width is taken to be in the shape's own coordinates as well.
+    pub fn contains(&self, point: Point2D<f32>, transform: &Transform, tolerance: f32) -> bool {
+        let Some(point) = transform
+            .inverse()
+            .map(|inverse| inverse.transform_point(point))
+        else {
+            return false;
+        };
+        match self.style {
+            DrawStyle::Fill => hit_test_path(&point, self.path.iter(), self.fill_rule, tolerance),
+            DrawStyle::Stroke { width, .. } => {
+                !self.is_empty()
+                    && self
+                        .outline(tolerance)
+                        .any(|line_segment| line_segment.distance_to_point(point) <= width / 2.0)
+            }
+        }
+    }
+
+    /// Returns the line segments which make up the shape's outline, with
+    /// curves flattened to within `tolerance`.
+    ///
+    /// Open sub-paths are filled as if they were closed, so they're closed
+    /// here too, unless the shape is stroked.
+    pub(crate) fn outline(&self, tolerance: f32) -> impl Iterator<Item = LineSegment<f32>> + '_ {
+        let filled = self.style == DrawStyle::Fill;
+        self.path
+            .iter()
+            .flattened(tolerance)
+            .filter_map(move |path_event| match path_event {
+                PathEvent::Line { from, to } => Some(LineSegment { from, to }),
+                PathEvent::End { last, first, close } if close || filled => Some(LineSegment {
+                    from: last,
+                    to: first,
+                }),
+                _ => None,
+            })
+    }
+}
+
+/// Whether a shape's path is filled in or only outlined.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DrawStyle {
+    #[default]
+    Fill,
+    /// Outlines the path (open sub-paths included) with a line `width` pixels
+    /// thick, centered on it. Lines which aren't thicker than zero aren't
+    /// drawn at all.
+    Stroke {
+        width: f32,
+        line_join: LineJoin,
+        line_cap: LineCap,
+    },
+}
+
+impl DrawStyle {
+    /// A stroke with mitered corners and butt-capped ends.
+    pub fn stroke(width: f32) -> Self {
+        Self::Stroke {
+            width,
+            line_join: LineJoin::Miter,
+            line_cap: LineCap::Butt,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub content: String,
+    /// Where the text is placed, in absolute coordinates: its top-left corner,
+    /// or the start of its baseline (see [`TextAnchor`]).
+    pub position: Point2D<f32>,
+    pub anchor: TextAnchor,
+    pub font_size: f32,
+    pub line_height: f32,
+    pub attrs: AttrsOwned,
+    pub shaping: Shaping,
+    pub wrap: Wrap,
+    /// How wide lines may get before they are wrapped (see [`Wrap`]), e.g.,
+    /// to lay text out in a column. `None` (the default) wraps them at the
+    /// window's width.
+    pub wrap_width: Option<f32>,
+    pub align: TextAlign,
+    /// Clips the rendered glyphs; anything outside of these bounds is cut off.
+    pub bounds: TextBounds,
+    /// How far the text is rotated clockwise around its position, in radians
+    /// (e.g., `-FRAC_PI_2` for a label which reads from bottom to top).
+    ///
+    /// Rotated text is rasterized into a texture of its own, which is drawn as
+    /// a rotated quad along with its layer's textured shapes. It thus ends up
+    /// underneath the layer's images and unrotated text, and isn't clipped to
+    /// its bounds.
+    pub angle: f32,
+}
+
+/// Geometry which has already been triangulated, and which is therefore drawn
+/// as-is instead of going through the tessellator.
+///
+/// Meshes are validated when they are created (see [`Mesh::new`]), so that
+/// every index points at a vertex.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    /// The mesh's vertices, in absolute coordinates.
+    vertices: Vec<Point2D<f32>>,
+    /// Every three indices (into `vertices`) make up one triangle.
+    indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// Creates a mesh out of `vertices` (in absolute coordinates), where every
+    /// three `indices` (into `vertices`) make up one triangle.
+    ///
+    /// Returns [`MetallicError::InvalidMeshError`] if the indices don't make up
+    /// whole triangles, or if any of them is out of range.
+    pub fn new(vertices: Vec<Point2D<f32>>, indices: Vec<u32>) -> MetallicResult<Self> {
+        let max_index = indices.iter().copied().max();
+        let in_range = max_index.is_none_or(|max_index| (max_index as usize) < vertices.len());
+        if !indices.len().is_multiple_of(3) || !in_range {
+            return Err(MetallicError::InvalidMeshError {
+                vertex_count: vertices.len(),
+                index_count: indices.len(),
+                max_index,
+            });
+        };
+        Ok(Self { vertices, indices })
+    }
+
+    pub fn vertices(&self) -> &[Point2D<f32>] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
+/// An image, stretched to fill `rect`.
+///
+/// Images are tinted by the brush they are drawn with (i.e., every pixel is
+/// multiplied by its color), so drawing one with white leaves it unchanged.
+/// Within a layer, images are drawn above shapes and meshes, and below text.
+#[derive(Debug, Clone)]
+pub struct Image {
+    /// Shared, so that an image which is drawn several times (or in several
+    /// frames) is only uploaded to the GPU once.
+    pub image: Arc<DynamicImage>,
+    /// Where to draw the image, in absolute coordinates.
+    pub rect: Box2D<f32>,
+    /// How the image is sampled when it's scaled: [`FilterMode::Linear`] (the
+    /// default) blends neighbouring pixels, which suits photos, while
+    /// [`FilterMode::Nearest`] keeps pixel art crisp.
+    pub filter_mode: FilterMode,
+}
+
+impl Image {
+    pub fn new(image: Arc<DynamicImage>, rect: Box2D<f32>) -> Self {
+        Self {
+            image,
+            rect,
+            filter_mode: FilterMode::Linear,
+        }
+    }
+
+    pub fn with_filter_mode(mut self, filter_mode: FilterMode) -> Self {
+        self.filter_mode = filter_mode;
+        self
+    }
+
+    /// Turns the image into an [`Object`], tinted with `brush`.
+    pub fn fill(self, brush: Brush) -> Object {
+        Object::image(self, brush)
+    }
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Self {
+            content: String::new(),
+            position: Point2D::new(0.0, 0.0),
+            anchor: TextAnchor::Top,
+            font_size: 16.0,
+            line_height: 20.0,
+            attrs: AttrsOwned::new(Attrs::new()),
+            shaping: Shaping::Basic,
+            wrap: Wrap::Word,
+            wrap_width: None,
+            align: TextAlign::Left,
+            bounds: TextBounds::default(),
+            angle: 0.0,
+        }
+    }
+}
+
+impl Text {
+    /// Creates unbounded text at the origin, using the default font at a size
+    /// of 16 (with a line height of 20).
+    pub fn new(content: &str) -> Self {
+        Self {
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_position(mut self, position: Point2D<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_anchor(mut self, anchor: TextAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_attrs(mut self, attrs: Attrs) -> Self {
+        self.attrs = AttrsOwned::new(attrs);
+        self
+    }
+
+    /// Sets the family of the font to render with, by name (e.g., `"Inter"`).
+    ///
+    /// The name is resolved against all fonts known to the engine, including
+    /// ones added through [`RenderingEngine::load_font`]. If no such font
+    /// exists, a fallback font is used instead.
+    ///
+    /// [`RenderingEngine::load_font`]: crate::rendering_engine::RenderingEngine::load_font
+    pub fn with_font(mut self, family: &str) -> Self {
+        self.attrs.family_owned = FamilyOwned::Name(family.into());
+        self
+    }
+
+    pub fn with_shaping(mut self, shaping: Shaping) -> Self {
+        self.shaping = shaping;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_wrap_width(mut self, wrap_width: f32) -> Self {
+        self.wrap_width = Some(wrap_width);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: TextBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    /// Rotates `point` (in absolute coordinates) around the text's position by
+    /// its angle.
+    pub(crate) fn rotate(&self, point: Point2D<f32>) -> Point2D<f32> {
+        let rotation = Rotation2D::new(Angle::radians(self.angle));
+        self.position + rotation.transform_vector(point - self.position)
+    }
+
+    /// Turns the text into an [`Object`] painted with `brush`.
+    pub fn fill(self, brush: Brush) -> Object {
+        Object::text(self, brush)
+    }
+}
+
+/// The dimensions of a piece of shaped [`Text`].
+///
+/// Vertical offsets are relative to the top of the text (i.e., to
+/// [`Text::position`], unless the text is anchored at its baseline), with the
+/// y-axis pointing down.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextMetrics {
+    /// How far the leftmost line starts to the right of [`Text::position`]
+    /// (i.e., 0 unless the text isn't left aligned; see [`TextAlign`]).
+    pub left: f32,
+    /// The width of the widest line.
+    pub width: f32,
+    /// The combined height of all lines.
+    pub height: f32,
+    /// How far the tallest glyph of the first line reaches above its baseline.
+    pub ascent: f32,
+    /// How far the lowest glyph of the first line reaches below its baseline.
+    pub descent: f32,
+    pub line_count: usize,
+    /// The offset of the first line's baseline.
+    pub baseline: f32,
+}
+
+#[derive(Debug, Clone)]
+pub enum ObjectKind {
+    Shape(Shape),
+    Text(Text),
+    Mesh(Mesh),
+    Image(Image),
+}
+
+/// Anything that can be added to a scene: *what* to draw, along with the
+/// [`Brush`] to draw it with.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub kind: ObjectKind,
+    pub brush: Brush,
+    /// Orders the object among the others in its layer: objects with a higher
+    /// z are drawn later (i.e., on top), and objects with the same z are drawn
+    /// in the order they were added. 0 by default.
+    pub z: f32,
+    /// Breaks ties between objects with the same layer and z: objects with a
+    /// higher bias are drawn later (i.e., on top), regardless of when they
+    /// were added. 0 by default.
+    pub z_bias: i32,
+}
+
+impl Object {
+    pub fn shape(shape: Shape, brush: Brush) -> Self {
+        Self {
+            kind: ObjectKind::Shape(shape),
+            brush,
+            z: 0.0,
+            z_bias: 0,
+        }
+    }
+
+    pub fn text(text: Text, brush: Brush) -> Self {
+        Self {
+            kind: ObjectKind::Text(text),
+            brush,
+            z: 0.0,
+            z_bias: 0,
+        }
+    }
+
+    pub fn mesh(mesh: Mesh, brush: Brush) -> Self {
+        Self {
+            kind: ObjectKind::Mesh(mesh),
+            brush,
+            z: 0.0,
+            z_bias: 0,
+        }
+    }
+
+    pub fn image(image: Image, brush: Brush) -> Self {
+        Self {
+            kind: ObjectKind::Image(image),
+            brush,
+            z: 0.0,
+            z_bias: 0,
+        }
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    pub fn with_z_bias(mut self, z_bias: i32) -> Self {
+        self.z_bias = z_bias;
+        self
+    }
+}
+
+/// A fluent wrapper around lyon's path builder, for building up [`Shape`]s
+/// without using lyon directly.
+///
+/// Coordinates are absolute unless another [`CoordinateSpace`] is set. Any
+/// sub-path which is still open when a new one is started (or when the shape
+/// is finished) is ended without being closed.
+///
+/// ```
+/// # use euclid::default::Point2D;
+/// # use metallic::primitives::{Brush, ShapeBuilder};
+/// # use wgpu::Color;
+/// let triangle = ShapeBuilder::new()
+///     .move_to(Point2D::new(0.0, 0.0))
+///     .line_to(Point2D::new(10.0, 0.0))
+///     .line_to(Point2D::new(5.0, 10.0))
+///     .close()
+///     .fill(Brush::Solid(Color::RED));
+/// ```
+pub struct ShapeBuilder {
+    builder: path::Builder,
+    coordinate_space: CoordinateSpace,
+    fill_rule: FillRule,
+    style: DrawStyle,
+    on_mouse_input: Option<MouseInputCallback>,
+    in_sub_path: bool,
+}
+
+impl Default for ShapeBuilder {
+    fn default() -> Self {
+        Self {
+            builder: Path::builder(),
+            coordinate_space: CoordinateSpace::Absolute,
+            fill_rule: FillRule::NonZero,
+            style: DrawStyle::Fill,
+            on_mouse_input: None,
+            in_sub_path: false,
+        }
+    }
+}
+
+impl ShapeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn coordinate_space(mut self, coordinate_space: CoordinateSpace) -> Self {
+        self.coordinate_space = coordinate_space;
+        self
+    }
+
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Sets the callback which is run whenever the shape is clicked on (see
+    /// [`Shape::on_mouse_input`]).
+    pub fn on_mouse_input<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&MouseInput) + 'static,
+    {
+        self.on_mouse_input = Some(Rc::new(callback));
+        self
+    }
+
+    pub fn style(mut self, style: DrawStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Starts a new sub-path at `to`.
+    pub fn move_to(mut self, to: Point2D<f32>) -> Self {
+        self.end_sub_path(false);
+        self.builder.begin(to);
+        self.in_sub_path = true;
+        self
+    }
+
+    /// Draws a line to `to`, starting a new sub-path at `to` if there isn't
+    /// one to continue.
+    pub fn line_to(mut self, to: Point2D<f32>) -> Self {
+        match self.in_sub_path {
+            true => {
+                self.builder.line_to(to);
+                self
+            }
+            false => self.move_to(to),
+        }
+    }
+
+    pub fn quad_to(mut self, ctrl: Point2D<f32>, to: Point2D<f32>) -> Self {
+        self = self.ensure_sub_path(ctrl);
+        self.builder.quadratic_bezier_to(ctrl, to);
+        self
+    }
+
+    pub fn cubic_to(mut self, ctrl1: Point2D<f32>, ctrl2: Point2D<f32>, to: Point2D<f32>) -> Self {
+        self = self.ensure_sub_path(ctrl1);
+        self.builder.cubic_bezier_to(ctrl1, ctrl2, to);
+        self
+    }
+
+    /// Closes the current sub-path with a line back to its start.
+    pub fn close(mut self) -> Self {
+        self.end_sub_path(true);
+        self
+    }
+
+    /// Adds a rectangle as its own closed sub-path.
+    pub fn rect(mut self, origin: Point2D<f32>, size: Size2D<f32>) -> Self {
+        self.end_sub_path(false);
+        self.builder.add_rectangle(
+            &Box2D::from_origin_and_size(origin, size),
+            Winding::Positive,
+        );
+        self
+    }
+
+    /// Adds a circle as its own closed sub-path.
+    pub fn circle(mut self, center: Point2D<f32>, radius: f32) -> Self {
+        self.end_sub_path(false);
+        self.builder.add_circle(center, radius, Winding::Positive);
+        self
+    }
+
+    pub fn build(mut self) -> Shape {
+        self.end_sub_path(false);
+        Shape {
+            path: self.builder.build(),
+            coordinate_space: self.coordinate_space,
+            fill_rule: self.fill_rule,
+            style: self.style,
+            on_mouse_input: self.on_mouse_input,
+        }
+    }
+
+    /// Finishes the shape and turns it into an [`Object`] painted with
+    /// `brush`.
+    pub fn fill(self, brush: Brush) -> Object {
+        Object::shape(self.build(), brush)
+    }
+
+    /// Finishes the shape and turns it into an [`Object`] whose outline is
+    /// `width` pixels thick and painted with `brush` (see
+    /// [`DrawStyle::stroke`]).
+    pub fn stroke(self, width: f32, brush: Brush) -> Object {
+        self.style(DrawStyle::stroke(width)).fill(brush)
+    }
+
+    fn ensure_sub_path(self, at: Point2D<f32>) -> Self {
+        match self.in_sub_path {
+            true => self,
+            false => self.move_to(at),
+        }
+    }
+
+    fn end_sub_path(&mut self, close: bool) {
+        if self.in_sub_path {
+            self.builder.end(close);
+            self.in_sub_path = false;
+        };
+    }
 }
 
 pub struct Ctor;
@@ -36,16 +892,164 @@ impl FillVertexConstructor<Point2D<f32>> for Ctor {
     }
 }
 
+impl StrokeVertexConstructor<Point2D<f32>> for Ctor {
+    fn new_vertex(&mut self, vertex: StrokeVertex) -> Point2D<f32> {
+        vertex.position()
+    }
+}
+
+/// Linearly interpolates between two paths, with `t = 0.0` yielding `a` and
+/// `t = 1.0` yielding `b`.
+///
+/// Points are matched up segment-by-segment, so both paths must be made up of
+/// the same sequence of segments (same number of sub-paths, and the same
+/// segment kinds in the same order). If they are not, `None` is returned; e.g.,
+/// a rectangle (three lines) can't be morphed into a circle (four cubic
+/// curves) without first rebuilding one of them out of matching segments.
+pub fn lerp_paths(a: &Path, b: &Path, t: f32) -> Option<Path> {
+    if a.iter().count() != b.iter().count() {
+        return None;
+    };
+    let mut builder = Path::builder();
+    for events in a.iter().zip(b.iter()) {
+        match events {
+            (Event::Begin { at: a }, Event::Begin { at: b }) => {
+                builder.begin(a.lerp(b, t));
+            }
+            (Event::Line { to: a, .. }, Event::Line { to: b, .. }) => {
+                builder.line_to(a.lerp(b, t));
+            }
+            (
+                Event::Quadratic {
+                    ctrl: a_ctrl,
+                    to: a,
+                    ..
+                },
+                Event::Quadratic {
+                    ctrl: b_ctrl,
+                    to: b,
+                    ..
+                },
+            ) => {
+                builder.quadratic_bezier_to(a_ctrl.lerp(b_ctrl, t), a.lerp(b, t));
+            }
+            (
+                Event::Cubic {
+                    ctrl1: a_ctrl1,
+                    ctrl2: a_ctrl2,
+                    to: a,
+                    ..
+                },
+                Event::Cubic {
+                    ctrl1: b_ctrl1,
+                    ctrl2: b_ctrl2,
+                    to: b,
+                    ..
+                },
+            ) => {
+                builder.cubic_bezier_to(
+                    a_ctrl1.lerp(b_ctrl1, t),
+                    a_ctrl2.lerp(b_ctrl2, t),
+                    a.lerp(b, t),
+                );
+            }
+            (Event::End { close: a, .. }, Event::End { close: b, .. }) if a == b => {
+                builder.end(a);
+            }
+            _ => return None,
+        };
+    }
+    Some(builder.build())
+}
+
+/// Scales and translates `path` so that its bounds fill `rect`.
+///
+/// If `preserve_aspect` is set, the path is scaled uniformly (by as much as
+/// still fits on both axes) and centered within `rect` instead. A path which
+/// is flat along an axis (e.g., a horizontal line) isn't stretched along it;
+/// it's only centered.
+pub fn fit_path_to_rect(path: &Path, rect: Box2D<f32>, preserve_aspect: bool) -> Path {
+    let bounds = bounding_box(path.iter());
+    let scale = |from: f32, to: f32| (from > 0.0).then(|| to / from);
+    let (scale_x, scale_y) = (
+        scale(bounds.width(), rect.width()),
+        scale(bounds.height(), rect.height()),
+    );
+    let (scale_x, scale_y) = match preserve_aspect {
+        true => {
+            let scale = match (scale_x, scale_y) {
+                (Some(scale_x), Some(scale_y)) => scale_x.min(scale_y),
+                (scale_x, scale_y) => scale_x.or(scale_y).unwrap_or(1.0),
+            };
+            (scale, scale)
+        }
+        false => (scale_x.unwrap_or(1.0), scale_y.unwrap_or(1.0)),
+    };
+    let center = bounds.center();
+    let transform = Transform::scale(scale_x, scale_y)
+        .then_translate(rect.center() - Point2D::new(center.x * scale_x, center.y * scale_y));
+    path.clone().transformed(&transform)
+}
+
 pub(crate) fn to_vertex(point_2d: Point2D<f32>, size: PhysicalSize<u32>, color: Color) -> Vertex {
+    to_unscaled_vertex(abs_to_ndc(point_2d, size), color)
+}
+
+pub(crate) fn abs_to_ndc(point_2d: Point2D<f32>, size: PhysicalSize<u32>) -> Point2D<f32> {
     let x = abs_to_scaled_1d(point_2d.x, size.width);
     let y = -abs_to_scaled_1d(point_2d.y, size.height);
-    let Color { r, g, b, a } = color;
+    Point2D::new(x, y)
+}
+
+/// The inverse of [`abs_to_ndc`].
+pub(crate) fn ndc_to_abs(point_2d: Point2D<f32>, size: PhysicalSize<u32>) -> Point2D<f32> {
+    let x = scaled_to_abs_1d(point_2d.x, size.width);
+    let y = scaled_to_abs_1d(-point_2d.y, size.height);
+    Point2D::new(x, y)
+}
+
+/// Builds a vertex which keeps `point_2d` exactly as given (i.e., in absolute
+/// scene coordinates rather than normalized device coordinates).
+pub(crate) fn to_unscaled_vertex(point_2d: Point2D<f32>, color: Color) -> Vertex {
     Vertex {
-        point: [x, y],
-        color: [r as _, g as _, b as _, a as _],
+        point: point_2d.to_array(),
+        color: to_vertex_color(color),
     }
 }
 
+/// Clamps every channel of `color` into `[0, 1]`.
+///
+/// Colors which come out of arithmetic (e.g., interpolating between two
+/// others) can end up slightly out of range, which the GPU would otherwise
+/// happily blend with.
+pub(crate) fn clamp_color(color: Color) -> Color {
+    let Color { r, g, b, a } = color;
+    let [r, g, b, a] = [r, g, b, a].map(|channel| channel.clamp(0.0, 1.0));
+    Color { r, g, b, a }
+}
+
+/// Returns `channel` (clamped into `[0, 1]`) as a byte, rounded to the
+/// nearest value.
+pub(crate) fn to_unorm8(channel: f64) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(not(feature = "unorm8-colors"))]
+pub(crate) fn to_vertex_color(color: Color) -> VertexColor {
+    let Color { r, g, b, a } = clamp_color(color);
+    [r as _, g as _, b as _, a as _]
+}
+
+#[cfg(feature = "unorm8-colors")]
+pub(crate) fn to_vertex_color(color: Color) -> VertexColor {
+    let Color { r, g, b, a } = color;
+    [r, g, b, a].map(to_unorm8)
+}
+
 fn abs_to_scaled_1d(x: f32, length: u32) -> f32 {
     (x / (length as f32)) * 2. - 1.
 }
+
+fn scaled_to_abs_1d(x: f32, length: u32) -> f32 {
+    (x + 1.) / 2. * (length as f32)
+}
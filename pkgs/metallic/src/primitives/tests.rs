@@ -1,3 +1,5 @@
+use lyon::algorithms::aabb::fast_bounding_box;
+
 use super::*;
 
 const LENGTH: u32 = 100;
@@ -16,3 +18,341 @@ fn test_abs_to_scaled_conversion() {
         assert_eq!(actual_output, expected_output);
     }
 }
+
+#[test]
+fn test_coordinate_space_conversion() {
+    let size = PhysicalSize::new(LENGTH, LENGTH);
+    let to_ndc = |x, y, scale_factor| {
+        CoordinateSpace::Absolute.convert(
+            Point2D::new(x, y),
+            CoordinateSpace::Ndc,
+            size,
+            scale_factor,
+        )
+    };
+    // The y-axis flips, from pointing down to pointing up.
+    assert_eq!(to_ndc(0.0, 0.0, 1.0), Point2D::new(-1.0, 1.0));
+    assert_eq!(to_ndc(100.0, 100.0, 1.0), Point2D::new(1.0, -1.0));
+    assert_eq!(to_ndc(25.0, 75.0, 1.0), Point2D::new(-0.5, -0.5));
+    // Absolute coordinates are logical, so they cover twice as many physical
+    // pixels at twice the scale.
+    assert_eq!(to_ndc(25.0, 25.0, 2.0), Point2D::new(0.0, 0.0));
+    for (point_2d, scale_factor) in [
+        (Point2D::new(25.0, 75.0), 1.0),
+        (Point2D::new(10.0, 40.0), 2.0),
+    ] {
+        let ndc = to_ndc(point_2d.x, point_2d.y, scale_factor);
+        let abs = CoordinateSpace::Ndc.convert(ndc, CoordinateSpace::Absolute, size, scale_factor);
+        assert!((abs - point_2d).length() < 1e-4);
+    }
+    assert_eq!(
+        CoordinateSpace::Ndc.convert(Point2D::new(0.5, 0.5), CoordinateSpace::Ndc, size, 2.0),
+        Point2D::new(0.5, 0.5)
+    );
+}
+
+fn rectangle(x: f32, y: f32, width: f32, height: f32) -> Path {
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::from_origin_and_size(Point2D::new(x, y), Size2D::new(width, height)),
+        Winding::Positive,
+    );
+    builder.build()
+}
+
+#[test]
+fn test_lerp_paths_endpoints() {
+    let a = rectangle(0.0, 0.0, 10.0, 10.0);
+    let b = rectangle(20.0, 40.0, 30.0, 50.0);
+    let start = lerp_paths(&a, &b, 0.0).unwrap();
+    let end = lerp_paths(&a, &b, 1.0).unwrap();
+    assert!(start.iter().eq(a.iter()));
+    assert!(end.iter().eq(b.iter()));
+}
+
+#[test]
+fn test_lerp_paths_midpoint() {
+    let a = rectangle(0.0, 0.0, 10.0, 10.0);
+    let b = rectangle(20.0, 40.0, 30.0, 50.0);
+    let middle = lerp_paths(&a, &b, 0.5).unwrap();
+    assert!(middle.iter().eq(rectangle(10.0, 20.0, 20.0, 30.0).iter()));
+}
+
+#[test]
+fn test_lerp_paths_mismatched_segments() {
+    let a = rectangle(0.0, 0.0, 10.0, 10.0);
+    let b = {
+        let mut builder = Path::builder();
+        builder.add_circle(Point2D::new(5.0, 5.0), 5.0, Winding::Positive);
+        builder.build()
+    };
+    assert!(lerp_paths(&a, &b, 0.5).is_none());
+    assert!(lerp_paths(&a, &Path::new(), 0.5).is_none());
+}
+
+/// The bounding box of the path, including its control points.
+fn bounds(shape: &Shape) -> Box2D<f32> {
+    fast_bounding_box(shape.path.iter())
+}
+
+#[test]
+fn test_shape_builder_lines_and_curves() {
+    let shape = ShapeBuilder::new()
+        .move_to(Point2D::new(10.0, 10.0))
+        .line_to(Point2D::new(30.0, 10.0))
+        .quad_to(Point2D::new(40.0, 20.0), Point2D::new(30.0, 30.0))
+        .cubic_to(
+            Point2D::new(20.0, 40.0),
+            Point2D::new(10.0, 40.0),
+            Point2D::new(10.0, 30.0),
+        )
+        .close()
+        .build();
+    assert_eq!(
+        bounds(&shape),
+        Box2D::new(Point2D::new(10.0, 10.0), Point2D::new(40.0, 40.0))
+    );
+    assert_eq!(
+        shape
+            .path
+            .iter()
+            .filter(|event| matches!(event, Event::End { close: true, .. }))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_shape_builder_rect_and_circle() {
+    let shape = ShapeBuilder::new()
+        .rect(Point2D::new(0.0, 0.0), Size2D::new(10.0, 20.0))
+        .circle(Point2D::new(50.0, 50.0), 10.0)
+        .fill_rule(FillRule::EvenOdd)
+        .build();
+    assert_eq!(
+        bounds(&shape),
+        Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(60.0, 60.0))
+    );
+    assert_eq!(shape.fill_rule, FillRule::EvenOdd);
+}
+
+#[test]
+fn test_shape_builder_ends_open_sub_paths() {
+    let object = ShapeBuilder::new()
+        .line_to(Point2D::new(0.0, 0.0))
+        .line_to(Point2D::new(10.0, 0.0))
+        .move_to(Point2D::new(0.0, 10.0))
+        .line_to(Point2D::new(10.0, 10.0))
+        .fill(Brush::Solid(Color::RED));
+    let ObjectKind::Shape(shape) = object.kind else {
+        panic!("Expected a shape");
+    };
+    let ends = shape
+        .path
+        .iter()
+        .filter_map(|event| match event {
+            Event::End { close, .. } => Some(close),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ends, [false, false]);
+    assert_eq!(object.brush, Brush::Solid(Color::RED));
+}
+
+#[test]
+fn test_text_builder() {
+    let object = Text::new("hi")
+        .with_position(Point2D::new(10.0, 20.0))
+        .with_font_size(32.0)
+        .with_line_height(40.0)
+        .fill(Brush::Solid(Color::BLACK));
+    let ObjectKind::Text(text) = object.kind else {
+        panic!("Expected text");
+    };
+    assert_eq!(text.content, "hi");
+    assert_eq!(text.position, Point2D::new(10.0, 20.0));
+    assert_eq!((text.font_size, text.line_height), (32.0, 40.0));
+    assert_eq!(text.shaping, Shaping::Basic);
+    assert_eq!(object.brush, Brush::Solid(Color::BLACK));
+}
+
+#[test]
+fn test_text_with_font() {
+    let text = Text::new("hi").with_font("DejaVu Sans Mono");
+    assert_eq!(
+        text.attrs.family_owned,
+        FamilyOwned::Name("DejaVu Sans Mono".into())
+    );
+}
+
+#[test]
+fn test_fit_path_to_rect_preserving_aspect() {
+    let path = rectangle(10.0, 10.0, 10.0, 10.0);
+    let rect = Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(100.0, 50.0));
+    let fitted = fit_path_to_rect(&path, rect, true);
+    // Scaled by 5 to fill the height, and centered horizontally.
+    assert!(fitted.iter().eq(rectangle(25.0, 0.0, 50.0, 50.0).iter()));
+}
+
+#[test]
+fn test_fit_path_to_rect_stretching() {
+    let path = rectangle(10.0, 10.0, 10.0, 10.0);
+    let rect = Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(100.0, 50.0));
+    let fitted = fit_path_to_rect(&path, rect, false);
+    assert!(fitted.iter().eq(rectangle(0.0, 0.0, 100.0, 50.0).iter()));
+}
+
+#[test]
+fn test_contains_accounts_for_the_transform() {
+    let shape = Shape {
+        path: rectangle(0.0, 0.0, 10.0, 10.0),
+        ..Shape::default()
+    };
+    let inside = Point2D::new(5.0, 5.0);
+    let identity = Transform::identity();
+    assert!(shape.contains(inside, &identity, 0.1));
+
+    // E.g., an object moved 100 to the right, seen through a camera which is
+    // panned 20 down.
+    let transform = Transform::translation(100.0, 0.0).then_translate([0.0, -20.0].into());
+    assert!(shape.contains(Point2D::new(105.0, -15.0), &transform, 0.1));
+    assert!(!shape.contains(inside, &transform, 0.1));
+
+    let scaled = Transform::scale(2.0, 2.0);
+    assert!(shape.contains(Point2D::new(15.0, 15.0), &scaled, 0.1));
+    assert!(!shape.contains(Point2D::new(25.0, 5.0), &scaled, 0.1));
+    assert!(!shape.contains(Point2D::new(0.0, 0.0), &Transform::scale(0.0, 1.0), 0.1));
+}
+
+#[test]
+fn test_colors_are_clamped_at_the_boundaries() {
+    for (channel, expected) in [(1.0, 255), (-0.1, 0), (1.5, 255), (0.0, 0), (0.5, 128)] {
+        assert_eq!(to_unorm8(channel), expected, "{channel}");
+    }
+    let color = Color {
+        r: 1.5,
+        g: -0.1,
+        b: 1.0,
+        a: 0.25,
+    };
+    let clamped = Color {
+        r: 1.0,
+        g: 0.0,
+        b: 1.0,
+        a: 0.25,
+    };
+    assert_eq!(clamp_color(color), clamped);
+    assert_eq!(to_vertex_color(color), to_vertex_color(clamped));
+}
+
+#[cfg(feature = "unorm8-colors")]
+#[test]
+fn test_unorm8_vertex_colors() {
+    assert_eq!(size_of::<Vertex>(), 12);
+    let color = Color {
+        r: 1.0,
+        g: 0.5,
+        b: -0.2,
+        a: 2.0,
+    };
+    assert_eq!(to_vertex_color(color), [255, 128, 0, 255]);
+}
+
+#[test]
+fn test_linear_gradients_interpolate_along_their_axis() {
+    let brush = Brush::LinearGradient {
+        start: Point2D::new(0.0, 0.0),
+        end: Point2D::new(100.0, 0.0),
+        // Unsorted, and reaching past 1.
+        stops: vec![(1.5, Color::BLUE), (0.0, Color::RED)],
+    };
+    let at = |x, y| brush.color_at(Point2D::new(x, y));
+    assert_eq!(at(0.0, 0.0), Color::RED);
+    assert_eq!(at(100.0, 0.0), Color::BLUE);
+    // Perpendicular to the axis, the color doesn't change.
+    assert_eq!(at(50.0, -20.0), at(50.0, 20.0));
+    let Color { r, b, a, .. } = at(50.0, 0.0);
+    assert_eq!((r, b, a), (0.5, 0.5, 1.0));
+    // Beyond either end, the outermost colors extend outwards.
+    assert_eq!(at(-50.0, 0.0), Color::RED);
+    assert_eq!(at(250.0, 0.0), Color::BLUE);
+}
+
+#[test]
+fn test_radial_gradients_interpolate_outwards() {
+    let brush = Brush::RadialGradient {
+        center: Point2D::new(50.0, 50.0),
+        radius: 10.0,
+        stops: vec![(0.0, Color::WHITE), (0.5, Color::RED), (1.0, Color::BLACK)],
+    };
+    let at = |x, y| brush.color_at(Point2D::new(x, y));
+    assert_eq!(at(50.0, 50.0), Color::WHITE);
+    assert_eq!(at(55.0, 50.0), Color::RED);
+    assert_eq!(at(50.0, 45.0), Color::RED);
+    assert_eq!(at(80.0, 80.0), Color::BLACK);
+}
+
+#[test]
+fn test_gradients_with_too_few_stops() {
+    let linear = |stops| Brush::LinearGradient {
+        start: Point2D::new(0.0, 0.0),
+        end: Point2D::new(10.0, 0.0),
+        stops,
+    };
+    let single = linear(vec![(0.7, Color::GREEN)]);
+    for x in [-5.0, 0.0, 5.0, 15.0] {
+        assert_eq!(single.color_at(Point2D::new(x, 0.0)), Color::GREEN);
+    }
+    let empty = linear(vec![]);
+    assert_eq!(empty.color_at(Point2D::new(5.0, 0.0)), Color::TRANSPARENT);
+    // A degenerate axis (or radius) paints one of the ends.
+    let point = Brush::LinearGradient {
+        start: Point2D::new(5.0, 5.0),
+        end: Point2D::new(5.0, 5.0),
+        stops: vec![(0.0, Color::RED), (1.0, Color::BLUE)],
+    };
+    assert_eq!(point.color_at(Point2D::new(0.0, 0.0)), Color::RED);
+}
+
+#[test]
+fn test_stops_sharing_an_offset_make_a_hard_edge() {
+    let brush = Brush::LinearGradient {
+        start: Point2D::new(0.0, 0.0),
+        end: Point2D::new(10.0, 0.0),
+        stops: vec![
+            (0.0, Color::RED),
+            (0.5, Color::RED),
+            (0.5, Color::BLUE),
+            (1.0, Color::BLUE),
+        ],
+    };
+    assert_eq!(brush.color_at(Point2D::new(4.9, 0.0)), Color::RED);
+    assert_eq!(brush.color_at(Point2D::new(5.1, 0.0)), Color::BLUE);
+}
+
+#[test]
+fn test_meshes_are_validated() {
+    let triangle = || {
+        vec![
+            Point2D::new(0.0, 0.0),
+            Point2D::new(10.0, 0.0),
+            Point2D::new(0.0, 10.0),
+        ]
+    };
+    assert!(Mesh::new(triangle(), vec![0, 1, 2]).is_ok());
+    assert!(Mesh::new(vec![], vec![]).is_ok());
+    // A trailing partial triangle.
+    assert!(matches!(
+        Mesh::new(triangle(), vec![0, 1, 2, 0]),
+        Err(MetallicError::InvalidMeshError { index_count: 4, .. })
+    ));
+    // An index past the last vertex.
+    assert!(matches!(
+        Mesh::new(triangle(), vec![0, 1, 3]),
+        Err(MetallicError::InvalidMeshError {
+            vertex_count: 3,
+            max_index: Some(3),
+            ..
+        })
+    ));
+}
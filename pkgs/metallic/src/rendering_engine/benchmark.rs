@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+use wgpu::{Maintain, PresentMode};
+
+use crate::{rendering_engine::RenderingEngine, MetallicResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchmarkReport {
+    pub frames: usize,
+    pub present_mode: PresentMode,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// How many bytes of geometry were written to the GPU over the whole run.
+    pub uploaded_bytes: u64,
+}
+
+impl BenchmarkReport {
+    pub(crate) fn new(
+        present_mode: PresentMode,
+        mut frame_times: Vec<Duration>,
+        uploaded_bytes: u64,
+    ) -> Self {
+        frame_times.sort_unstable();
+        let frames = frame_times.len();
+        let average = match frames {
+            0 => Duration::ZERO,
+            _ => frame_times.iter().sum::<Duration>() / (frames as u32),
+        };
+        Self {
+            frames,
+            present_mode,
+            average,
+            p50: percentile(&frame_times, 50),
+            p95: percentile(&frame_times, 95),
+            p99: percentile(&frame_times, 99),
+            max: frame_times.last().copied().unwrap_or_default(),
+            uploaded_bytes,
+        }
+    }
+}
+
+impl RenderingEngine {
+    /// Renders `frames` frames back-to-back and reports how long each one
+    /// took.
+    ///
+    /// The surface is switched over to [`PresentMode::Immediate`] (if the
+    /// adapter supports it) for the duration of the run so that vsync does
+    /// not cap the measurements, and the device is polled until idle after
+    /// every frame so that each timing covers the full GPU work. The
+    /// previous present mode is restored afterwards, even if a frame fails.
+    pub fn benchmark(&mut self, frames: usize) -> MetallicResult<BenchmarkReport> {
+        self.benchmark_with(frames, |_, _| ())
+    }
+
+    /// Like [`RenderingEngine::benchmark`], but calls `update` with the
+    /// frame's index before every frame (e.g., to change one shape per
+    /// frame).
+    ///
+    /// The time spent in `update` itself isn't measured.
+    pub fn benchmark_with<F>(
+        &mut self,
+        frames: usize,
+        mut update: F,
+    ) -> MetallicResult<BenchmarkReport>
+    where
+        F: FnMut(&mut Self, usize),
+    {
+        let previous_present_mode = self.wgpu_bundle.surface_configuration.present_mode;
+        let present_mode = match self
+            .wgpu_bundle
+            .present_modes
+            .contains(&PresentMode::Immediate)
+        {
+            true => PresentMode::Immediate,
+            false => previous_present_mode,
+        };
+        self.configure_present_mode(present_mode);
+        let mut uploaded_bytes = 0;
+        let frame_times = (0..frames)
+            .map(|frame| {
+                update(self, frame);
+                // Every frame is rendered in full, even if nothing has changed.
+                self.scene_bundle.dirty = true;
+                let start = Instant::now();
+                self.render()?;
+                self.wgpu_bundle.device.poll(Maintain::Wait);
+                uploaded_bytes += self.buffer_bundle.written_bytes;
+                Ok(start.elapsed())
+            })
+            .collect::<MetallicResult<Vec<_>>>();
+        self.configure_present_mode(previous_present_mode);
+        Ok(BenchmarkReport::new(
+            present_mode,
+            frame_times?,
+            uploaded_bytes,
+        ))
+    }
+}
+
+/// Nearest-rank percentile over an already sorted slice.
+fn percentile(sorted_frame_times: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted_frame_times.len() * percent).div_ceil(100);
+    sorted_frame_times
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+use bytemuck::cast_slice;
+use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue};
+
+use crate::rendering_engine::Geometry;
+
+/// The smallest capacity (in bytes) that either buffer is created with.
+const MIN_CAPACITY: BufferAddress = 1 << 12;
+
+/// The vertex and index buffers which all visible shapes and meshes are drawn
+/// from.
+///
+/// The buffers persist across frames. Whenever the scene's layout (i.e., which
+/// objects are visible, in which order, and how many vertices and indices each
+/// of them has) stays the same, only the slices of objects whose geometry or
+/// color actually changed are rewritten. Otherwise, everything is rewritten in
+/// one go.
+///
+/// A buffer which is too small is replaced with one of the next power of two
+/// of the required size (so at least twice as large); buffers never shrink.
+/// A scene which grows steadily is therefore only reallocated a logarithmic
+/// number of times.
+pub struct BufferBundle {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    /// Every visible layer (in ascending order), along with the range of
+    /// indices which its shapes occupy.
+    pub layers: Vec<(usize, Range<u32>)>,
+    /// A copy of the buffers' contents, to diff the next frame against.
+    geometry: Geometry,
+    /// How many bytes were written to the buffers by the last
+    /// [`prepare_geometry`].
+    pub written_bytes: u64,
+    /// How many times the buffers have been prepared, i.e., how many frames'
+    /// geometry went through [`prepare_geometry`].
+    pub prepared_frames: u64,
+}
+
+pub fn new_buffer_bundle(device: &Device) -> BufferBundle {
+    BufferBundle {
+        vertex_buffer: create_buffer(device, "metallic.vertex_buffer", BufferUsages::VERTEX, 0),
+        index_buffer: create_buffer(device, "metallic.index_buffer", BufferUsages::INDEX, 0),
+        layers: vec![],
+        geometry: Geometry::default(),
+        written_bytes: 0,
+        prepared_frames: 0,
+    }
+}
+
+/// Uploads `geometry`, rewriting only what has changed since the last call
+/// (see [`BufferBundle`]).
+pub fn prepare_geometry(
+    buffer_bundle: &mut BufferBundle,
+    device: &Device,
+    queue: &Queue,
+    geometry: Geometry,
+) {
+    let vertex_bytes = size_of_val(geometry.vertices.as_slice()) as BufferAddress;
+    let index_bytes = size_of_val(geometry.indices.as_slice()) as BufferAddress;
+    let grown = grow(
+        &mut buffer_bundle.vertex_buffer,
+        device,
+        "metallic.vertex_buffer",
+        BufferUsages::VERTEX,
+        vertex_bytes,
+    ) | grow(
+        &mut buffer_bundle.index_buffer,
+        device,
+        "metallic.index_buffer",
+        BufferUsages::INDEX,
+        index_bytes,
+    );
+    let previous = &buffer_bundle.geometry;
+    let same_layout = !grown && geometry.objects == previous.objects;
+    buffer_bundle.written_bytes = 0;
+    match same_layout {
+        true => {
+            for (_, vertices, indices) in &geometry.objects {
+                let vertices = vertices.start as usize..vertices.end as usize;
+                if geometry.vertices[vertices.clone()] != previous.vertices[vertices.clone()] {
+                    buffer_bundle.written_bytes += write(
+                        queue,
+                        &buffer_bundle.vertex_buffer,
+                        &geometry.vertices,
+                        vertices,
+                    );
+                };
+                let indices = indices.start as usize..indices.end as usize;
+                if geometry.indices[indices.clone()] != previous.indices[indices.clone()] {
+                    buffer_bundle.written_bytes += write(
+                        queue,
+                        &buffer_bundle.index_buffer,
+                        &geometry.indices,
+                        indices,
+                    );
+                };
+            }
+        }
+        false => {
+            buffer_bundle.written_bytes += write(
+                queue,
+                &buffer_bundle.vertex_buffer,
+                &geometry.vertices,
+                0..geometry.vertices.len(),
+            );
+            buffer_bundle.written_bytes += write(
+                queue,
+                &buffer_bundle.index_buffer,
+                &geometry.indices,
+                0..geometry.indices.len(),
+            );
+        }
+    };
+    buffer_bundle.layers = geometry.layers.clone();
+    buffer_bundle.geometry = geometry;
+    buffer_bundle.prepared_frames += 1;
+}
+
+/// Replaces `buffer` with a larger one if it can't hold `size` bytes, and
+/// returns whether it did.
+fn grow(
+    buffer: &mut Buffer,
+    device: &Device,
+    label: &str,
+    usage: BufferUsages,
+    size: BufferAddress,
+) -> bool {
+    if size <= buffer.size() {
+        return false;
+    };
+    *buffer = create_buffer(device, label, usage, size);
+    true
+}
+
+fn create_buffer(device: &Device, label: &str, usage: BufferUsages, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size: size.next_power_of_two().max(MIN_CAPACITY),
+        usage: usage | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Writes `data[range]` to the same range of `buffer`, returning how many
+/// bytes were written.
+fn write<T>(queue: &Queue, buffer: &Buffer, data: &[T], range: Range<usize>) -> u64
+where
+    T: bytemuck::Pod,
+{
+    let bytes: &[u8] = cast_slice(&data[range.clone()]);
+    if !bytes.is_empty() {
+        queue.write_buffer(buffer, (range.start * size_of::<T>()) as _, bytes);
+    };
+    bytes.len() as _
+}
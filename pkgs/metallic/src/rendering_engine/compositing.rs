@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use bytemuck::cast_slice;
+use wgpu::{
+    include_wgsl,
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, BufferBindingType,
+    BufferUsages, ColorTargetState, ColorWrites, Device, Extent3d, FragmentState, MultisampleState,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, RenderPass,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+use winit::dpi::PhysicalSize;
+
+use crate::rendering_engine::{
+    texture_pool::{TextureKey, TexturePool},
+    wgpu_bundle::WgpuBundle,
+    RenderingEngine,
+};
+
+/// State for the (opt-in) mode in which every visible layer is rendered into
+/// its own texture, with the textures then being blended onto the surface.
+pub struct CompositingBundle {
+    pub enabled: bool,
+    opacities: HashMap<usize, f32>,
+    render_pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+pub fn new_compositing_bundle(wgpu_bundle: &WgpuBundle) -> CompositingBundle {
+    let device = &wgpu_bundle.device;
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("metallic.composite_bind_group_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let render_pipeline = new_composite_pipeline(
+        device,
+        &bind_group_layout,
+        wgpu_bundle.surface_configuration.format,
+        wgpu_bundle.sample_count,
+    );
+    CompositingBundle {
+        enabled: false,
+        opacities: HashMap::new(),
+        render_pipeline,
+        bind_group_layout,
+        sampler: device.create_sampler(&SamplerDescriptor {
+            label: Some("metallic.composite_sampler"),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Creates the pipeline which blends layer textures onto a target of the
+/// given format, with `sample_count` samples per pixel.
+fn new_composite_pipeline(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(include_wgsl!("../shaders/composite.wgsl"));
+    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("metallic.composite_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("metallic.composite_pipeline"),
+        layout: Some(&render_pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs",
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs",
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+impl CompositingBundle {
+    /// Rebuilds the pipeline for targets with `sample_count` samples per pixel.
+    pub fn set_sample_count(&mut self, device: &Device, format: TextureFormat, sample_count: u32) {
+        self.render_pipeline =
+            new_composite_pipeline(device, &self.bind_group_layout, format, sample_count);
+    }
+
+    pub fn opacity(&self, layer: usize) -> f32 {
+        self.opacities.get(&layer).copied().unwrap_or(1.0)
+    }
+
+    pub fn remap_layers<F>(&mut self, remap: F)
+    where
+        F: Fn(usize) -> usize,
+    {
+        self.opacities = self
+            .opacities
+            .drain()
+            .map(|(layer, opacity)| (remap(layer), opacity))
+            .collect();
+    }
+
+    /// Returns `count` views onto textures which can be rendered into and then
+    /// composited, taking the textures from (and adding any missing ones to)
+    /// `texture_pool`.
+    pub fn layer_views(
+        &self,
+        texture_pool: &mut TexturePool,
+        device: &Device,
+        size: PhysicalSize<u32>,
+        format: TextureFormat,
+        count: usize,
+    ) -> Vec<TextureView> {
+        let key = TextureKey {
+            label: "metallic.layer_texture",
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            sample_count: 1,
+        };
+        texture_pool
+            .textures(device, key, count)
+            .iter()
+            .map(|target| {
+                target.create_view(&TextureViewDescriptor {
+                    label: Some("metallic.layer_view"),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Blends a layer's texture over whatever has already been drawn.
+    pub fn composite(
+        &self,
+        render_pass: &mut RenderPass,
+        device: &Device,
+        view: &TextureView,
+        opacity: f32,
+    ) {
+        let opacity_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("metallic.opacity_buffer"),
+            contents: cast_slice(&[opacity, 0.0, 0.0, 0.0]),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("metallic.composite_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: opacity_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+impl RenderingEngine {
+    /// Turns per-layer compositing on or off (it is off by default).
+    ///
+    /// When on, every visible layer is first rendered into its own
+    /// transparent texture, and those textures are then blended onto the
+    /// surface in order, each with its own opacity (see
+    /// [`RenderingEngine::set_layer_opacity`]). This costs an extra texture
+    /// and render pass per layer, so only turn it on if layer effects are
+    /// actually needed.
+    pub fn set_layer_compositing(&mut self, enabled: bool) {
+        self.compositing_bundle.enabled = enabled;
+        self.scene_bundle.dirty = true;
+    }
+
+    /// Sets the opacity (clamped to `0.0..=1.0`) that the given layer is
+    /// composited with.
+    ///
+    /// Only has an effect while layer compositing is turned on.
+    pub fn set_layer_opacity(&mut self, layer: usize, opacity: f32) {
+        self.compositing_bundle
+            .opacities
+            .insert(layer, opacity.clamp(0.0, 1.0));
+        self.scene_bundle.dirty = true;
+    }
+}
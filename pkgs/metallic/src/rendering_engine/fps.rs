@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use euclid::default::Point2D;
+use glyphon::{Attrs, Family};
+use uuid::Uuid;
+use wgpu::Color;
+
+use crate::{
+    primitives::{Brush, Text},
+    rendering_engine::{Handle, RenderingEngine},
+};
+
+/// How many of the most recent frames the FPS overlay averages over.
+pub(super) const FPS_WINDOW: usize = 60;
+
+/// The handle which the overlay's text is prepared under; no object ever has
+/// the nil UUID.
+pub(super) const FPS_HANDLE: Handle = Uuid::nil();
+
+/// Times the presented frames for the FPS overlay (see
+/// [`RenderingEngine::set_show_fps`]).
+#[derive(Default)]
+pub(super) struct FpsBundle {
+    pub(super) shown: bool,
+    /// The durations of (up to) the last [`FPS_WINDOW`] frames, oldest first.
+    frame_times: VecDeque<Duration>,
+    /// When the last frame was presented, unless rendering has been paused
+    /// since.
+    last_frame: Option<Instant>,
+}
+
+impl FpsBundle {
+    /// Records a frame which was presented at `now`.
+    pub(super) fn record(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            if self.frame_times.len() == FPS_WINDOW {
+                self.frame_times.pop_front();
+            };
+            self.frame_times.push_back(now - last_frame);
+        };
+    }
+
+    /// Forgets when the last frame was presented, so that the time during
+    /// which nothing is rendered (e.g., while the window is minimized) isn't
+    /// counted as a frame.
+    pub(super) fn pause(&mut self) {
+        self.last_frame = None;
+    }
+
+    /// Returns the average duration of the recent frames, if any were timed.
+    pub(super) fn frame_time(&self) -> Option<Duration> {
+        let frames = self.frame_times.len();
+        match frames {
+            0 => None,
+            _ => Some(self.frame_times.iter().sum::<Duration>() / (frames as u32)),
+        }
+    }
+
+    /// Returns the overlay's text (in the top-left corner), along with the
+    /// brush which it's painted with.
+    pub(super) fn overlay(&self) -> (Text, Brush) {
+        let content = match self.frame_time() {
+            Some(frame_time) => format!(
+                "{:.0} FPS\n{:.2} ms",
+                1.0 / frame_time.as_secs_f64(),
+                frame_time.as_secs_f64() * 1000.0
+            ),
+            None => "-- FPS\n-- ms".into(),
+        };
+        let text = Text::new(&content)
+            .with_position(Point2D::new(8.0, 8.0))
+            .with_font_size(14.0)
+            .with_line_height(18.0)
+            .with_attrs(Attrs::new().family(Family::Monospace));
+        (text, Brush::Solid(Color::GREEN))
+    }
+}
+
+impl RenderingEngine {
+    /// Shows (or hides) a small debug overlay in the top-left corner, with the
+    /// frame rate and the frame time, averaged over the last 60 frames.
+    ///
+    /// The overlay is drawn on top of everything else, including the render
+    /// hooks, but isn't part of the scene: it has no handle, never shows up in
+    /// hit tests, and is left out of [`RenderingEngine::render_to_image`].
+    /// While it's shown, frames are rendered continuously (as if the scene
+    /// changed every frame), so that it stays up to date.
+    pub fn set_show_fps(&mut self, show_fps: bool) {
+        if self.fps_bundle.shown == show_fps {
+            return;
+        };
+        self.fps_bundle = FpsBundle {
+            shown: show_fps,
+            ..FpsBundle::default()
+        };
+        self.scene_bundle.dirty = true;
+        self.redraw();
+    }
+
+    /// Returns whether the FPS overlay is shown (see
+    /// [`RenderingEngine::set_show_fps`]).
+    pub fn show_fps(&self) -> bool {
+        self.fps_bundle.shown
+    }
+
+    /// Returns the frame rate which the FPS overlay shows, if it's shown and
+    /// has timed any frames yet.
+    pub fn fps(&self) -> Option<f64> {
+        self.fps_bundle
+            .frame_time()
+            .filter(|_| self.fps_bundle.shown)
+            .map(|frame_time| 1.0 / frame_time.as_secs_f64())
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use euclid::default::Point2D;
+use glyphon::{
+    fontdb::Database, Attrs, AttrsList, AttrsOwned, Buffer, Cache, Color as GlyphonColor,
+    FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea, TextAtlas, TextBounds,
+    TextRenderer, Viewport,
+};
+use wgpu::{Color, Device, MultisampleState, Queue, TextureFormat};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    primitives::{to_unorm8, Brush, Text, TextAlign, TextAnchor, TextMetrics, Wrap},
+    rendering_engine::{rotated_text::RotatedText, Handle},
+    MetallicError, MetallicResult,
+};
+
+pub struct GlyphBundle {
+    /// Kept around so that the atlas can be rebuilt (see
+    /// [`GlyphBundle::clear_cache`]).
+    pub cache: Cache,
+    pub format: TextureFormat,
+    /// How many samples per pixel the text renderers are built for.
+    pub sample_count: u32,
+    pub font_system: FontSystem,
+    pub swash_cache: SwashCache,
+    /// Shared by all text renderers, across all frames; only rewritten when
+    /// the surface is resized (see [`GlyphBundle::resize`]).
+    pub viewport: Viewport,
+    pub text_atlas: TextAtlas,
+    /// One renderer per visible layer, so that text can be interleaved with
+    /// the shapes of the layers around it.
+    pub text_renderers: Vec<TextRenderer>,
+    /// The (indices of the) layers whose text failed to prepare last frame,
+    /// and which must therefore not be drawn.
+    pub unprepared_layers: HashSet<usize>,
+    /// The shaped buffer of every text object which was rendered last frame,
+    /// so that unchanged text isn't reshaped (and changed text reuses its
+    /// allocation) on the next one.
+    pub text_buffers: HashMap<Handle, CachedBuffer>,
+    /// The texture of every rotated text object which was rendered last frame
+    /// (see [`prepare_rotated_text`]).
+    ///
+    /// [`prepare_rotated_text`]: crate::rendering_engine::rotated_text::prepare_rotated_text
+    pub rotated_texts: HashMap<Handle, RotatedText>,
+}
+
+pub struct CachedBuffer {
+    pub buffer: Buffer,
+    shaped: Option<ShapedAs>,
+    /// The gradient which the glyphs are colored with, along with where the
+    /// buffer's top-left corner was at the time; `None` while they have no
+    /// colors of their own (see [`CachedBuffer::paint`]).
+    painted: Option<(Brush, Point2D<f32>)>,
+}
+
+/// Everything which affects how a [`Text`] is shaped.
+pub(super) struct ShapedAs {
+    content: String,
+    font_size: f32,
+    line_height: f32,
+    attrs: AttrsOwned,
+    shaping: Shaping,
+    wrap: Wrap,
+    wrap_width: Option<f32>,
+    align: TextAlign,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+}
+
+impl ShapedAs {
+    pub(super) fn new(text: &Text, size: PhysicalSize<u32>, scale_factor: f32) -> Self {
+        Self {
+            content: text.content.clone(),
+            font_size: text.font_size,
+            line_height: text.line_height,
+            attrs: text.attrs.clone(),
+            shaping: text.shaping,
+            wrap: text.wrap,
+            wrap_width: text.wrap_width,
+            align: text.align,
+            size,
+            scale_factor,
+        }
+    }
+
+    pub(super) fn matches(&self, text: &Text, size: PhysicalSize<u32>, scale_factor: f32) -> bool {
+        self.content == text.content
+            && self.font_size == text.font_size
+            && self.line_height == text.line_height
+            && self.attrs == text.attrs
+            && self.shaping == text.shaping
+            && self.wrap == text.wrap
+            && self.wrap_width == text.wrap_width
+            && self.align == text.align
+            && self.size == size
+            && self.scale_factor == scale_factor
+    }
+}
+
+impl CachedBuffer {
+    pub fn new(font_system: &mut FontSystem) -> Self {
+        Self {
+            buffer: Buffer::new(font_system, Metrics::new(1.0, 1.0)),
+            shaped: None,
+            painted: None,
+        }
+    }
+
+    /// Reshapes the buffer's text, unless it has already been shaped in
+    /// exactly the same way.
+    pub fn update(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &Text,
+        size: PhysicalSize<u32>,
+        scale_factor: f32,
+    ) {
+        if self
+            .shaped
+            .as_ref()
+            .is_some_and(|shaped| shaped.matches(text, size, scale_factor))
+        {
+            return;
+        };
+        shape_into(&mut self.buffer, font_system, text, size, scale_factor);
+        self.shaped = Some(ShapedAs::new(text, size, scale_factor));
+        // Reshaping starts over from the text's own attributes.
+        self.painted = None;
+    }
+
+    /// Colors the (already shaped) glyphs with `brush` (see
+    /// [`paint_glyphs`]), unless they already are.
+    ///
+    /// Solid (and textured, i.e., white) text is painted with its text area's
+    /// default color instead, so its glyphs are left without colors of their
+    /// own.
+    pub fn paint(&mut self, font_system: &mut FontSystem, text: &Text, brush: &Brush) {
+        let origin = Point2D::new(text.position.x, text_top(text, &self.buffer));
+        let painted = match brush {
+            Brush::Solid(_) | Brush::Texture { .. } => None,
+            _ => Some((brush.clone(), origin)),
+        };
+        if self.painted == painted {
+            return;
+        };
+        let color_at = painted.as_ref().map(|(brush, origin)| {
+            |center: Point2D<f32>| brush.color_at(*origin + center.to_vector())
+        });
+        paint_glyphs(
+            &mut self.buffer,
+            font_system,
+            text.attrs.as_attrs(),
+            color_at,
+        );
+        self.painted = painted;
+    }
+}
+
+pub fn new_glyph_bundle(device: &Device, queue: &Queue, format: TextureFormat) -> GlyphBundle {
+    let cache = Cache::new(device);
+    let viewport = Viewport::new(device, &cache);
+    let text_atlas = TextAtlas::new(device, queue, &cache, format);
+    GlyphBundle {
+        cache,
+        format,
+        sample_count: 1,
+        font_system: FontSystem::new(),
+        swash_cache: SwashCache::new(),
+        viewport,
+        text_atlas,
+        text_renderers: vec![],
+        unprepared_layers: HashSet::new(),
+        text_buffers: HashMap::new(),
+        rotated_texts: HashMap::new(),
+    }
+}
+
+impl GlyphBundle {
+    /// Updates the viewport's resolution to the surface's new size.
+    pub fn resize(&mut self, queue: &Queue, size: PhysicalSize<u32>) {
+        self.viewport.update(
+            queue,
+            Resolution {
+                width: size.width,
+                height: size.height,
+            },
+        );
+    }
+
+    /// Drops every rasterized glyph, on both the CPU and the GPU, by
+    /// rebuilding the swash cache and the text atlas.
+    ///
+    /// Loaded fonts and shaped text are kept.
+    pub fn clear_cache(&mut self, device: &Device, queue: &Queue) {
+        self.swash_cache = SwashCache::new();
+        self.text_atlas = TextAtlas::new(device, queue, &self.cache, self.format);
+        // Renderers are tied to the atlas they were created with; they are
+        // recreated on the next `prepare_text`.
+        self.text_renderers.clear();
+    }
+
+    /// Has the text renderers rebuilt (on the next `prepare_text`) for
+    /// targets with `sample_count` samples per pixel.
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.text_renderers.clear();
+    }
+}
+
+/// Shapes and uploads the text of every visible layer.
+///
+/// The text is laid out within `size`, which should match the resolution that
+/// the viewport was last [resized](GlyphBundle::resize) to, with its
+/// positions, bounds and font sizes (which are in logical pixels) scaled by
+/// `scale_factor`. After this,
+/// `text_renderers[index]` is ready to draw all of the text in `layers[index]`
+/// in a single call.
+///
+/// A layer whose text fails to prepare (e.g., because its glyphs don't fit
+/// into the atlas) doesn't fail the whole frame: the error is logged, and the
+/// layer is added to `unprepared_layers` so that its text is skipped (while
+/// its shapes are still drawn).
+pub fn prepare_text(
+    glyph_bundle: &mut GlyphBundle,
+    device: &Device,
+    queue: &Queue,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+    layers: &[Vec<(Handle, &Text, &Brush)>],
+) {
+    let GlyphBundle {
+        font_system,
+        cache: _,
+        format: _,
+        sample_count,
+        swash_cache,
+        viewport,
+        text_atlas,
+        text_renderers,
+        unprepared_layers,
+        text_buffers,
+        rotated_texts: _,
+    } = glyph_bundle;
+    let mut rendered = HashSet::new();
+    for &(handle, text, brush) in layers.iter().flatten() {
+        let cached_buffer = text_buffers
+            .entry(handle)
+            .or_insert_with(|| CachedBuffer::new(font_system));
+        cached_buffer.update(font_system, text, size, scale_factor);
+        cached_buffer.paint(font_system, text, brush);
+        rendered.insert(handle);
+    }
+    text_buffers.retain(|handle, _| rendered.contains(handle));
+    unprepared_layers.clear();
+    for (index, texts) in layers.iter().enumerate() {
+        if text_renderers.len() <= index {
+            let multisample = MultisampleState {
+                count: *sample_count,
+                ..Default::default()
+            };
+            let text_renderer = TextRenderer::new(text_atlas, device, multisample, None);
+            text_renderers.push(text_renderer);
+        };
+        let text_areas = texts.iter().map(|&(handle, text, brush)| TextArea {
+            buffer: &text_buffers[&handle].buffer,
+            left: text.position.x * scale_factor,
+            top: text_top(text, &text_buffers[&handle].buffer) * scale_factor,
+            scale: scale_factor,
+            bounds: scale_bounds(text.bounds, scale_factor),
+            // Only used by solid text; gradients color every glyph.
+            default_color: convert_color(brush.color_at(text.position)),
+            custom_glyphs: &[],
+        });
+        let prepared = text_renderers[index].prepare(
+            device,
+            queue,
+            font_system,
+            text_atlas,
+            viewport,
+            text_areas,
+            swash_cache,
+        );
+        if let Err(error) = prepared {
+            log::warn!("Skipping the text of layer {index} for this frame: {error}");
+            unprepared_layers.insert(index);
+        };
+    }
+}
+
+/// Shapes `text` (wrapping it at its wrap width, or else at the given size,
+/// just like when rendering it) and measures the result, in logical pixels.
+pub fn measure_text(
+    font_system: &mut FontSystem,
+    text: &Text,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> TextMetrics {
+    let buffer = to_buffer(font_system, text, size, scale_factor);
+    let first_line = buffer
+        .lines
+        .iter()
+        .find_map(|line| line.layout_opt().as_ref()?.first());
+    let (ascent, descent) = first_line
+        .map(|layout_line| (layout_line.max_ascent, layout_line.max_descent))
+        .unwrap_or_default();
+    // Lines which aren't left aligned start further to the right.
+    let (left, right) = buffer
+        .layout_runs()
+        .filter_map(|layout_run| {
+            let left = layout_run
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.x)
+                .reduce(f32::min)?;
+            Some((left, left + layout_run.line_w))
+        })
+        .reduce(|(a_left, a_right), (b_left, b_right)| (a_left.min(b_left), a_right.max(b_right)))
+        .unwrap_or_default();
+    buffer.layout_runs().fold(
+        TextMetrics {
+            left,
+            width: right - left,
+            ascent,
+            descent,
+            baseline: baseline(&buffer),
+            ..TextMetrics::default()
+        },
+        |metrics, layout_run| TextMetrics {
+            height: metrics.height + layout_run.line_height,
+            line_count: metrics.line_count + 1,
+            ..metrics
+        },
+    )
+}
+
+/// Loads the font file at `path` into `database`, reporting which file failed
+/// to load if it can't be read.
+pub fn load_font_file(database: &mut Database, path: &Path) -> MetallicResult<()> {
+    database
+        .load_font_file(path)
+        .map_err(|source| MetallicError::FontLoadError {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Returns the offset of the first line's baseline from the top of `buffer`.
+fn baseline(buffer: &Buffer) -> f32 {
+    buffer
+        .layout_runs()
+        .next()
+        .map_or(0.0, |layout_run| layout_run.line_y)
+}
+
+/// Returns where the top of `text` ends up, once shaped into `buffer`.
+pub(crate) fn text_top(text: &Text, buffer: &Buffer) -> f32 {
+    match text.anchor {
+        TextAnchor::Top => text.position.y,
+        TextAnchor::Baseline => text.position.y - baseline(buffer),
+    }
+}
+
+/// Scales text bounds from logical to physical pixels.
+fn scale_bounds(bounds: TextBounds, scale_factor: f32) -> TextBounds {
+    // Casts saturate, so unbounded sides stay unbounded.
+    let scale = |side: i32| (side as f32 * scale_factor) as i32;
+    TextBounds {
+        left: scale(bounds.left),
+        top: scale(bounds.top),
+        right: scale(bounds.right),
+        bottom: scale(bounds.bottom),
+    }
+}
+
+fn to_buffer(
+    font_system: &mut FontSystem,
+    text: &Text,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Buffer {
+    let mut buffer = Buffer::new(font_system, Metrics::new(text.font_size, text.line_height));
+    shape_into(&mut buffer, font_system, text, size, scale_factor);
+    buffer
+}
+
+/// Shapes `text` into `buffer` in logical pixels (i.e., within `size` with
+/// `scale_factor` divided out); it's only scaled up once it's drawn.
+pub(super) fn shape_into(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    text: &Text,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) {
+    buffer.set_metrics_and_size(
+        font_system,
+        Metrics::new(text.font_size, text.line_height),
+        Some(text.wrap_width.unwrap_or(size.width as f32 / scale_factor)),
+        Some(size.height as f32 / scale_factor),
+    );
+    buffer.set_wrap(font_system, text.wrap.into());
+    buffer.set_text(
+        font_system,
+        &text.content,
+        text.attrs.as_attrs(),
+        text.shaping,
+    );
+    for line in &mut buffer.lines {
+        line.set_align(Some(text.align.into()));
+    }
+    buffer.shape_until_scroll(font_system, false);
+}
+
+/// Gives every glyph in `buffer` the color which `color_at` returns for its
+/// center (relative to the buffer's top-left corner), or, without it, takes
+/// the glyphs' own colors away again.
+///
+/// Glyphs are colored through color runs in their lines' attributes, which
+/// have to be reshaped afterwards; colors don't affect shaping, though, so no
+/// glyph moves.
+pub(super) fn paint_glyphs(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    attrs: Attrs,
+    color_at: Option<impl Fn(Point2D<f32>) -> Color>,
+) {
+    let mut attrs_lists = vec![AttrsList::new(attrs); buffer.lines.len()];
+    if let Some(color_at) = color_at {
+        for layout_run in buffer.layout_runs() {
+            let y = layout_run.line_top + layout_run.line_height / 2.0;
+            for glyph in layout_run.glyphs {
+                let center = Point2D::new(glyph.x + glyph.w / 2.0, y);
+                let color = convert_color(color_at(center));
+                attrs_lists[layout_run.line_i].add_span(glyph.start..glyph.end, attrs.color(color));
+            }
+        }
+    };
+    for (line, attrs_list) in buffer.lines.iter_mut().zip(attrs_lists) {
+        line.set_attrs_list(attrs_list);
+    }
+    buffer.shape_until_scroll(font_system, false);
+}
+
+pub(crate) fn convert_color(color: Color) -> GlyphonColor {
+    let Color { r, g, b, a } = color;
+    GlyphonColor::rgba(to_unorm8(r), to_unorm8(g), to_unorm8(b), to_unorm8(a))
+}
@@ -0,0 +1,567 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    ops::Range,
+    sync::Arc,
+};
+
+use bytemuck::{cast_slice, Pod, Zeroable};
+use euclid::default::{Box2D, Point2D, Size2D};
+use image::DynamicImage;
+use wgpu::{
+    include_wgsl,
+    util::{BufferInitDescriptor, DeviceExt, TextureDataOrder},
+    vertex_attr_array, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferUsages, Color, ColorTargetState, ColorWrites, Device, Extent3d, FilterMode,
+    FragmentState, MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    PrimitiveState, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderStages, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexState, VertexStepMode,
+};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    primitives::{abs_to_ndc, clamp_color, Brush, Image, TextureId},
+    rendering_engine::{wgpu_bundle::validate_size, RenderingEngine},
+    MetallicError, MetallicResult,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ImageVertex {
+    pub point: [f32; 2],
+    pub uv: [f32; 2],
+    pub tint: [f32; 4],
+}
+
+impl ImageVertex {
+    pub const VERTEX_ATTRS: [VertexAttribute; 3] =
+        vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+}
+
+/// Identifies an uploaded image: either an image object's by the address of
+/// its [`Arc`], or a loaded texture's by its ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ImageKey {
+    Image(usize),
+    Texture(TextureId),
+}
+
+/// A textured shape (or mesh) of a layer: which texture it's painted with,
+/// and its triangles.
+pub type TexturedDraw = (TextureId, Vec<ImageVertex>);
+
+/// Which image to draw, how to sample it, and which range of the vertex buffer
+/// its quad occupies.
+type ImageDraw = (ImageKey, FilterMode, Range<u32>);
+
+pub struct ImageBundle {
+    render_pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    linear_sampler: Sampler,
+    nearest_sampler: Sampler,
+    /// Every image which was drawn last frame, already uploaded, along with
+    /// every loaded texture.
+    uploaded_images: HashMap<ImageKey, UploadedImage>,
+    /// The quads of all images which are about to be drawn.
+    vertex_buffer: Option<Buffer>,
+    /// The background image (if any), with its (full-surface) quad.
+    background: Option<ImageDraw>,
+    /// For every visible layer, which images to draw with which quads.
+    layers: Vec<Vec<ImageDraw>>,
+}
+
+struct UploadedImage {
+    /// Keeps the image alive, so that its address (i.e., its key) can't be
+    /// reused by another image while it is still cached. Textures are keyed
+    /// by their IDs instead, and so don't need this.
+    _image: Option<Arc<DynamicImage>>,
+    view: TextureView,
+    /// One bind group per filter mode the image is drawn with, so that the
+    /// same image can be drawn both crisp and smooth while only being
+    /// uploaded once.
+    bind_groups: HashMap<FilterMode, BindGroup>,
+}
+
+pub fn new_image_bundle(device: &Device, format: TextureFormat, sample_count: u32) -> ImageBundle {
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("metallic.image_bind_group_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let render_pipeline = new_image_pipeline(device, &bind_group_layout, format, sample_count);
+    ImageBundle {
+        render_pipeline,
+        bind_group_layout,
+        linear_sampler: device.create_sampler(&SamplerDescriptor {
+            label: Some("metallic.image_linear_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        }),
+        nearest_sampler: device.create_sampler(&SamplerDescriptor {
+            label: Some("metallic.image_nearest_sampler"),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        }),
+        uploaded_images: HashMap::new(),
+        vertex_buffer: None,
+        background: None,
+        layers: vec![],
+    }
+}
+
+/// Creates the pipeline which draws image quads into a target of the given
+/// format, with `sample_count` samples per pixel.
+fn new_image_pipeline(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(include_wgsl!("../shaders/image.wgsl"));
+    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("metallic.image_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("metallic.image_pipeline"),
+        layout: Some(&render_pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs",
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[VertexBufferLayout {
+                array_stride: size_of::<ImageVertex>() as _,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &ImageVertex::VERTEX_ATTRS,
+            }],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs",
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Uploads the quads of the background image and of every visible layer's
+/// images, along with any images which haven't been uploaded yet.
+///
+/// After this, [`ImageBundle::draw_background`] is ready to draw the
+/// background, and [`ImageBundle::draw`] all of the textured shapes in
+/// `textured[index]` followed by the images in `layers[index]`. Uploaded
+/// images which are neither the background nor part of `layers` are dropped
+/// (unlike loaded textures), while textured shapes whose texture isn't loaded
+/// are skipped.
+///
+/// Images are laid out in a target of the given size, with their rects (which
+/// are in logical pixels) scaled by the given scale factor.
+pub fn prepare_images(
+    image_bundle: &mut ImageBundle,
+    device: &Device,
+    queue: &Queue,
+    (size, scale_factor): (PhysicalSize<u32>, f32),
+    background: Option<&Arc<DynamicImage>>,
+    layers: &[Vec<(&Image, &Brush)>],
+    textured: &[Vec<TexturedDraw>],
+) -> MetallicResult<()> {
+    let mut vertices = vec![];
+    let mut drawn = HashSet::new();
+    image_bundle.background = None;
+    if let Some(image) = background {
+        let surface = Box2D::from_size(Size2D::new(size.width as _, size.height as _));
+        image_bundle.background = prepare_quad(
+            image_bundle,
+            device,
+            queue,
+            (
+                image,
+                surface,
+                &Brush::Solid(Color::WHITE),
+                FilterMode::Linear,
+            ),
+            // The surface is already in physical pixels.
+            (size, 1.0),
+            &mut vertices,
+            &mut drawn,
+        )?;
+    };
+    image_bundle.layers.clear();
+    for (index, images) in layers.iter().enumerate() {
+        let mut draws = vec![];
+        for (id, triangles) in textured.get(index).into_iter().flatten() {
+            let key = ImageKey::Texture(*id);
+            if !image_bundle.uploaded_images.contains_key(&key) {
+                log::warn!("Skipping a shape painted with texture {id}, which isn't loaded");
+                continue;
+            };
+            image_bundle.bind_group(device, key, FilterMode::Linear);
+            let start = vertices.len() as u32;
+            vertices.extend(triangles);
+            draws.push((key, FilterMode::Linear, start..vertices.len() as u32));
+        }
+        for &(image, brush) in images {
+            draws.extend(prepare_quad(
+                image_bundle,
+                device,
+                queue,
+                (&image.image, image.rect, brush, image.filter_mode),
+                (size, scale_factor),
+                &mut vertices,
+                &mut drawn,
+            )?);
+        }
+        image_bundle.layers.push(draws);
+    }
+    image_bundle
+        .uploaded_images
+        .retain(|key, _| matches!(key, ImageKey::Texture(_)) || drawn.contains(key));
+    image_bundle.vertex_buffer = match vertices.is_empty() {
+        true => None,
+        false => Some(device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("metallic.image_vertex_buffer"),
+            contents: cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        })),
+    };
+    Ok(())
+}
+
+/// Uploads the image (unless it already has been) and adds its quad to
+/// `vertices` (see [`quad`]), returning which image to draw with which
+/// vertices.
+///
+/// Empty images are skipped.
+fn prepare_quad(
+    image_bundle: &mut ImageBundle,
+    device: &Device,
+    queue: &Queue,
+    (image, rect, brush, filter_mode): (&Arc<DynamicImage>, Box2D<f32>, &Brush, FilterMode),
+    (size, scale_factor): (PhysicalSize<u32>, f32),
+    vertices: &mut Vec<ImageVertex>,
+    drawn: &mut HashSet<ImageKey>,
+) -> MetallicResult<Option<ImageDraw>> {
+    if image.width() == 0 || image.height() == 0 {
+        return Ok(None);
+    };
+    let key = ImageKey::Image(Arc::as_ptr(image) as usize);
+    if let Entry::Vacant(entry) = image_bundle.uploaded_images.entry(key) {
+        let size = PhysicalSize::new(image.width(), image.height());
+        entry.insert(UploadedImage {
+            _image: Some(image.clone()),
+            view: upload_rgba(device, queue, size, &image.to_rgba8())?,
+            bind_groups: HashMap::new(),
+        });
+    };
+    image_bundle.bind_group(device, key, filter_mode);
+    drawn.insert(key);
+    let start = vertices.len() as u32;
+    vertices.extend(quad(rect, brush, size, scale_factor));
+    Ok(Some((key, filter_mode, start..vertices.len() as u32)))
+}
+
+impl ImageBundle {
+    #[cfg(test)]
+    pub fn uploaded_image_count(&self) -> usize {
+        self.uploaded_images.len()
+    }
+
+    /// Rebuilds the pipeline for targets with `sample_count` samples per pixel.
+    pub fn set_sample_count(&mut self, device: &Device, format: TextureFormat, sample_count: u32) {
+        self.render_pipeline =
+            new_image_pipeline(device, &self.bind_group_layout, format, sample_count);
+    }
+
+    /// Uploads `rgba` (straight, sRGB-encoded RGBA8 pixels, row by row from
+    /// the top) as a texture which stays loaded until it's unloaded.
+    pub fn load_texture(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        rgba: &[u8],
+        size: PhysicalSize<u32>,
+    ) -> MetallicResult<TextureId> {
+        let PhysicalSize { width, height } = size;
+        if width == 0 || height == 0 || rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(MetallicError::InvalidTextureError {
+                width,
+                height,
+                len: rgba.len(),
+            });
+        };
+        let id = TextureId::new_v4();
+        self.uploaded_images.insert(
+            ImageKey::Texture(id),
+            UploadedImage {
+                _image: None,
+                view: upload_rgba(device, queue, size, rgba)?,
+                bind_groups: HashMap::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Drops a loaded texture, returning whether there was one with the given
+    /// ID.
+    pub fn unload_texture(&mut self, id: TextureId) -> bool {
+        self.uploaded_images
+            .remove(&ImageKey::Texture(id))
+            .is_some()
+    }
+
+    /// Creates the uploaded image's bind group for `filter_mode`, unless it
+    /// already exists.
+    fn bind_group(&mut self, device: &Device, key: ImageKey, filter_mode: FilterMode) {
+        let ImageBundle {
+            bind_group_layout,
+            linear_sampler,
+            nearest_sampler,
+            uploaded_images,
+            ..
+        } = self;
+        let sampler = match filter_mode {
+            FilterMode::Linear => linear_sampler,
+            FilterMode::Nearest => nearest_sampler,
+        };
+        let uploaded_image = uploaded_images
+            .get_mut(&key)
+            .expect("Bind groups are only created for uploaded images");
+        uploaded_image
+            .bind_groups
+            .entry(filter_mode)
+            .or_insert_with(|| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("metallic.image_bind_group"),
+                    layout: bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&uploaded_image.view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(sampler),
+                        },
+                    ],
+                })
+            });
+    }
+
+    /// Drops every uploaded image (but not the loaded textures), along with
+    /// everything that was prepared to be drawn; images are uploaded again as
+    /// they are next prepared.
+    pub fn clear(&mut self) {
+        self.uploaded_images
+            .retain(|key, _| matches!(key, ImageKey::Texture(_)));
+        self.vertex_buffer = None;
+        self.background = None;
+        self.layers.clear();
+    }
+
+    /// Draws the background image, if there is one.
+    pub fn draw_background(&self, render_pass: &mut RenderPass) {
+        if let Some(background) = &self.background {
+            self.draw_quads(render_pass, std::slice::from_ref(background));
+        };
+    }
+
+    /// Draws the images of the `index`-th visible layer.
+    pub fn draw(&self, render_pass: &mut RenderPass, index: usize) {
+        if let Some(draws) = self.layers.get(index) {
+            self.draw_quads(render_pass, draws);
+        };
+    }
+
+    fn draw_quads(&self, render_pass: &mut RenderPass, draws: &[ImageDraw]) {
+        let Some(vertex_buffer) = &self.vertex_buffer else {
+            return;
+        };
+        if draws.is_empty() {
+            return;
+        };
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        for (key, filter_mode, vertices) in draws {
+            let bind_group = &self.uploaded_images[key].bind_groups[filter_mode];
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(vertices.clone(), 0..1);
+        }
+    }
+}
+
+impl RenderingEngine {
+    /// Uploads a `width` by `height` texture, which shapes and meshes can
+    /// then be painted with (see [`Brush::Texture`]).
+    ///
+    /// `rgba` holds straight (i.e., non-premultiplied), sRGB-encoded RGBA8
+    /// pixels, row by row from the top, and has to be exactly as long as
+    /// that. The texture stays loaded until it's
+    /// [unloaded](RenderingEngine::unload_texture).
+    pub fn load_texture(
+        &mut self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+    ) -> MetallicResult<TextureId> {
+        let id = self.image_bundle.load_texture(
+            &self.wgpu_bundle.device,
+            &self.wgpu_bundle.queue,
+            rgba,
+            PhysicalSize::new(width, height),
+        )?;
+        // Objects may have been waiting for it.
+        self.scene_bundle.dirty = true;
+        Ok(id)
+    }
+
+    /// Frees a loaded texture, returning whether there was one with the given
+    /// ID. Objects which are still painted with it are skipped from then on.
+    pub fn unload_texture(&mut self, id: TextureId) -> bool {
+        let unloaded = self.image_bundle.unload_texture(id);
+        self.scene_bundle.dirty |= unloaded;
+        unloaded
+    }
+}
+
+/// Uploads `size`d RGBA8 pixels into a new texture, returning a view onto it.
+fn upload_rgba(
+    device: &Device,
+    queue: &Queue,
+    size: PhysicalSize<u32>,
+    rgba: &[u8],
+) -> MetallicResult<TextureView> {
+    validate_size(size, &device.limits())?;
+    let texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("metallic.image_texture"),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        rgba,
+    );
+    Ok(texture.create_view(&TextureViewDescriptor {
+        label: Some("metallic.image_view"),
+        ..Default::default()
+    }))
+}
+
+/// Returns the two triangles which make up an image's quad (whose `rect` is
+/// scaled by `scale_factor` into a `size`d target), with each corner tinted by
+/// the color that `brush` paints it with.
+fn quad(
+    rect: Box2D<f32>,
+    brush: &Brush,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> [ImageVertex; 6] {
+    let vertex = |x, y, u, v| {
+        let point = Point2D::new(x, y);
+        let Color { r, g, b, a } = clamp_color(brush.color_at(point));
+        ImageVertex {
+            point: abs_to_ndc(point * scale_factor, size).to_array(),
+            uv: [u, v],
+            tint: [r as _, g as _, b as _, a as _],
+        }
+    };
+    let (min, max) = (rect.min, rect.max);
+    let top_left = vertex(min.x, min.y, 0.0, 0.0);
+    let top_right = vertex(max.x, min.y, 1.0, 0.0);
+    let bottom_left = vertex(min.x, max.y, 0.0, 1.0);
+    let bottom_right = vertex(max.x, max.y, 1.0, 1.0);
+    [
+        top_left,
+        bottom_left,
+        top_right,
+        top_right,
+        bottom_left,
+        bottom_right,
+    ]
+}
+
+/// Returns the triangles of a textured shape (or mesh), given its tessellated
+/// `points` (in absolute, physical coordinates) and `indices`.
+///
+/// The points' bounds are mapped onto `uv_rect`, so the texture is stretched
+/// over the whole shape.
+pub fn textured_triangles(
+    points: &[Point2D<f32>],
+    indices: &[u32],
+    uv_rect: Box2D<f32>,
+    size: PhysicalSize<u32>,
+) -> Vec<ImageVertex> {
+    let bounds = Box2D::from_points(points);
+    // A flat shape covers no pixels anyway, so any coordinate does.
+    let to_uv = |x: f32, min: f32, length: f32, uv_min: f32, uv_length: f32| match length > 0.0 {
+        true => uv_min + (x - min) / length * uv_length,
+        false => uv_min,
+    };
+    indices
+        .iter()
+        .map(|&index| {
+            let point = points[index as usize];
+            let u = to_uv(
+                point.x,
+                bounds.min.x,
+                bounds.width(),
+                uv_rect.min.x,
+                uv_rect.width(),
+            );
+            let v = to_uv(
+                point.y,
+                bounds.min.y,
+                bounds.height(),
+                uv_rect.min.y,
+                uv_rect.height(),
+            );
+            ImageVertex {
+                point: abs_to_ndc(point, size).to_array(),
+                uv: [u, v],
+                tint: [1.0; 4],
+            }
+        })
+        .collect()
+}
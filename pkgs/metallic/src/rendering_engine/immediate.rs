@@ -0,0 +1,66 @@
+use euclid::default::{Box2D, Point2D};
+use uuid::Uuid;
+use wgpu::Color;
+
+use crate::{
+    primitives::{Brush, Object, ShapeBuilder, Text},
+    rendering_engine::RenderingEngine,
+};
+
+/// An immediate-mode facade over the (retained) scene, for quick prototypes.
+///
+/// Everything drawn through these methods is added to the current layer just
+/// like [`RenderingEngine::add_object`] would, but without handing out a
+/// handle: it only lives until the next call to [`RenderingEngine::render`]
+/// (or [`RenderingEngine::present`]), after which it is removed again, even if
+/// nothing was presented (e.g., while the window is minimized). Drawing the
+/// same things every frame (before every render) thus keeps them on screen
+/// without piling up, and objects which were added the usual way are left
+/// alone.
+impl RenderingEngine {
+    /// Draws `object` for the next frame only.
+    pub fn draw(&mut self, object: Object) {
+        let handle = Uuid::new_v4();
+        self.scene_bundle.insert(handle, object);
+        self.immediate_handles.insert(handle);
+    }
+
+    /// Draws a rectangle (in absolute coordinates) for the next frame only.
+    pub fn draw_rect(&mut self, rect: Box2D<f32>, color: Color) {
+        self.draw(
+            ShapeBuilder::new()
+                .rect(rect.min, rect.size())
+                .fill(Brush::Solid(color)),
+        );
+    }
+
+    /// Draws a circle (in absolute coordinates) for the next frame only.
+    pub fn draw_circle(&mut self, center: Point2D<f32>, radius: f32, color: Color) {
+        self.draw(
+            ShapeBuilder::new()
+                .circle(center, radius)
+                .fill(Brush::Solid(color)),
+        );
+    }
+
+    /// Draws `content` with its top-left corner at `position` (in the default
+    /// font and size) for the next frame only.
+    ///
+    /// For anything fancier, build a [`Text`] and [`draw`](Self::draw) it.
+    pub fn draw_text(&mut self, content: &str, position: Point2D<f32>, color: Color) {
+        self.draw(
+            Text::new(content)
+                .with_position(position)
+                .fill(Brush::Solid(color)),
+        );
+    }
+
+    /// Removes everything drawn through the immediate-mode methods.
+    pub(super) fn clear_immediate(&mut self) {
+        if self.immediate_handles.is_empty() {
+            return;
+        };
+        self.scene_bundle.remove_all(&self.immediate_handles);
+        self.immediate_handles.clear();
+    }
+}
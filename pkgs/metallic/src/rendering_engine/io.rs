@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, MouseButton, Touch, TouchPhase, WindowEvent},
+};
+
+use crate::rendering_engine::{hit_test_scene, Handle, SceneBundle};
+
+/// The subset of window input that the engine needs in order to work out
+/// which shapes are being interacted with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoEvent {
+    CursorMoved {
+        position: PhysicalPosition<f64>,
+    },
+    CursorLeft,
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    /// A finger touching the screen; `id` tells simultaneous touches apart.
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: PhysicalPosition<f64>,
+    },
+    /// Raw, unaccelerated mouse movement, straight from the device.
+    ///
+    /// Unlike cursor moves, this keeps arriving while the cursor is stuck
+    /// against the edge of the screen (or grabbed; see
+    /// [`RenderingEngine::set_cursor_grab`](super::RenderingEngine::set_cursor_grab)).
+    RawMouseMotion {
+        delta: (f64, f64),
+    },
+}
+
+impl IoEvent {
+    pub fn from_window_event(window_event: &WindowEvent) -> Option<Self> {
+        match *window_event {
+            WindowEvent::CursorMoved { position, .. } => Some(Self::CursorMoved { position }),
+            WindowEvent::CursorLeft { .. } => Some(Self::CursorLeft),
+            WindowEvent::MouseInput { state, button, .. } => {
+                Some(Self::MouseInput { state, button })
+            }
+            WindowEvent::Touch(Touch {
+                id,
+                phase,
+                location,
+                ..
+            }) => Some(Self::Touch {
+                id,
+                phase,
+                position: location,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn from_device_event(device_event: &DeviceEvent) -> Option<Self> {
+        match *device_event {
+            DeviceEvent::MouseMotion { delta } => Some(Self::RawMouseMotion { delta }),
+            _ => None,
+        }
+    }
+}
+
+/// A mouse button press or release on top of an object, as passed to its
+/// [`Shape::on_mouse_input`](crate::primitives::Shape::on_mouse_input)
+/// callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseInput {
+    /// The object which the cursor was over.
+    pub handle: Handle,
+    pub state: ElementState,
+    pub button: MouseButton,
+    pub position: PhysicalPosition<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractionKind {
+    Hovered,
+    /// The cursor has moved onto the object (i.e., it wasn't the topmost
+    /// object under the cursor before, but now it is).
+    Entered,
+    /// The cursor has moved off of the object, or the object is no longer
+    /// the topmost one under it.
+    Exited,
+    Pressed(MouseButton),
+    Released(MouseButton),
+    /// The touch with the given id has started, moved, ended or been
+    /// cancelled on top of the object.
+    Touched {
+        id: u64,
+        phase: TouchPhase,
+    },
+}
+
+#[derive(Default)]
+pub struct IoBundle {
+    cursor_position: Option<PhysicalPosition<f64>>,
+    hovered: Option<Handle>,
+    /// Every ongoing touch, along with the topmost object under it.
+    touches: HashMap<u64, Option<Handle>>,
+    hit_tolerance: f32,
+    interactions: Vec<(Handle, InteractionKind)>,
+    /// The raw mouse motion accumulated since it was last taken.
+    mouse_motion: (f64, f64),
+    /// The last click on an object, until its callback has been run.
+    mouse_input: Option<MouseInput>,
+}
+
+impl IoBundle {
+    /// Hit-tests the event against the scene and records the resulting
+    /// interactions.
+    ///
+    /// Returns whether anything noteworthy happened, i.e., whether the hovered
+    /// (or touched) object changed or an object was pressed, released or
+    /// touched. Cursor and touch moves which stay on the same object (or off
+    /// of every object) don't count, and consecutive
+    /// [`InteractionKind::Hovered`] interactions (or moves of the same touch)
+    /// on the same object are only recorded once.
+    ///
+    /// Raw mouse motion isn't hit-tested (and never counts); it's only
+    /// accumulated (see [`IoBundle::take_mouse_motion`]).
+    pub(super) fn register(
+        &mut self,
+        io_event: IoEvent,
+        scene_bundle: &SceneBundle,
+        size: PhysicalSize<u32>,
+    ) -> bool {
+        let interaction_kind = match io_event {
+            IoEvent::CursorMoved { position } => {
+                self.cursor_position = Some(position);
+                InteractionKind::Hovered
+            }
+            IoEvent::CursorLeft => {
+                self.cursor_position = None;
+                return self.update_hovered(None);
+            }
+            IoEvent::Touch {
+                id,
+                phase,
+                position,
+            } => return self.register_touch(id, phase, position, scene_bundle, size),
+            IoEvent::RawMouseMotion { delta: (x, y) } => {
+                self.mouse_motion.0 += x;
+                self.mouse_motion.1 += y;
+                return false;
+            }
+            IoEvent::MouseInput {
+                state: ElementState::Pressed,
+                button,
+            } => InteractionKind::Pressed(button),
+            IoEvent::MouseInput {
+                state: ElementState::Released,
+                button,
+            } => InteractionKind::Released(button),
+        };
+        let hit = self
+            .cursor_position
+            .and_then(|position| hit_test_scene(scene_bundle, position, size, self.hit_tolerance));
+        if let (IoEvent::MouseInput { state, button }, Some(handle), Some(position)) =
+            (io_event, hit, self.cursor_position)
+        {
+            self.mouse_input = Some(MouseInput {
+                handle,
+                state,
+                button,
+                position,
+            });
+        };
+        let changed = match interaction_kind {
+            InteractionKind::Hovered => self.update_hovered(hit),
+            _ => hit.is_some(),
+        };
+        let Some(handle) = hit else {
+            return changed;
+        };
+        self.push_interaction(handle, interaction_kind);
+        changed
+    }
+
+    /// Hit-tests a single touch point, independently of the cursor and of any
+    /// other touches.
+    fn register_touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: PhysicalPosition<f64>,
+        scene_bundle: &SceneBundle,
+        size: PhysicalSize<u32>,
+    ) -> bool {
+        let hit = hit_test_scene(scene_bundle, position, size, self.hit_tolerance);
+        let changed = match phase {
+            TouchPhase::Started => {
+                self.touches.insert(id, hit);
+                hit.is_some()
+            }
+            TouchPhase::Moved => self.touches.insert(id, hit) != Some(hit),
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&id).flatten().is_some() || hit.is_some()
+            }
+        };
+        if let Some(handle) = hit {
+            self.push_interaction(handle, InteractionKind::Touched { id, phase });
+        };
+        changed
+    }
+
+    /// Records an interaction, unless it's a repeat of the last one (i.e., the
+    /// same object being hovered over, or the same touch moving over it).
+    fn push_interaction(&mut self, handle: Handle, interaction_kind: InteractionKind) {
+        let repeatable = matches!(
+            interaction_kind,
+            InteractionKind::Hovered
+                | InteractionKind::Touched {
+                    phase: TouchPhase::Moved,
+                    ..
+                }
+        );
+        if repeatable && self.interactions.last() == Some(&(handle, interaction_kind)) {
+            return;
+        };
+        self.interactions.push((handle, interaction_kind));
+    }
+
+    /// Records enter/exit interactions if the hovered object has changed, and
+    /// returns whether it has.
+    fn update_hovered(&mut self, hovered: Option<Handle>) -> bool {
+        if self.hovered == hovered {
+            return false;
+        };
+        if let Some(handle) = self.hovered {
+            self.interactions.push((handle, InteractionKind::Exited));
+        };
+        if let Some(handle) = hovered {
+            self.interactions.push((handle, InteractionKind::Entered));
+        };
+        self.hovered = hovered;
+        true
+    }
+
+    pub(super) fn hit_tolerance(&self) -> f32 {
+        self.hit_tolerance
+    }
+
+    pub(super) fn set_hit_tolerance(&mut self, hit_tolerance: f32) {
+        self.hit_tolerance = hit_tolerance.max(0.0);
+    }
+
+    pub(super) fn hovered(&self) -> Option<Handle> {
+        self.hovered
+    }
+
+    pub(super) fn touched(&self, id: u64) -> Option<Handle> {
+        self.touches.get(&id).copied().flatten()
+    }
+
+    pub(super) fn take_mouse_motion(&mut self) -> (f64, f64) {
+        std::mem::take(&mut self.mouse_motion)
+    }
+
+    pub(super) fn take_mouse_input(&mut self) -> Option<MouseInput> {
+        self.mouse_input.take()
+    }
+
+    pub(super) fn interactions(&self) -> &[(Handle, InteractionKind)] {
+        &self.interactions
+    }
+
+    pub(super) fn end_frame(&mut self) {
+        self.interactions.clear();
+    }
+}
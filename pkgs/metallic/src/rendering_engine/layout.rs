@@ -0,0 +1,147 @@
+use euclid::default::{Box2D, Point2D, Size2D};
+use glyphon::FontSystem;
+use lyon::algorithms::aabb::bounding_box;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    primitives::{ndc_to_abs, CoordinateSpace, DrawStyle, Object, ObjectKind, TextAnchor},
+    rendering_engine::{
+        draw_order, glyph_bundle::measure_text, Handle, RenderingEngine, SceneBundle,
+    },
+};
+
+impl RenderingEngine {
+    /// Calls `f` with every object (in draw order), its handle, and its bounds
+    /// in absolute coordinates, letting it change the object in place.
+    ///
+    /// This is meant for layout passes on top of the renderer: measure every
+    /// object, then move it into place, all in one go. Text is measured the
+    /// same way as by [`RenderingEngine::measure_text`]. Whatever `f` changes
+    /// shows up on the next frame; objects whose z (or z-bias) was changed
+    /// are moved to their new place in the draw order.
+    pub fn for_each_object_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(Handle, &mut Object, Box2D<f32>),
+    {
+        let size = self.wgpu_bundle.inner_size();
+        let scale_factor = self.scene_bundle.scale_factor;
+        let font_system = &mut self.glyph_bundle.font_system;
+        self.scene_bundle.for_each_object_mut(
+            |object| object_bounds(object, font_system, size, scale_factor),
+            f,
+        );
+        // Text is reshaped if it changed, so there is no need to clear
+        // `text_buffers` here.
+    }
+}
+
+impl SceneBundle {
+    pub(super) fn for_each_object_mut<B, F>(&mut self, mut bounds: B, mut f: F)
+    where
+        B: FnMut(&Object) -> Box2D<f32>,
+        F: FnMut(Handle, &mut Object, Box2D<f32>),
+    {
+        for (handle, object, _) in &mut self.objects {
+            // Only the geometry of shapes and meshes is cached, so only theirs
+            // has to be compared to find out whether it's gone stale.
+            let cached = match self.tessellations.contains_key(handle) {
+                true => Some(object.kind.clone()),
+                false => None,
+            };
+            let object_bounds = bounds(object);
+            f(*handle, object, object_bounds);
+            if cached.is_some_and(|kind| !same_geometry(&kind, &object.kind)) {
+                self.tessellations.remove(handle);
+            };
+        }
+        *self.hit_index.get_mut() = None;
+        // Stable, so objects which kept their z stay in insertion order.
+        self.objects
+            .sort_by(|(_, a, a_layer), (_, b, b_layer)| draw_order((a, *a_layer), (b, *b_layer)));
+        self.dirty = true;
+    }
+}
+
+/// Returns the bounds of `object` in absolute (logical) coordinates, as laid
+/// out in a `size`d window at the given scale factor.
+pub(super) fn object_bounds(
+    object: &Object,
+    font_system: &mut FontSystem,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Box2D<f32> {
+    match &object.kind {
+        ObjectKind::Text(text) => {
+            let metrics = measure_text(font_system, text, size, scale_factor);
+            let top = match text.anchor {
+                TextAnchor::Top => text.position.y,
+                TextAnchor::Baseline => text.position.y - metrics.baseline,
+            };
+            let bounds = Box2D::from_origin_and_size(
+                Point2D::new(text.position.x + metrics.left, top),
+                Size2D::new(metrics.width, metrics.height),
+            );
+            // Rotated text covers (the bounding box of) its rotated bounds.
+            Box2D::from_points(
+                [
+                    bounds.min,
+                    Point2D::new(bounds.max.x, bounds.min.y),
+                    Point2D::new(bounds.min.x, bounds.max.y),
+                    bounds.max,
+                ]
+                .map(|corner| text.rotate(corner)),
+            )
+        }
+        ObjectKind::Shape(_) | ObjectKind::Mesh(_) | ObjectKind::Image(_) => {
+            hit_bounds(object, size, scale_factor).expect("Only text isn't hit-tested")
+        }
+    }
+}
+
+/// Like [`object_bounds`], but without text (which isn't hit-tested, and
+/// would have to be shaped to be measured).
+pub(super) fn hit_bounds(
+    object: &Object,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Option<Box2D<f32>> {
+    let bounds = match &object.kind {
+        ObjectKind::Shape(shape) => {
+            let bounds = bounding_box(shape.path.iter());
+            let bounds = match shape.coordinate_space {
+                CoordinateSpace::Absolute => bounds,
+                // NDC's y-axis points up, so the corners swap vertically.
+                CoordinateSpace::Ndc => Box2D::from_points(
+                    [bounds.min, bounds.max].map(|point| ndc_to_abs(point, size) / scale_factor),
+                ),
+            };
+            // Half of the line sticks out of the path (ignoring miters).
+            match shape.style {
+                DrawStyle::Stroke { width, .. } if !shape.is_empty() => {
+                    bounds.inflate(width / 2.0, width / 2.0)
+                }
+                _ => bounds,
+            }
+        }
+        ObjectKind::Mesh(mesh) => Box2D::from_points(mesh.vertices()),
+        ObjectKind::Image(image) => image.rect,
+        ObjectKind::Text(_) => return None,
+    };
+    Some(bounds)
+}
+
+/// Whether two objects would be tessellated into the same geometry.
+fn same_geometry(a: &ObjectKind, b: &ObjectKind) -> bool {
+    match (a, b) {
+        (ObjectKind::Shape(a), ObjectKind::Shape(b)) => {
+            a.coordinate_space == b.coordinate_space
+                && a.fill_rule == b.fill_rule
+                && a.style == b.style
+                && a.path.iter().eq(b.path.iter())
+        }
+        (ObjectKind::Mesh(a), ObjectKind::Mesh(b)) => {
+            a.vertices() == b.vertices() && a.indices() == b.indices()
+        }
+        _ => false,
+    }
+}
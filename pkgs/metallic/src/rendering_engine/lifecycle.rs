@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use crate::rendering_engine::{texture_pool::TexturePool, RenderingEngine};
+
+/// A [`TickHook`] of any context `C`; outside of tests, `C` is always the
+/// engine.
+///
+/// [`TickHook`]: crate::rendering_engine::TickHook
+type Tick<C> = Box<dyn FnMut(&mut C, Duration) -> bool>;
+
+/// The tick hook of `C` (i.e., of the engine, outside of tests), along with
+/// what's needed to time it.
+pub(super) struct TickBundle<C = RenderingEngine> {
+    tick: Option<Tick<C>>,
+    /// Bumped whenever the hook is set or cleared, so that a hook which does
+    /// either while it's running isn't put back afterwards.
+    generation: u64,
+    /// When the last tick (of the animation that is currently running, if
+    /// any) happened.
+    last_tick: Option<Instant>,
+}
+
+impl<C> Default for TickBundle<C> {
+    fn default() -> Self {
+        Self {
+            tick: None,
+            generation: 0,
+            last_tick: None,
+        }
+    }
+}
+
+impl<C> TickBundle<C> {
+    pub(super) fn set(&mut self, tick: Tick<C>) {
+        self.tick = Some(tick);
+        self.generation += 1;
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.tick = None;
+        self.last_tick = None;
+        self.generation += 1;
+    }
+
+    /// Runs the tick hook of `context` (whose tick bundle `tick_bundle`
+    /// returns) at `now`, returning whether it's still animating, or `None`
+    /// if there is no hook.
+    ///
+    /// The hook is taken out while it runs, since it gets `context` mutably;
+    /// it's put back afterwards, unless it set or cleared the hook itself.
+    pub(super) fn tick(
+        context: &mut C,
+        tick_bundle: fn(&mut C) -> &mut Self,
+        now: Instant,
+    ) -> Option<bool> {
+        let this = tick_bundle(context);
+        let mut tick = this.tick.take()?;
+        let (generation, elapsed) = (this.generation, this.elapsed(now));
+        let animating = tick(context, elapsed);
+        let this = tick_bundle(context);
+        if this.generation == generation {
+            this.tick = Some(tick);
+        };
+        // A cleared hook's animation is over, whatever it returned.
+        if this.tick.is_some() {
+            this.record(now, animating);
+        };
+        Some(animating)
+    }
+
+    /// Returns how much time has passed since the last tick; nothing, if no
+    /// animation was running.
+    pub(super) fn elapsed(&self, now: Instant) -> Duration {
+        self.last_tick
+            .map_or(Duration::ZERO, |last_tick| now - last_tick)
+    }
+
+    /// Records a tick at `now`, which only counts if the animation keeps on
+    /// running; otherwise, the next animation starts from scratch.
+    pub(super) fn record(&mut self, now: Instant, animating: bool) {
+        self.last_tick = match animating {
+            true => Some(now),
+            false => None,
+        };
+    }
+}
+
+/// Counterparts to the [`ApplicationHandler`] callbacks which metallic needs
+/// to hear about; forward them to the engine as-is.
+///
+/// [`ApplicationHandler`]: winit::application::ApplicationHandler
+impl RenderingEngine {
+    /// Registers a hook which advances animations, e.g., by moving objects
+    /// around. It's run on every [`RenderingEngine::about_to_wait`], with the
+    /// time since the previous tick (or zero, when an animation starts).
+    ///
+    /// As long as the hook returns `true`, every tick requests another frame
+    /// (and hence another tick), so the animation runs at the display's
+    /// refresh rate. Once it returns `false`, the engine goes back to only
+    /// rendering when something changes.
+    pub fn set_on_tick<F>(&mut self, tick: F)
+    where
+        F: FnMut(&mut RenderingEngine, Duration) -> bool + 'static,
+    {
+        self.tick_bundle.set(Box::new(tick));
+    }
+
+    /// Removes the tick hook, which may also be done from within the hook
+    /// itself.
+    pub fn clear_on_tick(&mut self) {
+        self.tick_bundle.clear();
+    }
+
+    /// To be called from [`ApplicationHandler::about_to_wait`]: runs the tick
+    /// hook (see [`RenderingEngine::set_on_tick`]), if there is one.
+    ///
+    /// [`ApplicationHandler::about_to_wait`]: winit::application::ApplicationHandler::about_to_wait
+    pub fn about_to_wait(&mut self) {
+        let animating = TickBundle::tick(self, |engine| &mut engine.tick_bundle, Instant::now());
+        if animating == Some(true) {
+            self.redraw();
+        };
+    }
+
+    /// To be called from [`ApplicationHandler::memory_warning`]: drops every
+    /// cache which can be rebuilt.
+    ///
+    /// On mobile platforms, the OS sends this before killing apps which use
+    /// too much memory. Most of what the engine holds on to can be rebuilt
+    /// from the scene, so it all goes: the glyph atlas and its rasterized
+    /// glyphs (like [`RenderingEngine::clear_glyph_cache`]), shaped text,
+    /// tessellated shapes, uploaded images and pooled offscreen textures. The
+    /// next frame is redrawn from scratch, and is slower for it.
+    ///
+    /// [`ApplicationHandler::memory_warning`]: winit::application::ApplicationHandler::memory_warning
+    pub fn memory_warning(&mut self) {
+        self.glyph_bundle
+            .clear_cache(&self.wgpu_bundle.device, &self.wgpu_bundle.queue);
+        self.glyph_bundle.text_buffers.clear();
+        self.scene_bundle.tessellations.clear();
+        self.image_bundle.clear();
+        self.texture_pool = TexturePool::default();
+        self.scene_bundle.dirty = true;
+        self.redraw();
+    }
+}
@@ -1,178 +1,2015 @@
+pub mod benchmark;
+mod buffer_bundle;
+mod compositing;
+mod fps;
+mod glyph_bundle;
+mod image_bundle;
+mod immediate;
+pub mod io;
+mod layout;
+mod lifecycle;
+mod offscreen;
+mod pacing;
+mod rotated_text;
+mod tessellation;
+#[cfg(test)]
+mod tests;
+mod texture_pool;
 mod wgpu_bundle;
 
-use bytemuck::cast_slice;
-use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, VertexBuffers};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use euclid::default::Point2D;
+use image::DynamicImage;
+use lyon::{
+    geom::{LineSegment, Triangle},
+    math::{point, Transform},
+    tessellation::VertexBuffers,
+};
+use uuid::Uuid;
 use wgpu::{
-    util::{BufferInitDescriptor, DeviceExt},
-    Buffer, BufferUsages, Color, CommandEncoderDescriptor, IndexFormat, LoadOp, Operations,
-    RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TextureViewDescriptor,
+    Adapter, Color, CommandEncoder, CommandEncoderDescriptor, CompositeAlphaMode, Device, Extent3d,
+    Features, IndexFormat, LoadOp, Operations, PresentMode, Queue, RenderPass,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, StoreOp, Surface,
+    SurfaceConfiguration, SurfaceError, TextureUsages, TextureView, TextureViewDescriptor,
+};
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event_loop::ActiveEventLoop,
+    window::{CursorGrabMode, Window},
 };
-use winit::{dpi::PhysicalSize, event_loop::ActiveEventLoop};
 
 use crate::{
-    primitives::{to_vertex, Ctor, Shape, Vertex},
-    rendering_engine::wgpu_bundle::{new_wgpu_bundle, WgpuBundle},
-    MetallicResult,
+    hit_engine::{BoundingBox, HitEngine},
+    primitives::{
+        abs_to_ndc, ndc_to_abs, to_unscaled_vertex, to_vertex, Brush, CoordinateSpace, DrawStyle,
+        Image, MouseInputCallback, Object, ObjectKind, Shape, Text, TextMetrics, Vertex,
+    },
+    rendering_engine::{
+        buffer_bundle::{new_buffer_bundle, prepare_geometry, BufferBundle},
+        compositing::{new_compositing_bundle, CompositingBundle},
+        fps::{FpsBundle, FPS_HANDLE},
+        glyph_bundle::{load_font_file, measure_text, new_glyph_bundle, prepare_text, GlyphBundle},
+        image_bundle::{
+            new_image_bundle, prepare_images, textured_triangles, ImageBundle, TexturedDraw,
+        },
+        io::{InteractionKind, IoBundle, IoEvent},
+        layout::hit_bounds,
+        lifecycle::TickBundle,
+        pacing::PacingBundle,
+        rotated_text::prepare_rotated_text,
+        tessellation::{tessellate_shape, Tessellators},
+        texture_pool::{TextureKey, TexturePool},
+        wgpu_bundle::{
+            new_shape_pipeline, new_wgpu_bundle, new_wgpu_bundle_from_parts, new_window,
+            select_present_mode, select_sample_count, validate_size, WgpuBundle,
+        },
+    },
+    MetallicError, MetallicResult,
 };
 
+const TOLERANCE: f32 = 0.02;
+
+/// The maximum number of layers; i.e., the deepest layer is `MAX_LAYERS - 1`.
+pub const MAX_LAYERS: usize = 4096;
+
+/// Identifies an object that has been added to a [`RenderingEngine`].
+pub type Handle = Uuid;
+
+/// A user-supplied callback which records extra draw commands into the
+/// engine's render pass.
+pub type RenderHook = Box<dyn for<'pass> FnMut(&mut RenderPass<'pass>, &Device, &Queue)>;
+
+/// A user-supplied callback which advances animations by the given amount of
+/// time, returning whether they are still running.
+pub type TickHook = Box<dyn FnMut(&mut RenderingEngine, Duration) -> bool>;
+
 pub struct SceneBundle {
     background_color: Color,
-    shapes: Vec<(Shape, usize)>,
+    /// Stretched over the whole surface, underneath everything else.
+    background_image: Option<Arc<DynamicImage>>,
+    objects: Vec<(Handle, Object, usize)>,
     layer: usize,
-    fill_tessellator: FillTessellator,
+    hidden_layers: HashSet<usize>,
+    /// How many physical pixels there are to a logical one.
+    ///
+    /// Absolute coordinates (and stroke widths, font sizes, etc.) are in
+    /// logical pixels, and are scaled by this when drawn, so that the scene
+    /// comes out the same size on HiDPI displays.
+    scale_factor: f32,
+    tessellators: Tessellators,
+    /// The tessellated geometry of every shape and mesh, so that objects
+    /// which haven't changed aren't re-tessellated every frame.
+    ///
+    /// Positions are kept in the object's own coordinate space and without
+    /// color, so that neither resizing nor recoloring invalidates them (save
+    /// for strokes of NDC shapes, whose width depends on the window's size
+    /// and scale factor).
+    tessellations: HashMap<Handle, Tessellation>,
+    tessellation_stats: TessellationStats,
+    /// The bounds of every object which can be hit, so that hit-testing only
+    /// has to look closely at the objects around a point.
+    ///
+    /// Built on the first hit test, as laid out at that test's size, and
+    /// rebuilt whenever the size differs (NDC objects move along with it).
+    /// Objects which are added or removed are kept track of; anything which
+    /// changes objects in place drops the index instead.
+    hit_index: RefCell<Option<HitIndex>>,
+    /// Whether anything has changed since the last frame was rendered.
+    ///
+    /// Starts out as `true` so that the very first frame is always rendered.
+    dirty: bool,
+}
+
+impl SceneBundle {
+    fn new(background_color: Color) -> Self {
+        Self {
+            background_color,
+            background_image: None,
+            objects: vec![],
+            layer: 0,
+            hidden_layers: HashSet::new(),
+            scale_factor: 1.0,
+            tessellators: Tessellators::default(),
+            tessellations: HashMap::new(),
+            tessellation_stats: TessellationStats::default(),
+            hit_index: RefCell::new(None),
+            dirty: true,
+        }
+    }
+
+    /// Returns every object whose bounds are within `hit_tolerance` of
+    /// `position`, as laid out in a `size`d window.
+    fn hit_candidates(
+        &self,
+        position: Point2D<f32>,
+        size: PhysicalSize<u32>,
+        hit_tolerance: f32,
+    ) -> HashSet<Handle> {
+        let mut hit_index = self.hit_index.borrow_mut();
+        let hit_index = match &mut *hit_index {
+            Some(hit_index) if hit_index.size == size => hit_index,
+            hit_index => hit_index.insert(HitIndex {
+                size,
+                hit_engine: HitEngine::from_boxes(self.objects.iter().filter_map(
+                    |&(handle, ref object, layer)| {
+                        bounding_box(handle, object, layer, size, self.scale_factor)
+                    },
+                )),
+            }),
+        };
+        // Outlines are flattened before they are hit-tested, which may move
+        // them out of their bounds by up to the flattening tolerance.
+        hit_index
+            .hit_engine
+            .hit_search_with_tolerance(position, hit_tolerance + TOLERANCE)
+    }
+
+    fn push_layer(&mut self) -> MetallicResult<()> {
+        if self.layer + 1 >= MAX_LAYERS {
+            return Err(MetallicError::TooManyLayersError {
+                max_layers: MAX_LAYERS,
+            });
+        };
+        self.layer += 1;
+        Ok(())
+    }
+
+    /// Inserts an object into the current layer.
+    ///
+    /// `objects` is kept sorted by layer (ascending), then by z (see
+    /// [`Object::z`]) and then by z-bias (see [`Object::z_bias`]), and objects
+    /// which tie on all three are kept in insertion order; this is also the
+    /// order in which they are drawn.
+    fn insert(&mut self, handle: Handle, object: Object) {
+        self.insert_into(handle, object, self.layer);
+    }
+
+    /// Inserts many objects into the current layer at once, in the same order
+    /// as inserting them one by one would.
+    ///
+    /// Instead of searching for (and shifting everything after) each object's
+    /// place, they are all appended and `objects` is then sorted once, which
+    /// takes O((n + m) log(n + m)) rather than O(m * n) for m objects added to
+    /// a scene of n (and close to O(n + m log m), since `objects` is already
+    /// sorted).
+    fn insert_all<I>(&mut self, objects: I)
+    where
+        I: IntoIterator<Item = (Handle, Object)>,
+    {
+        let count = self.objects.len();
+        let layer = self.layer;
+        self.objects.extend(
+            objects
+                .into_iter()
+                .map(|(handle, object)| (handle, object, layer)),
+        );
+        if let Some(hit_index) = self.hit_index.get_mut() {
+            for (handle, object, layer) in &self.objects[count..] {
+                if let Some(bounding_box) =
+                    bounding_box(*handle, object, *layer, hit_index.size, self.scale_factor)
+                {
+                    hit_index.hit_engine.insert(bounding_box);
+                };
+            }
+        };
+        // Stable, so new objects go after the ones they tie with, in order.
+        self.objects
+            .sort_by(|(_, a, a_layer), (_, b, b_layer)| draw_order((a, *a_layer), (b, *b_layer)));
+        self.dirty |= self.objects.len() != count;
+    }
+
+    /// Inserts an object at a fractional z-order: into layer `z.floor()`, with
+    /// the fractional part as its z within that layer.
+    ///
+    /// E.g., at a z of 1.5, the object is drawn above everything in layer 1
+    /// (with the default z of 0), but still below layer 2.
+    fn insert_at(&mut self, handle: Handle, mut object: Object, z: f32) -> MetallicResult<()> {
+        if !z.is_finite() || z < 0.0 {
+            return Err(MetallicError::InvalidZError { z });
+        };
+        let layer = z.floor() as usize;
+        check_layer(layer)?;
+        object.z = z.fract();
+        self.insert_into(handle, object, layer);
+        Ok(())
+    }
+
+    fn insert_into(&mut self, handle: Handle, object: Object, layer: usize) {
+        let index = self
+            .objects
+            .partition_point(|(_, curr_object, curr_layer)| {
+                draw_order((curr_object, *curr_layer), (&object, layer)).is_le()
+            });
+        if let Some(hit_index) = self.hit_index.get_mut() {
+            if let Some(bounding_box) =
+                bounding_box(handle, &object, layer, hit_index.size, self.scale_factor)
+            {
+                hit_index.hit_engine.insert(bounding_box);
+            };
+        };
+        self.objects.insert(index, (handle, object, layer));
+        self.dirty = true;
+    }
+
+    fn mouse_input_callback(&self, handle: Handle) -> Option<MouseInputCallback> {
+        match &self.object(handle)?.kind {
+            ObjectKind::Shape(shape) => shape.on_mouse_input.clone(),
+            ObjectKind::Mesh(_) | ObjectKind::Text(_) | ObjectKind::Image(_) => None,
+        }
+    }
+
+    fn object(&self, handle: Handle) -> Option<&Object> {
+        self.objects
+            .iter()
+            .find(|&&(curr_handle, _, _)| curr_handle == handle)
+            .map(|(_, object, _)| object)
+    }
+
+    /// Like [`SceneBundle::object`], but assumes that the object is about to
+    /// be changed.
+    fn object_mut(&mut self, handle: Handle) -> Option<&mut Object> {
+        let (_, object, _) = self
+            .objects
+            .iter_mut()
+            .find(|&&mut (curr_handle, _, _)| curr_handle == handle)?;
+        self.tessellations.remove(&handle);
+        *self.hit_index.get_mut() = None;
+        self.dirty = true;
+        Some(object)
+    }
+
+    fn remove(&mut self, handle: Handle) -> Option<Object> {
+        let index = self
+            .objects
+            .iter()
+            .position(|&(curr_handle, _, _)| curr_handle == handle)?;
+        let (_, object, _) = self.objects.remove(index);
+        self.tessellations.remove(&handle);
+        if let Some(hit_index) = self.hit_index.get_mut() {
+            hit_index.hit_engine.remove(handle);
+        };
+        self.dirty = true;
+        Some(object)
+    }
+
+    fn object_vertex_count(
+        &mut self,
+        handle: Handle,
+        size: PhysicalSize<u32>,
+    ) -> MetallicResult<Option<usize>> {
+        match self.tessellations.get(&handle) {
+            Some(tessellation) if tessellation.fits(size) => {
+                Ok(Some(tessellation.geometry.vertices.len()))
+            }
+            _ => self.object_vertex_count_at(handle, TOLERANCE, size),
+        }
+    }
+
+    /// Tessellates the object with the given handle at `tolerance` (bypassing
+    /// the cache), as laid out in a `size`d window, and counts its vertices.
+    fn object_vertex_count_at(
+        &mut self,
+        handle: Handle,
+        tolerance: f32,
+        size: PhysicalSize<u32>,
+    ) -> MetallicResult<Option<usize>> {
+        let Some((_, object, _)) = self
+            .objects
+            .iter()
+            .find(|&&(curr_handle, _, _)| curr_handle == handle)
+        else {
+            return Ok(None);
+        };
+        let vertex_count = match &object.kind {
+            ObjectKind::Shape(shape) => tessellate_shape(
+                &mut self.tessellators,
+                shape,
+                tolerance,
+                size,
+                self.scale_factor,
+            )?
+            .vertices
+            .len(),
+            ObjectKind::Mesh(mesh) => mesh.vertices().len(),
+            ObjectKind::Text(_) | ObjectKind::Image(_) => 0,
+        };
+        Ok(Some(vertex_count))
+    }
+
+    fn set_brush(&mut self, handle: Handle, brush: Brush) -> Option<Brush> {
+        let (_, object, _) = self
+            .objects
+            .iter_mut()
+            .find(|&&mut (curr_handle, _, _)| curr_handle == handle)?;
+        self.dirty |= object.brush != brush;
+        let previous = std::mem::replace(&mut object.brush, brush);
+        Some(previous)
+    }
+
+    /// Moves an object to `z` within its layer, on top of the objects it now
+    /// ties with, returning its previous z.
+    ///
+    /// Its cached geometry stays valid, since z doesn't affect it.
+    fn set_z(&mut self, handle: Handle, z: f32) -> Option<f32> {
+        let index = self
+            .objects
+            .iter()
+            .position(|&(curr_handle, _, _)| curr_handle == handle)?;
+        let (_, mut object, layer) = self.objects.remove(index);
+        let previous = std::mem::replace(&mut object.z, z);
+        self.insert_into(handle, object, layer);
+        Some(previous)
+    }
+
+    /// Lays the scene out at a new scale factor, returning whether it changed.
+    fn set_scale_factor(&mut self, scale_factor: f32) -> bool {
+        if self.scale_factor == scale_factor {
+            return false;
+        };
+        self.scale_factor = scale_factor;
+        // Only strokes of NDC shapes are tessellated in physical pixels; all
+        // other geometry is scaled once it's turned into vertices.
+        self.tessellations
+            .retain(|_, tessellation| tessellation.size.is_none());
+        *self.hit_index.get_mut() = None;
+        self.dirty = true;
+        true
+    }
+
+    fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        let changed = match visible {
+            true => self.hidden_layers.remove(&layer),
+            false => self.hidden_layers.insert(layer),
+        };
+        self.dirty |= changed;
+    }
+
+    fn clear(&mut self) {
+        self.dirty |= !self.objects.is_empty();
+        self.objects.clear();
+        self.tessellations.clear();
+        *self.hit_index.get_mut() = None;
+    }
+
+    /// Removes every object whose handle is in `handles`.
+    fn remove_all(&mut self, handles: &HashSet<Handle>) {
+        let count = self.objects.len();
+        self.objects
+            .retain(|(handle, _, _)| !handles.contains(handle));
+        if let Some(hit_index) = self.hit_index.get_mut() {
+            for &handle in handles {
+                hit_index.hit_engine.remove(handle);
+            }
+        };
+        self.dirty |= self.objects.len() != count;
+    }
+
+    /// Moves every object (and the visibility) of each layer to the layer
+    /// that `remap` returns for it, keeping objects within the same layer in
+    /// their relative order.
+    fn remap_layers<F>(&mut self, remap: F)
+    where
+        F: Fn(usize) -> usize,
+    {
+        for (_, _, layer) in &mut self.objects {
+            *layer = remap(*layer);
+        }
+        // Stable, so objects within a layer stay in insertion order.
+        self.objects
+            .sort_by(|(_, a, a_layer), (_, b, b_layer)| draw_order((a, *a_layer), (b, *b_layer)));
+        self.hidden_layers = self.hidden_layers.iter().copied().map(remap).collect();
+        *self.hit_index.get_mut() = None;
+        self.dirty = true;
+    }
+}
+
+struct HitIndex {
+    size: PhysicalSize<u32>,
+    hit_engine: HitEngine,
+}
+
+/// Returns the bounding box which `object` is hit-tested within, or `None` if
+/// it isn't hit-tested at all.
+fn bounding_box(
+    handle: Handle,
+    object: &Object,
+    layer: usize,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Option<BoundingBox> {
+    Some(BoundingBox {
+        id: handle,
+        bounds: hit_bounds(object, size, scale_factor)?,
+        layer,
+    })
+}
+
+/// Compares two objects (in the given layers) by when they are drawn: by
+/// layer, then by z and then by z-bias.
+fn draw_order((a, a_layer): (&Object, usize), (b, b_layer): (&Object, usize)) -> Ordering {
+    a_layer
+        .cmp(&b_layer)
+        .then(a.z.total_cmp(&b.z))
+        .then(a.z_bias.cmp(&b.z_bias))
+}
+
+/// Returns where `layer` ends up once layer `from` has been moved to `to`
+/// (with the layers in between shifting over by one to make room).
+fn moved_layer(layer: usize, from: usize, to: usize) -> usize {
+    match layer {
+        _ if layer == from => to,
+        _ if from < to && (from + 1..=to).contains(&layer) => layer - 1,
+        _ if to < from && (to..from).contains(&layer) => layer + 1,
+        _ => layer,
+    }
+}
+
+fn swapped_layer(layer: usize, a: usize, b: usize) -> usize {
+    match layer {
+        _ if layer == a => b,
+        _ if layer == b => a,
+        _ => layer,
+    }
+}
+
+fn check_layer(layer: usize) -> MetallicResult<()> {
+    match layer < MAX_LAYERS {
+        true => Ok(()),
+        false => Err(MetallicError::TooManyLayersError {
+            max_layers: MAX_LAYERS,
+        }),
+    }
 }
 
 pub struct RenderingEngine {
     wgpu_bundle: WgpuBundle,
+    glyph_bundle: GlyphBundle,
+    image_bundle: ImageBundle,
     scene_bundle: SceneBundle,
+    io_bundle: IoBundle,
+    hook_bundle: HookBundle,
+    tick_bundle: TickBundle,
+    pacing_bundle: PacingBundle,
+    compositing_bundle: CompositingBundle,
+    texture_pool: TexturePool,
+    buffer_bundle: BufferBundle,
+    fps_bundle: FpsBundle,
+    /// Every object drawn through the immediate-mode methods (e.g.,
+    /// [`RenderingEngine::draw_rect`]) since the last render.
+    immediate_handles: HashSet<Handle>,
+    redraw_pending: bool,
+    /// Set while the window has a zero-sized surface (e.g., while minimized
+    /// on Windows), which can't be configured or rendered to.
+    minimized: bool,
+    /// Set while the window is fully hidden from view.
+    occluded: bool,
+}
+
+#[derive(Default)]
+struct HookBundle {
+    pre_render: Option<RenderHook>,
+    post_render: Option<RenderHook>,
 }
 
 impl RenderingEngine {
+    /// Creates a window, along with an engine which renders into it.
+    ///
+    /// If `background_color` isn't fully opaque, the window is created as a
+    /// transparent one (where the platform supports it), so that only the
+    /// objects drawn into it are visible over the desktop.
+    ///
+    /// The window is created right away; the rest of the setup never blocks
+    /// (it only awaits wgpu's adapter and device requests), so the returned
+    /// future can be driven by any executor, or even polled by hand. Since it
+    /// borrows `event_loop`, though, it has to be driven to completion within
+    /// the event handler which called this. To finish setting up elsewhere
+    /// (e.g., on a `tokio` task), create the window yourself and use
+    /// [`RenderingEngine::for_window`] instead.
     pub async fn new(
         event_loop: &ActiveEventLoop,
         background_color: Color,
     ) -> MetallicResult<Self> {
-        let wgpu_bundle = new_wgpu_bundle(event_loop).await?;
-        Ok(Self {
+        Self::new_with_features(event_loop, background_color, Features::empty()).await
+    }
+
+    /// Like [`RenderingEngine::new`], but additionally requires the device to
+    /// support `required_features`.
+    ///
+    /// Fails with a [`MetallicError::RequestDeviceError`] if the adapter
+    /// doesn't support all of them; use [`crate::adapter_features`] to check
+    /// beforehand.
+    pub async fn new_with_features(
+        event_loop: &ActiveEventLoop,
+        background_color: Color,
+        required_features: Features,
+    ) -> MetallicResult<Self> {
+        let window = new_window(event_loop, background_color.a < 1.0)?;
+        Self::for_window(window, background_color, required_features).await
+    }
+
+    /// Creates an engine which renders into an existing window.
+    ///
+    /// Unlike [`RenderingEngine::new`], the returned future doesn't borrow
+    /// anything, so it can be spawned onto an async runtime and finished
+    /// outside of the event loop's handlers (handing the engine back to the
+    /// event loop, e.g., through an
+    /// [`EventLoopProxy`](winit::event_loop::EventLoopProxy)).
+    ///
+    /// If `background_color` isn't fully opaque, the window should have been
+    /// created as a transparent one, in order to see through to the desktop.
+    pub async fn for_window(
+        window: Arc<Window>,
+        background_color: Color,
+        required_features: Features,
+    ) -> MetallicResult<Self> {
+        let wgpu_bundle =
+            new_wgpu_bundle(window, background_color.a < 1.0, required_features).await?;
+        Ok(Self::from_wgpu_bundle(wgpu_bundle, background_color))
+    }
+
+    /// Creates an engine which renders into an existing window, using wgpu
+    /// objects that the application has already set up itself (e.g., because
+    /// it draws with wgpu too, and uses metallic as a UI layer on top).
+    ///
+    /// None of the usual setup happens; instead:
+    /// - `surface` has to have been created for `window`, from the same
+    ///   [`Instance`](wgpu::Instance) as `adapter` (the instance itself isn't
+    ///   needed beyond that).
+    /// - `device` and `queue` have to have been requested from `adapter`. They
+    ///   are shared, so the application can keep using them (e.g., from within
+    ///   [`RenderingEngine::set_pre_render`] hooks, which draw into the
+    ///   engine's render pass).
+    /// - `surface_configuration` has to use an sRGB format, and is applied to
+    ///   `surface` right away. From then on, the engine owns the surface: it
+    ///   reconfigures it on [`RenderingEngine::resize`], and acquires and
+    ///   presents its textures on [`RenderingEngine::render`].
+    ///
+    /// The window is kept alive for as long as the engine is.
+    pub fn from_wgpu(
+        window: Arc<Window>,
+        adapter: &Adapter,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        surface: Surface<'static>,
+        surface_configuration: SurfaceConfiguration,
+        background_color: Color,
+    ) -> MetallicResult<Self> {
+        let wgpu_bundle = new_wgpu_bundle_from_parts(
+            window,
+            adapter,
+            device,
+            queue,
+            surface,
+            surface_configuration,
+        )?;
+        Ok(Self::from_wgpu_bundle(wgpu_bundle, background_color))
+    }
+
+    fn from_wgpu_bundle(wgpu_bundle: WgpuBundle, background_color: Color) -> Self {
+        let mut glyph_bundle = new_glyph_bundle(
+            &wgpu_bundle.device,
+            &wgpu_bundle.queue,
+            wgpu_bundle.surface_configuration.format,
+        );
+        glyph_bundle.resize(&wgpu_bundle.queue, wgpu_bundle.inner_size());
+        let image_bundle = new_image_bundle(
+            &wgpu_bundle.device,
+            wgpu_bundle.surface_configuration.format,
+            wgpu_bundle.sample_count,
+        );
+        let compositing_bundle = new_compositing_bundle(&wgpu_bundle);
+        let buffer_bundle = new_buffer_bundle(&wgpu_bundle.device);
+        let mut scene_bundle = SceneBundle::new(background_color);
+        scene_bundle.set_scale_factor(wgpu_bundle.scale_factor() as _);
+        Self {
             wgpu_bundle,
-            scene_bundle: SceneBundle {
-                background_color,
-                shapes: vec![],
-                layer: 0,
-                fill_tessellator: FillTessellator::default(),
-            },
-        })
+            glyph_bundle,
+            image_bundle,
+            scene_bundle,
+            io_bundle: IoBundle::default(),
+            hook_bundle: HookBundle::default(),
+            tick_bundle: TickBundle::default(),
+            pacing_bundle: PacingBundle::default(),
+            compositing_bundle,
+            texture_pool: TexturePool::default(),
+            buffer_bundle,
+            fps_bundle: FpsBundle::default(),
+            immediate_handles: HashSet::new(),
+            redraw_pending: false,
+            minimized: false,
+            occluded: false,
+        }
     }
 
-    pub fn push_layer(&mut self) {
-        self.scene_bundle.layer = self
-            .scene_bundle
-            .layer
-            .checked_add(1)
-            .expect("Too many layers pushed");
+    /// Moves on to the next layer up, which objects are then added to.
+    ///
+    /// Fails (without changing the current layer) if that layer would be
+    /// beyond [`MAX_LAYERS`].
+    pub fn push_layer(&mut self) -> MetallicResult<()> {
+        self.scene_bundle.push_layer()
     }
 
     pub fn pop_layer(&mut self) {
         self.scene_bundle.layer = self.scene_bundle.layer.saturating_sub(1);
     }
 
-    pub fn add_shape(&mut self, shape: Shape) {
-        let layer = self.scene_bundle.layer;
-        let index = match self
-            .scene_bundle
-            .shapes
-            .binary_search_by(|&(_, curr_layer)| curr_layer.cmp(&layer))
-        {
-            Ok(index) => index + 1,
-            Err(index) => index,
+    /// Adds an object to the current layer, on top of everything already in
+    /// that layer (with the same [`Object::z`]).
+    pub fn add_object(&mut self, object: Object) -> Handle {
+        let handle = Uuid::new_v4();
+        self.scene_bundle.insert(handle, object);
+        handle
+    }
+
+    /// Adds many objects to the current layer at once, returning their handles
+    /// in the same order.
+    ///
+    /// The result is the same as adding them one by one with
+    /// [`RenderingEngine::add_object`], but much faster for large batches:
+    /// every call to that searches for the object's place in the draw order
+    /// and shifts everything after it over (O(n) in the number of objects
+    /// already in the scene), while this sorts all new objects in at once.
+    pub fn add_objects<I>(&mut self, objects: I) -> Vec<Handle>
+    where
+        I: IntoIterator<Item = Object>,
+    {
+        let objects = objects
+            .into_iter()
+            .map(|object| (Uuid::new_v4(), object))
+            .collect::<Vec<_>>();
+        let handles = objects.iter().map(|&(handle, _)| handle).collect();
+        self.scene_bundle.insert_all(objects);
+        handles
+    }
+
+    /// Adds an object at a fractional z-order, regardless of the current
+    /// layer: the integer part is the layer, and the fractional part orders
+    /// the object among the others in that layer (overriding
+    /// [`Object::z`]).
+    ///
+    /// This places objects between layers without renumbering them; e.g., at
+    /// a z of 1.5, an object is drawn above layer 1 but below layer 2. It is
+    /// shown, hidden and composited along with the rest of its layer.
+    ///
+    /// Fails if `z` is negative or not finite, or if the layer is beyond
+    /// [`MAX_LAYERS`].
+    pub fn add_object_at(&mut self, object: Object, z: f32) -> MetallicResult<Handle> {
+        let handle = Uuid::new_v4();
+        self.scene_bundle.insert_at(handle, object, z)?;
+        Ok(handle)
+    }
+
+    /// Removes the object with the given handle, returning it (or `None` if
+    /// there is no such object).
+    ///
+    /// Handles are never reused, so every other object's handle stays valid,
+    /// and the remaining objects keep their order.
+    pub fn remove_object(&mut self, handle: Handle) -> Option<Object> {
+        self.scene_bundle.remove(handle)
+    }
+
+    /// Returns the object with the given handle.
+    pub fn object(&self, handle: Handle) -> Option<&Object> {
+        self.scene_bundle.object(handle)
+    }
+
+    /// Returns the object with the given handle for changing it in place.
+    ///
+    /// The object is redrawn (and re-tessellated) on the next frame, whether
+    /// or not it was actually changed. Its place in the draw order stays the
+    /// same, though, even if its [`Object::z`] or [`Object::z_bias`] changes;
+    /// use [`RenderingEngine::set_z`] (or remove and re-add it) to move it.
+    pub fn object_mut(&mut self, handle: Handle) -> Option<&mut Object> {
+        self.scene_bundle.object_mut(handle)
+    }
+
+    /// Moves the given object to `z` within its layer (see [`Object::z`]),
+    /// returning its previous z (or `None`, changing nothing, if there is no
+    /// such object).
+    ///
+    /// The object stays on its layer, and is drawn over the objects of that
+    /// layer with a lower z and under those with a higher one. Among objects
+    /// with the same z (and [`Object::z_bias`]), it's drawn last, just like a
+    /// newly added object; the others keep their order.
+    pub fn set_z(&mut self, handle: Handle, z: f32) -> Option<f32> {
+        self.scene_bundle.set_z(handle, z)
+    }
+
+    /// Returns the brush which the given object is painted with.
+    pub fn brush(&self, handle: Handle) -> Option<Brush> {
+        self.scene_bundle
+            .object(handle)
+            .map(|object| object.brush.clone())
+    }
+
+    /// Repaints the given object with `brush`, returning its previous brush
+    /// (or `None`, changing nothing, if there is no such object).
+    pub fn set_brush(&mut self, handle: Handle, brush: Brush) -> Option<Brush> {
+        self.scene_bundle.set_brush(handle, brush)
+    }
+
+    /// Returns the color of an object painted with a solid brush.
+    pub fn color(&self, handle: Handle) -> Option<Color> {
+        match self.brush(handle)? {
+            Brush::Solid(color) => Some(color),
+            Brush::LinearGradient { .. } | Brush::RadialGradient { .. } | Brush::Texture { .. } => {
+                None
+            }
+        }
+    }
+
+    /// Repaints the given object with a solid brush of the given color.
+    ///
+    /// Shorthand for [`RenderingEngine::set_brush`] with [`Brush::Solid`].
+    pub fn set_color(&mut self, handle: Handle, color: Color) -> Option<Brush> {
+        self.set_brush(handle, Brush::Solid(color))
+    }
+
+    pub fn background_color(&self) -> Color {
+        self.scene_bundle.background_color
+    }
+
+    /// Changes the color which the surface is cleared to at the start of
+    /// every frame (e.g., to switch between light and dark themes), and
+    /// requests a redraw if it's a different one.
+    ///
+    /// Whether the window is transparent is decided once, when it's created,
+    /// so a translucent color only shows the desktop through windows which
+    /// were created with one (see [`RenderingEngine::new`]).
+    pub fn set_background_color(&mut self, background_color: Color) {
+        if self.scene_bundle.background_color == background_color {
+            return;
         };
-        self.scene_bundle.shapes.insert(index, (shape, layer));
+        self.scene_bundle.background_color = background_color;
+        self.scene_bundle.dirty = true;
+        self.redraw();
+    }
+
+    /// Sets (or, given `None`, removes) an image which is stretched over the
+    /// whole surface at the start of every frame.
+    ///
+    /// Unlike an [`Object::image`], the background image is always drawn
+    /// first (before any layer or [`RenderingEngine::set_pre_render`] hook)
+    /// and always resized to the surface. It is drawn on top of the background
+    /// color, so a translucent image lets the color shine through.
+    pub fn set_background_image(&mut self, image: Option<Arc<DynamicImage>>) {
+        self.scene_bundle.background_image = image;
+        self.scene_bundle.dirty = true;
+        self.redraw();
+    }
+
+    /// Shows or hides every object on the given layer.
+    ///
+    /// Hidden layers keep their objects; they are just skipped when the scene
+    /// is rendered.
+    pub fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        self.scene_bundle.set_layer_visible(layer, visible);
+    }
+
+    /// Swaps two layers, along with all of their objects, visibility and
+    /// opacity.
+    ///
+    /// Handles stay valid, and the layer which new objects are added to (see
+    /// [`RenderingEngine::push_layer`]) is left as is. Fails if either layer
+    /// is beyond [`MAX_LAYERS`].
+    pub fn swap_layers(&mut self, a: usize, b: usize) -> MetallicResult<()> {
+        check_layer(a)?;
+        check_layer(b)?;
+        self.remap_layers(|layer| swapped_layer(layer, a, b));
+        Ok(())
+    }
+
+    /// Moves layer `from` to `to`, shifting the layers in between over by
+    /// one (e.g., moving layer 0 to 2 turns layers 1 and 2 into 0 and 1).
+    ///
+    /// Just like with [`RenderingEngine::swap_layers`], everything on a layer
+    /// moves along with it.
+    pub fn move_layer(&mut self, from: usize, to: usize) -> MetallicResult<()> {
+        check_layer(from)?;
+        check_layer(to)?;
+        self.remap_layers(|layer| moved_layer(layer, from, to));
+        Ok(())
+    }
+
+    fn remap_layers<F>(&mut self, remap: F)
+    where
+        F: Fn(usize) -> usize,
+    {
+        self.scene_bundle.remap_layers(&remap);
+        self.compositing_bundle.remap_layers(&remap);
+    }
+
+    /// Tessellates every visible shape on the CPU and returns the resulting
+    /// triangles, without uploading anything to the GPU.
+    ///
+    /// Triangles are returned in render order (i.e., bottom layer first) and
+    /// their vertices are in absolute scene coordinates (the same space that
+    /// shapes are specified in), not normalized device coordinates; shapes in
+    /// [`CoordinateSpace::Ndc`] keep their points as given. Text is not
+    /// tessellated and so isn't included, and neither are textured objects
+    /// (which are drawn along with the images).
+    pub fn tessellate_all(&mut self) -> MetallicResult<Vec<(Vertex, Vertex, Vertex)>> {
+        let Geometry {
+            vertices, indices, ..
+        } = tessellate_scene_with(
+            &mut self.scene_bundle,
+            self.wgpu_bundle.inner_size(),
+            to_unscaled_vertex,
+        )?;
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                (
+                    vertices[triangle[0] as usize],
+                    vertices[triangle[1] as usize],
+                    vertices[triangle[2] as usize],
+                )
+            })
+            .collect();
+        Ok(triangles)
+    }
+
+    /// Returns how many vertices the object with the given handle is
+    /// tessellated into, or `None` if there is no such object.
+    ///
+    /// Meant for profiling: it points out which shapes use up most of the
+    /// vertex budget. Geometry which has already been tessellated (e.g., for
+    /// the last frame) is reused; anything else is tessellated on the spot,
+    /// whether or not its layer is visible. Text and images aren't tessellated
+    /// and so always report zero.
+    pub fn object_vertex_count(&mut self, handle: Handle) -> MetallicResult<Option<usize>> {
+        let size = self.wgpu_bundle.inner_size();
+        self.scene_bundle.object_vertex_count(handle, size)
+    }
+
+    /// Returns how often the tessellation cache has been hit and missed since
+    /// the engine was created.
+    ///
+    /// Meant for debugging: a scene which doesn't change should stop missing
+    /// altogether after its first frame. Every object keeps its geometry until
+    /// its shape changes or it's removed (e.g., by [`RenderingEngine::clear`]);
+    /// resizing only invalidates strokes of
+    /// [`CoordinateSpace::Ndc`] shapes.
+    pub fn tessellation_stats(&self) -> TessellationStats {
+        self.scene_bundle.tessellation_stats
     }
 
     pub fn clear(&mut self) {
-        self.scene_bundle.shapes.clear();
+        self.scene_bundle.clear();
     }
 
-    pub fn redraw(&self) {
-        self.wgpu_bundle.window.request_redraw();
+    /// Feeds a window input event to the engine.
+    ///
+    /// Cursor moves, mouse button presses/releases and touches are hit-tested
+    /// against the scene right away; the topmost visible shape under the
+    /// cursor (or under the touch point) is recorded and can be read back with
+    /// [`RenderingEngine::events_this_frame`].
+    ///
+    /// Mouse button presses and releases are also passed to the
+    /// [`Shape::on_mouse_input`] callback of the topmost shape under the
+    /// cursor, if it has one, before this returns. Callbacks only get the
+    /// event, not the engine (which is busy registering it), so in order to
+    /// change the scene, they should record what to change (e.g., in state
+    /// which they share with the application) and leave it to be applied once
+    /// this has returned.
+    ///
+    /// Returns whether the event changed anything which is worth reacting to
+    /// (i.e., the hovered or touched object changed, or an object was pressed,
+    /// released or touched). Cursor moves which don't are cheap, and need no
+    /// redraw.
+    pub fn register_io_event(&mut self, io_event: IoEvent) -> bool {
+        let size = self.wgpu_bundle.inner_size();
+        let changed = self.io_bundle.register(io_event, &self.scene_bundle, size);
+        let Some(mouse_input) = self.io_bundle.take_mouse_input() else {
+            return changed;
+        };
+        // Cloned out of the scene, which then isn't borrowed while it runs.
+        if let Some(callback) = self.scene_bundle.mouse_input_callback(mouse_input.handle) {
+            callback(&mouse_input);
+        };
+        changed
+    }
+
+    /// Returns every shape interaction registered since the last call to
+    /// [`RenderingEngine::render`], in the order the events arrived.
+    ///
+    /// This is a polling alternative to storing callbacks on shapes: react to
+    /// the returned handles directly, with full mutable access to the engine.
+    pub fn events_this_frame(&self) -> Vec<(Handle, InteractionKind)> {
+        self.io_bundle.interactions().to_vec()
+    }
+
+    /// Returns the topmost visible object under the cursor, as of the last
+    /// cursor event.
+    ///
+    /// Changes to this are also reported through
+    /// [`RenderingEngine::events_this_frame`] as [`InteractionKind::Exited`]
+    /// and [`InteractionKind::Entered`] interactions.
+    pub fn hovered(&self) -> Option<Handle> {
+        self.io_bundle.hovered()
+    }
+
+    /// Returns the topmost visible object under the touch with the given id,
+    /// as of that touch's last event.
+    ///
+    /// Touches are tracked from when they start until they end (or are
+    /// cancelled), independently of the cursor and of each other.
+    pub fn touched(&self, id: u64) -> Option<Handle> {
+        self.io_bundle.touched(id)
+    }
+
+    /// Returns the raw mouse motion (see [`IoEvent::RawMouseMotion`])
+    /// registered since the last call, and resets it to zero.
+    pub fn take_mouse_motion(&mut self) -> (f64, f64) {
+        self.io_bundle.take_mouse_motion()
+    }
+
+    /// Grabs (or releases) the cursor; see [`Window::set_cursor_grab`].
+    ///
+    /// Not every platform supports every mode (e.g., X11 can't lock the
+    /// cursor, and Windows can only confine it), in which case an error is
+    /// returned and the cursor is left as it was.
+    ///
+    /// [`Window::set_cursor_grab`]: winit::window::Window::set_cursor_grab
+    pub fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> MetallicResult<()> {
+        self.wgpu_bundle.set_cursor_grab(mode)?;
+        Ok(())
+    }
+
+    /// Returns every visible object at `position` (in physical pixels, e.g.,
+    /// from [`WindowEvent::CursorMoved`]), topmost first.
+    ///
+    /// Objects are hit-tested the same way as for
+    /// [`RenderingEngine::hovered`] (including the
+    /// [hit tolerance](RenderingEngine::set_hit_tolerance)), and text isn't
+    /// hit-tested at all. Only the objects whose bounds are around `position`
+    /// are looked at closely, so this stays cheap in large scenes.
+    ///
+    /// [`WindowEvent::CursorMoved`]: winit::event::WindowEvent::CursorMoved
+    pub fn hit_test(&self, position: PhysicalPosition<f64>) -> Vec<Handle> {
+        let size = self.wgpu_bundle.inner_size();
+        scene_hits(
+            &self.scene_bundle,
+            position,
+            size,
+            self.io_bundle.hit_tolerance(),
+        )
+        .collect()
+    }
+
+    pub fn hit_tolerance(&self) -> f32 {
+        self.io_bundle.hit_tolerance()
+    }
+
+    /// Sets how many pixels away from an object the cursor may be while still
+    /// counting as being over it (0 by default, and clamped to at least 0).
+    ///
+    /// This makes thin or small objects easier to hover and click. Where
+    /// several objects are within reach, the topmost one still wins.
+    pub fn set_hit_tolerance(&mut self, hit_tolerance: f32) {
+        self.io_bundle.set_hit_tolerance(hit_tolerance);
+    }
+
+    /// Registers a hook which is run inside the engine's render pass, right
+    /// after the surface has been cleared and before any shapes are drawn.
+    ///
+    /// The hook can bind its own pipelines and buffers and issue draw calls.
+    /// The engine rebinds its own state afterwards, so nothing needs to be
+    /// restored. The pass only lives for the duration of the call, so the hook
+    /// must not try to stash it (or anything borrowed from it) away. Its
+    /// pipelines have to target the surface's format with
+    /// [`RenderingEngine::sample_count`] samples per pixel.
+    pub fn set_pre_render<F>(&mut self, hook: F)
+    where
+        F: for<'pass> FnMut(&mut RenderPass<'pass>, &Device, &Queue) + 'static,
+    {
+        self.hook_bundle.pre_render = Some(Box::new(hook));
+    }
+
+    /// Registers a hook which is run inside the engine's render pass, after
+    /// all shapes and text have been drawn.
+    ///
+    /// The same lifetime constraints as [`RenderingEngine::set_pre_render`]
+    /// apply.
+    pub fn set_post_render<F>(&mut self, hook: F)
+    where
+        F: for<'pass> FnMut(&mut RenderPass<'pass>, &Device, &Queue) + 'static,
+    {
+        self.hook_bundle.post_render = Some(Box::new(hook));
+    }
+
+    /// Removes both the pre-render and the post-render hooks.
+    pub fn clear_render_hooks(&mut self) {
+        self.hook_bundle = HookBundle::default();
+    }
+
+    /// Loads the font file at `path`, making it available to all text.
+    ///
+    /// All text (whether rendered or measured) is shaped with the engine's
+    /// single font system, so a font only ever needs to be loaded once. Any
+    /// already shaped text is reshaped on the next frame, in case the new font
+    /// changes how it's laid out.
+    ///
+    /// Fails with [`MetallicError::FontLoadError`] (naming `path`) if the file
+    /// can't be read.
+    pub fn load_font<P>(&mut self, path: P) -> MetallicResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        load_font_file(self.glyph_bundle.font_system.db_mut(), path.as_ref())?;
+        self.glyph_bundle.text_buffers.clear();
+        self.scene_bundle.dirty = true;
+        Ok(())
+    }
+
+    /// Loads a font from its raw (e.g., `include_bytes!`-ed) data, making it
+    /// available to all text.
+    pub fn load_font_bytes(&mut self, bytes: Vec<u8>) {
+        self.glyph_bundle.font_system.db_mut().load_font_data(bytes);
+        self.glyph_bundle.text_buffers.clear();
+        self.scene_bundle.dirty = true;
     }
 
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+    /// Frees all rasterized glyphs, both the CPU-side cache and the GPU-side
+    /// atlas.
+    ///
+    /// The atlas only ever grows while text is being shown, so long-running
+    /// apps which cycle through many fonts or sizes may want to call this
+    /// whenever their UI changes context. The next frame with text in it will
+    /// be slightly slower, since every glyph it uses has to be rasterized and
+    /// uploaded again.
+    pub fn clear_glyph_cache(&mut self) {
+        self.glyph_bundle
+            .clear_cache(&self.wgpu_bundle.device, &self.wgpu_bundle.queue);
+        self.scene_bundle.dirty = true;
+    }
+
+    /// Shapes `text` the same way it would be rendered (i.e., wrapping it at
+    /// its [`Text::wrap_width`], or else at the window's width) and measures
+    /// it.
+    pub fn measure_text(&mut self, text: &Text) -> TextMetrics {
+        let size = self.wgpu_bundle.inner_size();
+        measure_text(
+            &mut self.glyph_bundle.font_system,
+            text,
+            size,
+            self.scene_bundle.scale_factor,
+        )
+    }
+
+    /// Returns the window's inner size in logical pixels (i.e., with the
+    /// window's scale factor divided out).
+    pub fn logical_size(&self) -> LogicalSize<f32> {
+        let wgpu_bundle = &self.wgpu_bundle;
+        wgpu_bundle
+            .inner_size()
+            .to_logical(wgpu_bundle.scale_factor())
+    }
+
+    /// Returns the window's current scale factor, i.e., how many physical
+    /// pixels there are to a logical one.
+    ///
+    /// Absolute coordinates (along with stroke widths, font sizes, hit
+    /// tolerances, etc.) are in logical pixels, so that the scene comes out
+    /// the same size on HiDPI displays. Positions which are passed in
+    /// [`PhysicalPosition`]s (e.g., to [`RenderingEngine::hit_test`]) are
+    /// converted to logical pixels internally.
+    pub fn scale_factor(&self) -> f64 {
+        self.wgpu_bundle.scale_factor()
+    }
+
+    /// Lays the scene out at a new scale factor, which should be called on
+    /// every [`WindowEvent::ScaleFactorChanged`] (e.g., when the window is
+    /// moved to another monitor).
+    ///
+    /// The surface is reconfigured to the window's current size and, if the
+    /// scale factor changed, the scene is redrawn.
+    ///
+    /// [`WindowEvent::ScaleFactorChanged`]: winit::event::WindowEvent::ScaleFactorChanged
+    pub fn set_scale_factor(&mut self, scale_factor: f64) -> MetallicResult<()> {
+        self.resize(self.wgpu_bundle.inner_size())?;
+        if self.scene_bundle.set_scale_factor(scale_factor as _) {
+            self.redraw();
+        };
+        Ok(())
+    }
+
+    /// Asks the window for a [`WindowEvent::RedrawRequested`] event, in
+    /// response to which [`RenderingEngine::render`] should be called.
+    ///
+    /// Calling this several times before the next render only requests a
+    /// single redraw, so every event handler that changes the scene can call
+    /// it freely without causing redundant renders.
+    ///
+    /// [`WindowEvent::RedrawRequested`]: winit::event::WindowEvent::RedrawRequested
+    pub fn redraw(&mut self) {
+        if !self.redraw_pending {
+            self.redraw_pending = true;
+            self.wgpu_bundle.request_redraw();
+        };
+    }
+
+    /// Resizes the surface to `new_size`.
+    ///
+    /// A zero-sized window (which is what some platforms report while it's
+    /// minimized) leaves the surface untouched and pauses rendering until the
+    /// window is resized back to a non-zero size.
+    ///
+    /// Returns [`MetallicError::SizeExceedsLimitsError`] (leaving the surface
+    /// untouched) if either dimension is larger than the device supports.
+    ///
+    /// [`MetallicError::SizeExceedsLimitsError`]: crate::MetallicError::SizeExceedsLimitsError
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) -> MetallicResult<()> {
+        validate_size(new_size, &self.wgpu_bundle.device.limits())?;
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if self.minimized {
+            self.fps_bundle.pause();
+            return Ok(());
+        };
         self.wgpu_bundle.surface_configuration.width = new_size.width;
         self.wgpu_bundle.surface_configuration.height = new_size.height;
-        self.wgpu_bundle.surface.configure(
+        self.wgpu_bundle.configure();
+        self.glyph_bundle.resize(&self.wgpu_bundle.queue, new_size);
+        self.scene_bundle.dirty = true;
+        Ok(())
+    }
+
+    /// Returns how frames are presented (see
+    /// [`RenderingEngine::set_present_mode`]).
+    pub fn present_mode(&self) -> PresentMode {
+        self.wgpu_bundle.surface_configuration.present_mode
+    }
+
+    /// Changes how frames are presented, returning the mode which is actually
+    /// used from now on.
+    ///
+    /// Engines start out with [`PresentMode::AutoVsync`]. Modes which the
+    /// surface doesn't support (e.g., [`PresentMode::Mailbox`] on many
+    /// platforms) fall back to [`PresentMode::Fifo`], or to the first
+    /// supported mode if even that one is missing; the auto modes are always
+    /// supported.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> PresentMode {
+        let present_mode = select_present_mode(&self.wgpu_bundle.present_modes, present_mode)
+            .unwrap_or(self.present_mode());
+        self.configure_present_mode(present_mode);
+        present_mode
+    }
+
+    fn configure_present_mode(&mut self, present_mode: PresentMode) {
+        self.wgpu_bundle.surface_configuration.present_mode = present_mode;
+        self.reconfigure_surface();
+    }
+
+    /// Applies the current surface configuration and marks the scene dirty.
+    fn reconfigure_surface(&mut self) {
+        // A zero-sized surface can't be configured; the configuration is
+        // applied once the window is resized again.
+        if !self.minimized {
+            self.wgpu_bundle.configure();
+        };
+        self.scene_bundle.dirty = true;
+    }
+
+    /// Returns how many samples every pixel is rendered with (see
+    /// [`RenderingEngine::set_sample_count`]).
+    pub fn sample_count(&self) -> u32 {
+        self.wgpu_bundle.sample_count
+    }
+
+    /// Turns on multisample anti-aliasing (MSAA) with `sample_count` samples
+    /// per pixel (e.g., 2, 4 or 8), or turns it off again with 1, returning
+    /// the count which is actually used from now on.
+    ///
+    /// Engines start out without multisampling. Counts which the surface's
+    /// format doesn't support fall back to 1. Frames are then rendered into a
+    /// multisampled texture (which follows the surface's size) and resolved
+    /// onto the surface, which smooths out the jagged edges of shapes at the
+    /// cost of memory and fill rate. Pipelines which are drawn from the
+    /// render hooks have to use the same count.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> u32 {
+        let sample_count = select_sample_count(&self.wgpu_bundle.sample_counts, sample_count);
+        if sample_count == self.wgpu_bundle.sample_count {
+            return sample_count;
+        };
+        let device = &self.wgpu_bundle.device;
+        let format = self.wgpu_bundle.surface_configuration.format;
+        self.wgpu_bundle.sample_count = sample_count;
+        self.wgpu_bundle.render_pipeline = new_shape_pipeline(device, format, sample_count);
+        self.image_bundle
+            .set_sample_count(device, format, sample_count);
+        self.compositing_bundle
+            .set_sample_count(device, format, sample_count);
+        self.glyph_bundle.set_sample_count(sample_count);
+        self.scene_bundle.dirty = true;
+        sample_count
+    }
+
+    /// Pauses rendering while the window is hidden from view (as reported by
+    /// [`WindowEvent::Occluded`]), and requests a redraw once it's visible
+    /// again.
+    ///
+    /// [`WindowEvent::Occluded`]: winit::event::WindowEvent::Occluded
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+        match occluded {
+            true => self.fps_bundle.pause(),
+            false => {
+                self.scene_bundle.dirty = true;
+                self.redraw();
+            }
+        };
+    }
+
+    /// Renders the scene and presents it.
+    ///
+    /// Nothing is rendered while the window is minimized or occluded. Nor is
+    /// anything rendered if the scene hasn't changed since the last frame
+    /// (and there are no render hooks, which may draw something different
+    /// every frame); the previously presented frame is still up to date. If
+    /// [`RenderingEngine::max_frames_in_flight`] frames are already queued up
+    /// on the GPU, this first blocks until the oldest of them is done.
+    ///
+    /// Whether or not anything was rendered, the frame ends afterwards:
+    /// immediate-mode objects (see [`RenderingEngine::draw`]) are removed, and
+    /// [`RenderingEngine::events_this_frame`] starts over.
+    ///
+    /// This is [`RenderingEngine::prepare`] followed by
+    /// [`RenderingEngine::present`].
+    pub fn render(&mut self) -> MetallicResult<()> {
+        self.redraw_pending = false;
+        let visible = !self.minimized && !self.occluded;
+        let has_hooks =
+            self.hook_bundle.pre_render.is_some() || self.hook_bundle.post_render.is_some();
+        let animating = has_hooks || self.fps_bundle.shown;
+        let result = match visible && (self.scene_bundle.dirty || animating) {
+            true => self
+                .prepare()
+                .and_then(|prepared_frame| self.present_frame(prepared_frame)),
+            false => Ok(()),
+        };
+        self.end_frame();
+        result
+    }
+
+    /// Does the CPU-side work of a frame: tessellates all visible layers,
+    /// uploads the resulting geometry (and any new images) and prepares all
+    /// text.
+    ///
+    /// The returned frame is drawn with [`RenderingEngine::present`]. Text is
+    /// prepared into the engine's (shared) glyph state, so only the most
+    /// recently prepared frame should be presented. Unlike
+    /// [`RenderingEngine::render`], this always does the full amount of work,
+    /// regardless of whether the scene has changed.
+    pub fn prepare(&mut self) -> MetallicResult<PreparedFrame> {
+        self.prepare_at(self.wgpu_bundle.inner_size(), self.fps_bundle.shown)
+    }
+
+    /// Like [`RenderingEngine::prepare`], but lays the frame out for a target
+    /// of the given size instead of the window's, and only prepares the FPS
+    /// overlay if `fps_overlay` is set.
+    fn prepare_at(
+        &mut self,
+        size: PhysicalSize<u32>,
+        fps_overlay: bool,
+    ) -> MetallicResult<PreparedFrame> {
+        let mut geometry = tessellate_scene(&mut self.scene_bundle, size)?;
+        let mut textured = std::mem::take(&mut geometry.textured);
+        prepare_geometry(
+            &mut self.buffer_bundle,
             &self.wgpu_bundle.device,
-            &self.wgpu_bundle.surface_configuration,
+            &self.wgpu_bundle.queue,
+            geometry,
         );
+        let overlay = fps_overlay.then(|| self.fps_bundle.overlay());
+        // Rotated text is drawn as textured quads instead (see `Text::angle`).
+        let (mut layer_texts, rotated_texts): (Vec<_>, Vec<_>) = self
+            .buffer_bundle
+            .layers
+            .iter()
+            .map(|(layer, _)| {
+                texts_in_layer(&self.scene_bundle, *layer)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(_, text, _)| text.angle == 0.0)
+            })
+            .unzip();
+        // The overlay's text goes on top of every layer.
+        if let Some((text, brush)) = &overlay {
+            layer_texts.push(vec![(FPS_HANDLE, text, brush)]);
+        };
+        prepare_text(
+            &mut self.glyph_bundle,
+            &self.wgpu_bundle.device,
+            &self.wgpu_bundle.queue,
+            size,
+            self.scene_bundle.scale_factor,
+            &layer_texts,
+        );
+        let rotated_draws = prepare_rotated_text(
+            &mut self.glyph_bundle,
+            &mut self.image_bundle,
+            &self.wgpu_bundle.device,
+            &self.wgpu_bundle.queue,
+            (size, self.scene_bundle.scale_factor),
+            &rotated_texts,
+        );
+        for (draws, rotated_draws) in textured.iter_mut().zip(rotated_draws) {
+            draws.extend(rotated_draws);
+        }
+        let layer_images = self
+            .buffer_bundle
+            .layers
+            .iter()
+            .map(|(layer, _)| images_in_layer(&self.scene_bundle, *layer))
+            .collect::<Vec<_>>();
+        prepare_images(
+            &mut self.image_bundle,
+            &self.wgpu_bundle.device,
+            &self.wgpu_bundle.queue,
+            (size, self.scene_bundle.scale_factor),
+            self.scene_bundle.background_image.as_ref(),
+            &layer_images,
+            &textured,
+        )?;
+        Ok(PreparedFrame { size, fps_overlay })
     }
 
-    pub fn render(&mut self) -> MetallicResult<()> {
-        let buffer_bundle = create_buffer_bundle(self)?;
-        let surface_texture = self.wgpu_bundle.surface.get_current_texture()?;
-        let view = surface_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        let mut encoder = self
-            .wgpu_bundle
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor::default());
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(self.scene_bundle.background_color),
-                        store: StoreOp::Store,
-                    },
-                })],
-                ..Default::default()
-            });
-            render_pass.set_pipeline(&self.wgpu_bundle.render_pipeline);
-            render_pass.set_vertex_buffer(0, buffer_bundle.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(buffer_bundle.index_buffer.slice(..), IndexFormat::Uint16);
-            render_pass.draw_indexed(0..(buffer_bundle.index_buffer_size as _), 0, 0..1);
+    /// Draws a frame that was returned by [`RenderingEngine::prepare`],
+    /// submits it and presents it.
+    ///
+    /// The frame is dropped without being drawn while the window is minimized
+    /// or occluded. If [`RenderingEngine::max_frames_in_flight`] frames are
+    /// already queued up on the GPU, this first blocks until the oldest of
+    /// them is done.
+    ///
+    /// A surface which has been lost or become outdated (e.g., after a resize
+    /// or a move to another monitor) isn't an error either: it's reconfigured,
+    /// the frame is dropped, and a redraw is requested to draw it again.
+    ///
+    /// Either way, the frame ends afterwards, just like after
+    /// [`RenderingEngine::render`].
+    pub fn present(&mut self, prepared_frame: PreparedFrame) -> MetallicResult<()> {
+        let result = self.present_frame(prepared_frame);
+        self.end_frame();
+        result
+    }
+
+    fn present_frame(&mut self, prepared_frame: PreparedFrame) -> MetallicResult<()> {
+        if self.minimized || self.occluded {
+            return Ok(());
         };
+        self.pacing_bundle
+            .wait_for_free_slot(&self.wgpu_bundle.device);
+        let PreparedFrame { size, fps_overlay } = prepared_frame;
+        let frame_texture = match self.wgpu_bundle.current_texture() {
+            Ok(frame_texture) => frame_texture,
+            Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                self.wgpu_bundle.configure();
+                // The scene is still dirty, so the next render draws it again.
+                self.redraw();
+                return Ok(());
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let view = frame_texture.texture().create_view(&TextureViewDescriptor {
+            label: Some("metallic.surface_view"),
+            ..Default::default()
+        });
+        let mut encoder =
+            self.wgpu_bundle
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("metallic.encoder"),
+                });
+        self.encode_frame(
+            &mut encoder,
+            &view,
+            size,
+            self.wgpu_bundle.surface_configuration.alpha_mode,
+            fps_overlay,
+        )?;
         let command_buffer = encoder.finish();
         self.wgpu_bundle.queue.submit([command_buffer]);
-        surface_texture.present();
+        self.wgpu_bundle
+            .queue
+            .on_submitted_work_done(self.pacing_bundle.frame_submitted());
+        frame_texture.present();
+        if self.fps_bundle.shown {
+            self.fps_bundle.record(Instant::now());
+            self.redraw();
+        };
+        self.scene_bundle.dirty = false;
+        self.glyph_bundle.text_atlas.trim();
+        self.texture_pool.trim();
         Ok(())
     }
+
+    /// Removes the frame's immediate-mode objects and forgets its
+    /// interactions.
+    fn end_frame(&mut self) {
+        self.clear_immediate();
+        self.io_bundle.end_frame();
+    }
+
+    /// Records the passes which draw the most recently prepared frame into
+    /// `view` (a target of the given size, in the surface's format).
+    ///
+    /// The background color is cleared according to `alpha_mode` (see
+    /// [`clear_color`]), and the FPS overlay is drawn last if `fps_overlay`
+    /// is set (in which case it must have been prepared).
+    fn encode_frame(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        size: PhysicalSize<u32>,
+        alpha_mode: CompositeAlphaMode,
+        fps_overlay: bool,
+    ) -> MetallicResult<()> {
+        let msaa_view = self.msaa_view(size);
+        let buffer_bundle = &self.buffer_bundle;
+        let layer_views = match self.compositing_bundle.enabled {
+            true => {
+                let layer_views = self.compositing_bundle.layer_views(
+                    &mut self.texture_pool,
+                    &self.wgpu_bundle.device,
+                    size,
+                    self.wgpu_bundle.surface_configuration.format,
+                    buffer_bundle.layers.len(),
+                );
+                for (index, layer_view) in layer_views.iter().enumerate() {
+                    let mut render_pass = begin_render_pass(
+                        encoder,
+                        "metallic.layer_pass",
+                        layer_view,
+                        msaa_view.as_ref(),
+                        Color::TRANSPARENT,
+                    );
+                    draw_layer(
+                        &mut render_pass,
+                        &self.wgpu_bundle.render_pipeline,
+                        &self.glyph_bundle,
+                        &self.image_bundle,
+                        buffer_bundle,
+                        index,
+                    )?;
+                }
+                Some(layer_views)
+            }
+            false => None,
+        };
+        {
+            let clear_color = clear_color(self.scene_bundle.background_color, alpha_mode);
+            let mut render_pass = begin_render_pass(
+                encoder,
+                "metallic.main_pass",
+                view,
+                msaa_view.as_ref(),
+                clear_color,
+            );
+            self.image_bundle.draw_background(&mut render_pass);
+            if let Some(pre_render) = self.hook_bundle.pre_render.as_mut() {
+                pre_render(
+                    &mut render_pass,
+                    &self.wgpu_bundle.device,
+                    &self.wgpu_bundle.queue,
+                );
+            };
+            match &layer_views {
+                Some(layer_views) => {
+                    for ((layer, _), layer_view) in buffer_bundle.layers.iter().zip(layer_views) {
+                        self.compositing_bundle.composite(
+                            &mut render_pass,
+                            &self.wgpu_bundle.device,
+                            layer_view,
+                            self.compositing_bundle.opacity(*layer),
+                        );
+                    }
+                }
+                None => {
+                    for index in 0..buffer_bundle.layers.len() {
+                        draw_layer(
+                            &mut render_pass,
+                            &self.wgpu_bundle.render_pipeline,
+                            &self.glyph_bundle,
+                            &self.image_bundle,
+                            buffer_bundle,
+                            index,
+                        )?;
+                    }
+                }
+            };
+            if let Some(post_render) = self.hook_bundle.post_render.as_mut() {
+                post_render(
+                    &mut render_pass,
+                    &self.wgpu_bundle.device,
+                    &self.wgpu_bundle.queue,
+                );
+            };
+            if fps_overlay {
+                // Its text was prepared right after the last layer's.
+                draw_text(
+                    &mut render_pass,
+                    &self.glyph_bundle,
+                    buffer_bundle.layers.len(),
+                )?;
+            };
+        };
+        Ok(())
+    }
+
+    /// Returns a view onto the `size`d, multisampled texture which every pass
+    /// renders into (before it's resolved onto the pass's actual target), or
+    /// `None` while multisampling is off.
+    ///
+    /// The passes of a frame run one after the other, so they can all share
+    /// the same texture.
+    fn msaa_view(&mut self, size: PhysicalSize<u32>) -> Option<TextureView> {
+        let sample_count = self.wgpu_bundle.sample_count;
+        if sample_count == 1 {
+            return None;
+        };
+        let key = TextureKey {
+            label: "metallic.msaa_texture",
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            format: self.wgpu_bundle.surface_configuration.format,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            sample_count,
+        };
+        let texture = &self.texture_pool.textures(&self.wgpu_bundle.device, key, 1)[0];
+        Some(texture.create_view(&TextureViewDescriptor {
+            label: Some("metallic.msaa_view"),
+            ..Default::default()
+        }))
+    }
+}
+
+/// Returns the color to clear the surface with.
+///
+/// Surfaces which are composited with the desktop expect premultiplied colors
+/// (e.g., a background with an alpha of 0 has to be fully zeroed out in order
+/// to be see-through), while opaque ones ignore alpha altogether.
+fn clear_color(background_color: Color, alpha_mode: CompositeAlphaMode) -> Color {
+    match alpha_mode {
+        CompositeAlphaMode::PreMultiplied => {
+            let Color { r, g, b, a } = background_color;
+            Color {
+                r: r * a,
+                g: g * a,
+                b: b * a,
+                a,
+            }
+        }
+        _ => background_color,
+    }
+}
+
+/// Begins a pass which draws into `view`; or, if given, into `msaa_view`
+/// (a multisampled texture of the same size and format) which is then
+/// resolved onto `view`.
+fn begin_render_pass<'encoder>(
+    encoder: &'encoder mut CommandEncoder,
+    label: &str,
+    view: &TextureView,
+    msaa_view: Option<&TextureView>,
+    clear_color: Color,
+) -> RenderPass<'encoder> {
+    // Only the resolved pixels are needed once the pass is over.
+    let (view, resolve_target, store) = match msaa_view {
+        Some(msaa_view) => (msaa_view, Some(view), StoreOp::Discard),
+        None => (view, None, StoreOp::Store),
+    };
+    encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: Operations {
+                load: LoadOp::Clear(clear_color),
+                store,
+            },
+        })],
+        ..Default::default()
+    })
+}
+
+/// Draws the shapes, then the images and then the text of the `index`-th
+/// visible layer.
+fn draw_layer<'pass>(
+    render_pass: &mut RenderPass<'pass>,
+    render_pipeline: &RenderPipeline,
+    glyph_bundle: &'pass GlyphBundle,
+    image_bundle: &ImageBundle,
+    buffer_bundle: &BufferBundle,
+    index: usize,
+) -> MetallicResult<()> {
+    let (_, indices) = &buffer_bundle.layers[index];
+    if !indices.is_empty() {
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_vertex_buffer(0, buffer_bundle.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(buffer_bundle.index_buffer.slice(..), IndexFormat::Uint32);
+        render_pass.draw_indexed(indices.clone(), 0, 0..1);
+    };
+    image_bundle.draw(render_pass, index);
+    draw_text(render_pass, glyph_bundle, index)
+}
+
+/// Draws the text which was prepared into the `index`th text renderer (see
+/// [`prepare_text`]), unless it failed to prepare.
+fn draw_text<'pass>(
+    render_pass: &mut RenderPass<'pass>,
+    glyph_bundle: &'pass GlyphBundle,
+    index: usize,
+) -> MetallicResult<()> {
+    // Missing if the glyph cache was cleared after this frame was prepared.
+    let text_renderer = glyph_bundle.text_renderers.get(index);
+    if let Some(text_renderer) =
+        text_renderer.filter(|_| !glyph_bundle.unprepared_layers.contains(&index))
+    {
+        text_renderer.render(
+            &glyph_bundle.text_atlas,
+            &glyph_bundle.viewport,
+            render_pass,
+        )?;
+    };
+    Ok(())
+}
+
+/// A frame whose geometry has been tessellated and uploaded and whose text
+/// has been prepared, but which has not been drawn yet.
+pub struct PreparedFrame {
+    size: PhysicalSize<u32>,
+    fps_overlay: bool,
+}
+
+/// How often the geometry of shapes and meshes has been reused from the
+/// tessellation cache (see [`RenderingEngine::tessellation_stats`]).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TessellationStats {
+    /// How many times an object's cached geometry was reused for a frame.
+    pub hits: usize,
+    /// How many times a shape had to be run through the tessellator (i.e.,
+    /// wasn't cached yet, or its cached geometry had gone stale).
+    pub misses: usize,
+}
+
+/// The tessellated shapes of all visible layers.
+#[derive(Default)]
+struct Geometry {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    /// Every visible layer (in ascending order), along with the range of
+    /// `indices` which its shapes occupy.
+    layers: Vec<(usize, Range<u32>)>,
+    /// Every visible shape and mesh (in draw order), along with the ranges of
+    /// `vertices` and `indices` which it occupies.
+    objects: Vec<(Handle, Range<u32>, Range<u32>)>,
+    /// For every visible layer, its shapes and meshes which are painted with
+    /// a texture; these are drawn with the images rather than from
+    /// `vertices`.
+    textured: Vec<Vec<TexturedDraw>>,
+}
+
+/// An object's tessellated geometry, in its own coordinate space.
+struct Tessellation {
+    geometry: VertexBuffers<Point2D<f32>, u32>,
+    coordinate_space: CoordinateSpace,
+    /// The size of the window the geometry was tessellated for, if it depends
+    /// on it at all (i.e., for strokes of NDC shapes).
+    size: Option<PhysicalSize<u32>>,
+}
+
+impl Tessellation {
+    /// Whether the geometry can be reused in a `size`d window.
+    fn fits(&self, size: PhysicalSize<u32>) -> bool {
+        self.size.is_none_or(|curr_size| curr_size == size)
+    }
 }
 
-struct BufferBundle {
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    index_buffer_size: usize,
+fn tessellate_scene(
+    scene_bundle: &mut SceneBundle,
+    size: PhysicalSize<u32>,
+) -> MetallicResult<Geometry> {
+    let scale_factor = scene_bundle.scale_factor;
+    tessellate_scene_with(scene_bundle, size, |point_2d, color| {
+        to_vertex(point_2d * scale_factor, size, color)
+    })
 }
 
-fn create_buffer_bundle(rendering_engine: &mut RenderingEngine) -> MetallicResult<BufferBundle> {
-    let size = rendering_engine.wgpu_bundle.window.inner_size();
+/// Tessellates every visible object as laid out in a `size`d window, turning
+/// absolute (logical) positions into vertices with `into_vertex`.
+fn tessellate_scene_with<F>(
+    scene_bundle: &mut SceneBundle,
+    size: PhysicalSize<u32>,
+    mut into_vertex: F,
+) -> MetallicResult<Geometry>
+where
+    F: FnMut(Point2D<f32>, Color) -> Vertex,
+{
     let mut vertices = vec![];
     let mut indices = vec![];
-    let mut offset = 0;
-    for (shape, _) in &rendering_engine.scene_bundle.shapes {
-        let mut geometry = VertexBuffers::<_, u16>::new();
-        let mut buffers_builder = BuffersBuilder::new(&mut geometry, Ctor);
-        rendering_engine
-            .scene_bundle
-            .fill_tessellator
-            .tessellate_path(
-                &shape.path,
-                &FillOptions::tolerance(0.02),
-                &mut buffers_builder,
-            )?;
-        let length = geometry.vertices.len();
-        vertices.extend(
-            geometry
+    let mut layers = vec![];
+    let mut objects = vec![];
+    let mut textured = vec![];
+    let SceneBundle {
+        objects: scene_objects,
+        hidden_layers,
+        scale_factor,
+        tessellators,
+        tessellations,
+        tessellation_stats,
+        ..
+    } = scene_bundle;
+    // Objects which are gone for good don't need their tessellations anymore.
+    let alive = scene_objects
+        .iter()
+        .map(|&(handle, _, _)| handle)
+        .collect::<HashSet<_>>();
+    tessellations.retain(|handle, _| alive.contains(handle));
+    for (handle, object, layer) in scene_objects.iter() {
+        if hidden_layers.contains(layer) {
+            continue;
+        };
+        if layers.last().map(|(last_layer, _)| last_layer) != Some(layer) {
+            let start = indices.len() as u32;
+            layers.push((*layer, start..start));
+            textured.push(vec![]);
+        };
+        let tessellation = match &object.kind {
+            // Nothing to tessellate (and nothing to offset the indices of the
+            // following objects by).
+            ObjectKind::Shape(shape) if shape.is_empty() => continue,
+            ObjectKind::Mesh(mesh) if mesh.indices().is_empty() => continue,
+            ObjectKind::Text(_) | ObjectKind::Image(_) => continue,
+            _ if tessellations
+                .get(handle)
+                .is_some_and(|tessellation| tessellation.fits(size)) =>
+            {
+                tessellation_stats.hits += 1;
+                &tessellations[handle]
+            }
+            ObjectKind::Shape(shape) => {
+                let geometry =
+                    tessellate_shape(tessellators, shape, TOLERANCE, size, *scale_factor)?;
+                tessellation_stats.misses += 1;
+                let size = match (shape.style, shape.coordinate_space) {
+                    (DrawStyle::Stroke { .. }, CoordinateSpace::Ndc) => Some(size),
+                    _ => None,
+                };
+                let tessellation = Tessellation {
+                    geometry,
+                    coordinate_space: shape.coordinate_space,
+                    size,
+                };
+                tessellations.insert(*handle, tessellation);
+                &tessellations[handle]
+            }
+            ObjectKind::Mesh(mesh) => {
+                let tessellation = Tessellation {
+                    geometry: VertexBuffers {
+                        vertices: mesh.vertices().to_vec(),
+                        indices: mesh.indices().to_vec(),
+                    },
+                    coordinate_space: CoordinateSpace::Absolute,
+                    size: None,
+                };
+                tessellations.entry(*handle).or_insert(tessellation)
+            }
+        };
+        let Tessellation {
+            geometry,
+            coordinate_space,
+            ..
+        } = tessellation;
+        if let Brush::Texture { id, uv_rect } = &object.brush {
+            let points = geometry
                 .vertices
-                .into_iter()
-                .map(|point_2d| to_vertex(point_2d, size, shape.color)),
-        );
-        indices.extend(geometry.indices.into_iter().map(|index| index + offset));
-        offset += length as u16;
-    }
-    let vertex_buffer =
-        rendering_engine
-            .wgpu_bundle
-            .device
-            .create_buffer_init(&BufferInitDescriptor {
-                label: None,
-                contents: &cast_slice(&vertices),
-                usage: BufferUsages::VERTEX,
-            });
-    let index_buffer =
-        rendering_engine
-            .wgpu_bundle
-            .device
-            .create_buffer_init(&BufferInitDescriptor {
-                label: None,
-                contents: &cast_slice(&indices),
-                usage: BufferUsages::INDEX,
-            });
-    Ok(BufferBundle {
-        vertex_buffer,
-        index_buffer,
-        index_buffer_size: indices.len(),
+                .iter()
+                .map(|&point_2d| match coordinate_space {
+                    CoordinateSpace::Absolute => point_2d * *scale_factor,
+                    CoordinateSpace::Ndc => ndc_to_abs(point_2d, size),
+                })
+                .collect::<Vec<_>>();
+            let triangles = textured_triangles(&points, &geometry.indices, *uv_rect, size);
+            if let Some(draws) = textured.last_mut() {
+                draws.push((*id, triangles));
+            };
+            continue;
+        };
+        let (vertex_start, index_start) = (vertices.len() as u32, indices.len() as u32);
+        vertices.extend(geometry.vertices.iter().map(|&point_2d| {
+            let color = object.brush.color_at(point_2d);
+            match coordinate_space {
+                CoordinateSpace::Absolute => into_vertex(point_2d, color),
+                CoordinateSpace::Ndc => to_unscaled_vertex(point_2d, color),
+            }
+        }));
+        indices.extend(geometry.indices.iter().map(|index| index + vertex_start));
+        objects.push((
+            *handle,
+            vertex_start..vertices.len() as u32,
+            index_start..indices.len() as u32,
+        ));
+        if let Some((_, range)) = layers.last_mut() {
+            range.end = indices.len() as u32;
+        };
+    }
+    Ok(Geometry {
+        vertices,
+        indices,
+        layers,
+        objects,
+        textured,
+    })
+}
+
+fn objects_in_layer(scene_bundle: &SceneBundle, layer: usize) -> &[(Handle, Object, usize)] {
+    let start = scene_bundle
+        .objects
+        .partition_point(|&(_, _, curr_layer)| curr_layer < layer);
+    let end = scene_bundle
+        .objects
+        .partition_point(|&(_, _, curr_layer)| curr_layer <= layer);
+    &scene_bundle.objects[start..end]
+}
+
+fn texts_in_layer(scene_bundle: &SceneBundle, layer: usize) -> Vec<(Handle, &Text, &Brush)> {
+    objects_in_layer(scene_bundle, layer)
+        .iter()
+        .filter_map(|(handle, object, _)| match &object.kind {
+            ObjectKind::Text(text) => Some((*handle, text, &object.brush)),
+            ObjectKind::Shape(_) | ObjectKind::Mesh(_) | ObjectKind::Image(_) => None,
+        })
+        .collect()
+}
+
+fn images_in_layer(scene_bundle: &SceneBundle, layer: usize) -> Vec<(&Image, &Brush)> {
+    objects_in_layer(scene_bundle, layer)
+        .iter()
+        .filter_map(|(_, object, _)| match &object.kind {
+            ObjectKind::Image(image) => Some((image, &object.brush)),
+            ObjectKind::Shape(_) | ObjectKind::Mesh(_) | ObjectKind::Text(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the topmost visible object at `position`; text is not hit-tested.
+///
+/// Objects whose outline is within `hit_tolerance` pixels of `position` count
+/// as hit as well, so that thin or small objects are easier to point at.
+fn hit_test_scene(
+    scene_bundle: &SceneBundle,
+    position: PhysicalPosition<f64>,
+    size: PhysicalSize<u32>,
+    hit_tolerance: f32,
+) -> Option<Handle> {
+    scene_hits(scene_bundle, position, size, hit_tolerance).next()
+}
+
+/// Like [`hit_test_scene`], but returns every visible object at `position`,
+/// topmost first.
+fn scene_hits(
+    scene_bundle: &SceneBundle,
+    position: PhysicalPosition<f64>,
+    size: PhysicalSize<u32>,
+    hit_tolerance: f32,
+) -> impl Iterator<Item = Handle> + '_ {
+    let physical_position = point(position.x as _, position.y as _);
+    let ndc_position = abs_to_ndc(physical_position, size);
+    let ndc_tolerance = TOLERANCE * 2.0 / (size.width.max(size.height).max(1) as f32);
+    // Everything else is hit-tested in logical pixels.
+    let scale_factor = scene_bundle.scale_factor;
+    let position = physical_position / scale_factor;
+    let candidates = scene_bundle.hit_candidates(position, size, hit_tolerance);
+    scene_bundle
+        .objects
+        .iter()
+        .rev()
+        .filter(|(_, _, layer)| !scene_bundle.hidden_layers.contains(layer))
+        .filter(move |(handle, _, _)| candidates.contains(handle))
+        .filter(move |(_, object, _)| match &object.kind {
+            ObjectKind::Shape(shape) => {
+                let (shape_position, tolerance) = match shape.coordinate_space {
+                    CoordinateSpace::Absolute => (position, TOLERANCE),
+                    CoordinateSpace::Ndc => (ndc_position, ndc_tolerance),
+                };
+                match shape.style {
+                    // Objects can't be transformed (yet), nor can the view.
+                    DrawStyle::Fill => {
+                        shape.contains(shape_position, &Transform::identity(), tolerance)
+                            || (hit_tolerance > 0.0
+                                && outline_is_within(
+                                    shape,
+                                    position,
+                                    size,
+                                    scale_factor,
+                                    tolerance,
+                                    hit_tolerance,
+                                ))
+                    }
+                    // Stroke widths are in pixels, whatever the shape's
+                    // coordinate space is.
+                    DrawStyle::Stroke { width, .. } => {
+                        !shape.is_empty()
+                            && outline_is_within(
+                                shape,
+                                position,
+                                size,
+                                scale_factor,
+                                tolerance,
+                                width / 2.0 + hit_tolerance,
+                            )
+                    }
+                }
+            }
+            ObjectKind::Mesh(mesh) => mesh.indices().chunks_exact(3).any(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices()[triangle[i] as usize]);
+                let triangle = Triangle { a, b, c };
+                triangle.contains_point(position)
+                    || (hit_tolerance > 0.0
+                        && [triangle.ab(), triangle.bc(), triangle.ca()]
+                            .iter()
+                            .any(|edge| edge.distance_to_point(position) <= hit_tolerance))
+            }),
+            ObjectKind::Image(image) => image
+                .rect
+                .inflate(hit_tolerance, hit_tolerance)
+                .contains_inclusive(position),
+            ObjectKind::Text(_) => false,
+        })
+        .map(|&(handle, _, _)| handle)
+}
+
+/// Whether any part of the shape's outline is within `distance` pixels of
+/// `position` (which, like `distance`, is in absolute, logical coordinates).
+fn outline_is_within(
+    shape: &Shape,
+    position: Point2D<f32>,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+    tolerance: f32,
+    distance: f32,
+) -> bool {
+    let to_abs = |point| match shape.coordinate_space {
+        CoordinateSpace::Absolute => point,
+        CoordinateSpace::Ndc => ndc_to_abs(point, size) / scale_factor,
+    };
+    shape.outline(tolerance).any(|LineSegment { from, to }| {
+        let line_segment = LineSegment {
+            from: to_abs(from),
+            to: to_abs(to),
+        };
+        line_segment.distance_to_point(position) <= distance
     })
 }
@@ -0,0 +1,214 @@
+use std::{path::Path, sync::mpsc::channel};
+
+use image::{ImageFormat, RgbaImage};
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoder, CommandEncoderDescriptor, CompositeAlphaMode,
+    Device, Extent3d, ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, Queue, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    rendering_engine::{validate_size, PreparedFrame, RenderingEngine},
+    MetallicResult,
+};
+
+impl RenderingEngine {
+    /// Renders the scene offscreen at the given size (instead of the window's)
+    /// and reads the result back as straight (i.e., non-premultiplied) RGBA.
+    ///
+    /// Objects are laid out exactly as they would be in a window of that size.
+    /// The window itself isn't touched, although its next frame is rebuilt
+    /// from scratch.
+    pub fn render_to_image(&mut self, size: PhysicalSize<u32>) -> MetallicResult<RgbaImage> {
+        validate_size(size, &self.wgpu_bundle.device.limits())?;
+        if size.width == 0 || size.height == 0 {
+            return Ok(RgbaImage::new(size.width, size.height));
+        };
+        self.glyph_bundle.resize(&self.wgpu_bundle.queue, size);
+        let image = self
+            .prepare_at(size, false)
+            .and_then(|PreparedFrame { size, .. }| self.draw_offscreen(size));
+        self.glyph_bundle
+            .resize(&self.wgpu_bundle.queue, self.wgpu_bundle.inner_size());
+        // The uploaded geometry now belongs to the offscreen frame.
+        self.scene_bundle.dirty = true;
+        image
+    }
+
+    /// Renders the scene offscreen at the given size and saves it as a PNG at
+    /// `path` (see [`RenderingEngine::render_to_image`]).
+    pub fn save_png<P>(&mut self, path: P, size: PhysicalSize<u32>) -> MetallicResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        let image = self.render_to_image(size)?;
+        image.save_with_format(path, ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// Captures what's currently on screen (i.e., the scene at the window's
+    /// size) as tightly packed, straight RGBA8 pixels, row by row from the
+    /// top.
+    ///
+    /// The scene is rendered again offscreen (see
+    /// [`RenderingEngine::render_to_image`]) rather than read back from the
+    /// surface, since surface textures generally can't be copied from. Pixels
+    /// come out the same whatever the surface's format is (e.g., BGRA ones are
+    /// swizzled), which makes the result ready to hand to any PNG encoder.
+    pub fn screenshot(&mut self) -> MetallicResult<Vec<u8>> {
+        let size = self.wgpu_bundle.inner_size();
+        let image = self.render_to_image(size)?;
+        Ok(image.into_raw())
+    }
+
+    fn draw_offscreen(&mut self, size: PhysicalSize<u32>) -> MetallicResult<RgbaImage> {
+        let device = &self.wgpu_bundle.device;
+        let format = self.wgpu_bundle.surface_configuration.format;
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("metallic.offscreen_texture"),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("metallic.offscreen_view"),
+            ..Default::default()
+        });
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("metallic.offscreen_encoder"),
+        });
+        // Everything is blended premultiplied, so the image has to be cleared
+        // that way too; it's un-premultiplied once read back.
+        self.encode_frame(
+            &mut encoder,
+            &view,
+            size,
+            CompositeAlphaMode::PreMultiplied,
+            false,
+        )?;
+        read_back(
+            &self.wgpu_bundle.device,
+            &self.wgpu_bundle.queue,
+            encoder,
+            &texture,
+        )
+    }
+}
+
+/// Finishes `encoder` with a copy of `texture` (which must be
+/// [`TextureUsages::COPY_SRC`], with 4 bytes per pixel), submits it and waits
+/// for the copy to be read back as a straight RGBA image.
+pub(crate) fn read_back(
+    device: &Device,
+    queue: &Queue,
+    mut encoder: CommandEncoder,
+    texture: &Texture,
+) -> MetallicResult<RgbaImage> {
+    let size = PhysicalSize::new(texture.width(), texture.height());
+    let bytes_per_row = padded_bytes_per_row(size.width);
+    let readback_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("metallic.readback_buffer"),
+        size: (bytes_per_row * size.height) as _,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &readback_buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+    let slice = readback_buffer.slice(..);
+    let (sender, receiver) = channel();
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    receiver
+        .recv()
+        .expect("The readback buffer was dropped before being mapped")?;
+    let image = to_rgba_image(
+        &slice.get_mapped_range(),
+        size,
+        bytes_per_row,
+        texture.format(),
+    );
+    readback_buffer.unmap();
+    Ok(image)
+}
+
+/// Returns how many bytes one row of a `width` pixels wide, 4-byte-per-pixel
+/// texture takes up when copied into a buffer.
+///
+/// Copies require every row to start at a multiple of
+/// [`COPY_BYTES_PER_ROW_ALIGNMENT`], so rows are padded up to it.
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    (width * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Converts the (row-padded, premultiplied) pixels read back from a texture of
+/// the given format into a tightly packed, straight RGBA image.
+pub(crate) fn to_rgba_image(
+    padded: &[u8],
+    size: PhysicalSize<u32>,
+    bytes_per_row: u32,
+    format: TextureFormat,
+) -> RgbaImage {
+    let bgra = matches!(
+        format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    let mut pixels = Vec::with_capacity((size.width * size.height * 4) as _);
+    for row in padded.chunks(bytes_per_row as _).take(size.height as _) {
+        for pixel in row[..(size.width * 4) as usize].chunks_exact(4) {
+            let [r, g, b, a] = match bgra {
+                true => [pixel[2], pixel[1], pixel[0], pixel[3]],
+                false => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            };
+            let unpremultiply = |channel: u8| match (a, format.is_srgb()) {
+                (0, _) => 0,
+                // Colors were premultiplied before being encoded, so they have
+                // to be decoded before being divided.
+                (a, true) => {
+                    let linear = srgb_to_linear(channel as f32 / 255.0) / (a as f32 / 255.0);
+                    (linear_to_srgb(linear.min(1.0)) * 255.0).round() as u8
+                }
+                (a, false) => ((channel as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+            };
+            pixels.extend([unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+        }
+    }
+    RgbaImage::from_raw(size.width, size.height, pixels)
+        .expect("Exactly width * height pixels were read back")
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use wgpu::{Device, Maintain};
+
+use crate::rendering_engine::RenderingEngine;
+
+pub(crate) const DEFAULT_MAX_FRAMES_IN_FLIGHT: u32 = 2;
+
+/// Keeps track of how many submitted frames the GPU has yet to finish, so that
+/// the CPU can't run arbitrarily far ahead of it.
+pub(crate) struct PacingBundle {
+    max_frames_in_flight: u32,
+    frames_in_flight: Arc<AtomicU32>,
+}
+
+impl Default for PacingBundle {
+    fn default() -> Self {
+        Self {
+            max_frames_in_flight: DEFAULT_MAX_FRAMES_IN_FLIGHT,
+            frames_in_flight: Arc::default(),
+        }
+    }
+}
+
+impl PacingBundle {
+    pub(crate) fn frames_in_flight(&self) -> u32 {
+        self.frames_in_flight.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn has_free_slot(&self) -> bool {
+        self.frames_in_flight() < self.max_frames_in_flight
+    }
+
+    /// Blocks until fewer than the maximum number of frames are in flight.
+    pub(crate) fn wait_for_free_slot(&self, device: &Device) {
+        device.poll(Maintain::Poll);
+        while !self.has_free_slot() {
+            device.poll(Maintain::Wait);
+        }
+    }
+
+    /// Counts a newly submitted frame as in flight.
+    ///
+    /// The returned callback marks that frame as done again; it is meant to be
+    /// handed to [`wgpu::Queue::on_submitted_work_done`].
+    pub(crate) fn frame_submitted(&self) -> impl FnOnce() + Send + 'static {
+        self.frames_in_flight.fetch_add(1, Ordering::AcqRel);
+        let frames_in_flight = self.frames_in_flight.clone();
+        move || {
+            frames_in_flight.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl RenderingEngine {
+    pub fn max_frames_in_flight(&self) -> u32 {
+        self.pacing_bundle.max_frames_in_flight
+    }
+
+    /// Sets how many frames may be submitted to the GPU before
+    /// [`RenderingEngine::render`] blocks and waits for the oldest one to
+    /// finish.
+    ///
+    /// Lower values keep input latency down under heavy load; higher values
+    /// (e.g., 3 for triple buffering) smooth out uneven frame times. The
+    /// surface's `desired_maximum_frame_latency` is updated to match. Values
+    /// below 1 are treated as 1.
+    pub fn set_max_frames_in_flight(&mut self, max_frames_in_flight: u32) {
+        let max_frames_in_flight = max_frames_in_flight.max(1);
+        self.pacing_bundle.max_frames_in_flight = max_frames_in_flight;
+        self.wgpu_bundle
+            .surface_configuration
+            .desired_maximum_frame_latency = max_frames_in_flight;
+        self.reconfigure_surface();
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use euclid::default::{Box2D, Point2D, Size2D};
+use glyphon::{Buffer, FontSystem, Metrics, SwashCache, SwashContent};
+use wgpu::{Device, Queue};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    primitives::{abs_to_ndc, Brush, Text, TextAnchor, TextureId},
+    rendering_engine::{
+        glyph_bundle::{convert_color, paint_glyphs, shape_into, text_top, GlyphBundle, ShapedAs},
+        image_bundle::{ImageBundle, ImageVertex, TexturedDraw},
+        Handle,
+    },
+};
+
+/// A rotated text object, rasterized into a texture of the image bundle (see
+/// [`ImageBundle::load_texture`]).
+pub struct RotatedText {
+    rasterized_as: RasterizedAs,
+    /// The texture, along with the rectangle which it covers before being
+    /// rotated (relative to the text's position, in physical pixels); `None`
+    /// if the text has no pixels (or they couldn't be uploaded).
+    texture: Option<(TextureId, Box2D<f32>)>,
+}
+
+/// Everything which affects a rotated text's pixels.
+struct RasterizedAs {
+    shaped: ShapedAs,
+    anchor: TextAnchor,
+    brush: Brush,
+    /// Where the text is and how far it's rotated, for gradients only: those
+    /// are sampled wherever the glyphs end up, while other brushes' pixels
+    /// don't depend on either.
+    placement: Option<(Point2D<f32>, f32)>,
+}
+
+impl RasterizedAs {
+    fn new(text: &Text, brush: &Brush, size: PhysicalSize<u32>, scale_factor: f32) -> Self {
+        Self {
+            shaped: ShapedAs::new(text, size, scale_factor),
+            anchor: text.anchor,
+            brush: brush.clone(),
+            placement: placement(text, brush),
+        }
+    }
+
+    fn matches(
+        &self,
+        text: &Text,
+        brush: &Brush,
+        size: PhysicalSize<u32>,
+        scale_factor: f32,
+    ) -> bool {
+        self.shaped.matches(text, size, scale_factor)
+            && self.anchor == text.anchor
+            && self.brush == *brush
+            && self.placement == placement(text, brush)
+    }
+}
+
+fn placement(text: &Text, brush: &Brush) -> Option<(Point2D<f32>, f32)> {
+    match brush {
+        Brush::Solid(_) | Brush::Texture { .. } => None,
+        _ => Some((text.position, text.angle)),
+    }
+}
+
+/// Rasterizes the rotated text of every visible layer into a texture (unless
+/// it already has been, in exactly the same way), returning the rotated quads
+/// which draw the textures of `layers[index]` at `index`.
+///
+/// Just like in [`prepare_text`], the text is laid out within `size`, with its
+/// positions and font sizes scaled by `scale_factor`. Text whose texture can't
+/// be uploaded (e.g., because it's larger than the device supports) doesn't
+/// fail the whole frame: the error is logged, and the text is skipped.
+///
+/// [`prepare_text`]: crate::rendering_engine::glyph_bundle::prepare_text
+pub fn prepare_rotated_text(
+    glyph_bundle: &mut GlyphBundle,
+    image_bundle: &mut ImageBundle,
+    device: &Device,
+    queue: &Queue,
+    (size, scale_factor): (PhysicalSize<u32>, f32),
+    layers: &[Vec<(Handle, &Text, &Brush)>],
+) -> Vec<Vec<TexturedDraw>> {
+    let GlyphBundle {
+        font_system,
+        swash_cache,
+        rotated_texts,
+        ..
+    } = glyph_bundle;
+    let mut rendered = HashSet::new();
+    let mut draws = vec![];
+    for texts in layers {
+        let mut layer_draws = vec![];
+        for &(handle, text, brush) in texts {
+            rendered.insert(handle);
+            let up_to_date = rotated_texts.get(&handle).is_some_and(|rotated_text| {
+                rotated_text
+                    .rasterized_as
+                    .matches(text, brush, size, scale_factor)
+            });
+            if !up_to_date {
+                let rasterized =
+                    rasterize_text(font_system, swash_cache, text, brush, size, scale_factor);
+                let texture = rasterized.and_then(|(rgba, texture_size, rect)| match image_bundle
+                    .load_texture(device, queue, &rgba, texture_size)
+                {
+                    Ok(id) => Some((id, rect)),
+                    Err(error) => {
+                        log::warn!("Skipping rotated text {handle}: {error}");
+                        None
+                    }
+                });
+                let rotated_text = RotatedText {
+                    rasterized_as: RasterizedAs::new(text, brush, size, scale_factor),
+                    texture,
+                };
+                if let Some((id, _)) = rotated_texts
+                    .insert(handle, rotated_text)
+                    .and_then(|stale| stale.texture)
+                {
+                    image_bundle.unload_texture(id);
+                };
+            };
+            if let Some((id, rect)) = rotated_texts[&handle].texture {
+                layer_draws.push((id, rotated_quad(text, rect, size, scale_factor)));
+            };
+        }
+        draws.push(layer_draws);
+    }
+    rotated_texts.retain(|handle, rotated_text| {
+        let retained = rendered.contains(handle);
+        if let Some((id, _)) = rotated_text.texture.filter(|_| !retained) {
+            image_bundle.unload_texture(id);
+        };
+        retained
+    });
+    draws
+}
+
+/// Rasterizes `text` (without rotating it) into straight-alpha sRGB RGBA8
+/// pixels, colored by `brush` just like unrotated text is.
+///
+/// Returns the pixels, their size, and the rectangle which they cover relative
+/// to the text's position, all in physical pixels; or `None` if the text
+/// covers no pixels at all.
+fn rasterize_text(
+    font_system: &mut FontSystem,
+    swash_cache: &mut SwashCache,
+    text: &Text,
+    brush: &Brush,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Option<(Vec<u8>, PhysicalSize<u32>, Box2D<f32>)> {
+    // Shaped at its physical size (rather than scaled up afterwards), so that
+    // its glyphs are just as sharp as unrotated text's.
+    let scaled = Text {
+        position: text.position * scale_factor,
+        font_size: text.font_size * scale_factor,
+        line_height: text.line_height * scale_factor,
+        wrap_width: text.wrap_width.map(|wrap_width| wrap_width * scale_factor),
+        ..text.clone()
+    };
+    let mut buffer = Buffer::new(
+        font_system,
+        Metrics::new(scaled.font_size, scaled.line_height),
+    );
+    shape_into(&mut buffer, font_system, &scaled, size, 1.0);
+    let origin = Point2D::new(scaled.position.x, text_top(&scaled, &buffer));
+    if placement(text, brush).is_some() {
+        // Every glyph gets the color of wherever its center ends up on screen.
+        let color_at = |center: Point2D<f32>| {
+            brush.color_at(text.rotate((origin + center.to_vector()) / scale_factor))
+        };
+        paint_glyphs(
+            &mut buffer,
+            font_system,
+            text.attrs.as_attrs(),
+            Some(color_at),
+        );
+    };
+    let default_color = convert_color(brush.color_at(text.position));
+    let mut pixels = vec![];
+    for layout_run in buffer.layout_runs() {
+        for glyph in layout_run.glyphs {
+            let physical_glyph = glyph.physical((0.0, 0.0), 1.0);
+            let Some(image) = swash_cache.get_image(font_system, physical_glyph.cache_key) else {
+                continue;
+            };
+            let color = glyph.color_opt.unwrap_or(default_color);
+            let left = physical_glyph.x + image.placement.left;
+            let top = layout_run.line_y as i32 + physical_glyph.y - image.placement.top;
+            let width = image.placement.width as usize;
+            let channels = match image.content {
+                SwashContent::Mask => 1,
+                SwashContent::Color => 4,
+                SwashContent::SubpixelMask => continue,
+            };
+            for (index, data) in image.data.chunks_exact(channels).enumerate() {
+                let rgba = match image.content {
+                    SwashContent::Color => [data[0], data[1], data[2], data[3]],
+                    // Coverage scales the glyph's own alpha.
+                    _ => {
+                        let alpha = data[0] as u32 * color.a() as u32 / 255;
+                        [color.r(), color.g(), color.b(), alpha as u8]
+                    }
+                };
+                let x = left + (index % width) as i32;
+                let y = top + (index / width) as i32;
+                pixels.push((Point2D::new(x, y), rgba));
+            }
+        }
+    }
+    if pixels.is_empty() {
+        return None;
+    };
+    let bounds = Box2D::from_points(pixels.iter().map(|&(point, _)| point));
+    let (width, height) = (bounds.width() as u32 + 1, bounds.height() as u32 + 1);
+    let mut rgba = vec![0; width as usize * height as usize * 4];
+    for (point, color) in pixels {
+        let offset = point - bounds.min;
+        let index = (offset.y as usize * width as usize + offset.x as usize) * 4;
+        blend(&mut rgba[index..index + 4], color);
+    }
+    let rect = Box2D::from_origin_and_size(
+        bounds.min.to_f32(),
+        Size2D::new(width as f32, height as f32),
+    )
+    .translate(origin - scaled.position);
+    Some((rgba, PhysicalSize::new(width, height), rect))
+}
+
+/// Composites the straight-alpha `color` over the straight-alpha `pixel`,
+/// where glyphs overlap.
+fn blend(pixel: &mut [u8], color: [u8; 4]) {
+    let alpha = color[3] as f32 / 255.0;
+    let below = pixel[3] as f32 / 255.0 * (1.0 - alpha);
+    let blended = alpha + below;
+    if blended == 0.0 {
+        return;
+    };
+    for (channel, value) in pixel.iter_mut().zip(color).take(3) {
+        *channel = ((value as f32 * alpha + *channel as f32 * below) / blended).round() as u8;
+    }
+    pixel[3] = (blended * 255.0).round() as u8;
+}
+
+/// Returns the two triangles which draw a whole texture over `rect` (relative
+/// to the text's position, in physical pixels), rotated by the text's angle.
+fn rotated_quad(
+    text: &Text,
+    rect: Box2D<f32>,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Vec<ImageVertex> {
+    let vertex = |x: f32, y: f32, u: f32, v: f32| {
+        let corner = text.position + Point2D::new(x, y).to_vector() / scale_factor;
+        ImageVertex {
+            point: abs_to_ndc(text.rotate(corner) * scale_factor, size).to_array(),
+            uv: [u, v],
+            // The pixels already have the brush's colors.
+            tint: [1.0; 4],
+        }
+    };
+    let (min, max) = (rect.min, rect.max);
+    let top_left = vertex(min.x, min.y, 0.0, 0.0);
+    let top_right = vertex(max.x, min.y, 1.0, 0.0);
+    let bottom_left = vertex(min.x, max.y, 0.0, 1.0);
+    let bottom_right = vertex(max.x, max.y, 1.0, 1.0);
+    vec![
+        top_left,
+        bottom_left,
+        top_right,
+        top_right,
+        bottom_left,
+        bottom_right,
+    ]
+}
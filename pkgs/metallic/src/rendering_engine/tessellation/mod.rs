@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests;
+
+use euclid::default::Point2D;
+use lyon::{
+    math::{vector, Transform},
+    tessellation::{
+        BuffersBuilder, FillOptions, FillTessellator, StrokeOptions, StrokeTessellator,
+        VertexBuffers,
+    },
+};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    primitives::{abs_to_ndc, CoordinateSpace, Ctor, DrawStyle, Shape},
+    MetallicResult,
+};
+
+/// The tessellators which shapes are run through, kept around so that their
+/// internal buffers are reused from one shape to the next.
+#[derive(Default)]
+pub struct Tessellators {
+    pub fill: FillTessellator,
+    pub stroke: StrokeTessellator,
+}
+
+/// Fills or strokes `shape` (depending on its style), approximating its
+/// curves with line segments which stray at most `tolerance` away from them.
+///
+/// The tolerance is in the shape's own units; i.e., in pixels for absolute
+/// shapes. Stroke widths are always in (logical) pixels, so strokes of NDC
+/// shapes are tessellated in the physical pixel space of a `size`d window
+/// (tolerance included), at `scale_factor` times their width, and their
+/// vertices mapped back to NDC afterwards. Strokes which aren't thicker than
+/// zero come out empty.
+pub fn tessellate_shape(
+    tessellators: &mut Tessellators,
+    shape: &Shape,
+    tolerance: f32,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> MetallicResult<VertexBuffers<Point2D<f32>, u32>> {
+    let mut geometry = VertexBuffers::new();
+    match shape.style {
+        DrawStyle::Fill => {
+            tessellators.fill.tessellate_path(
+                &shape.path,
+                &FillOptions::tolerance(tolerance).with_fill_rule(shape.fill_rule),
+                &mut BuffersBuilder::new(&mut geometry, Ctor),
+            )?;
+        }
+        _ if shape.is_empty() => (),
+        DrawStyle::Stroke {
+            width,
+            line_join,
+            line_cap,
+        } => {
+            let options = StrokeOptions::tolerance(tolerance)
+                .with_line_width(width)
+                .with_line_join(line_join)
+                .with_line_cap(line_cap);
+            match shape.coordinate_space {
+                CoordinateSpace::Absolute => {
+                    tessellators.stroke.tessellate_path(
+                        &shape.path,
+                        &options,
+                        &mut BuffersBuilder::new(&mut geometry, Ctor),
+                    )?;
+                }
+                // Stroking in NDC would stretch the line along the window's
+                // longer axis.
+                CoordinateSpace::Ndc => {
+                    let options = options.with_line_width(width * scale_factor);
+                    let (half_width, half_height) =
+                        (size.width as f32 / 2.0, size.height as f32 / 2.0);
+                    let ndc_to_abs = Transform::scale(half_width, -half_height)
+                        .then_translate(vector(half_width, half_height));
+                    tessellators.stroke.tessellate_path(
+                        &shape.path.clone().transformed(&ndc_to_abs),
+                        &options,
+                        &mut BuffersBuilder::new(&mut geometry, Ctor),
+                    )?;
+                    for vertex in &mut geometry.vertices {
+                        *vertex = abs_to_ndc(*vertex, size);
+                    }
+                }
+            };
+        }
+    };
+    Ok(geometry)
+}
@@ -0,0 +1,186 @@
+use euclid::default::Box2D;
+use lyon::{
+    path::{Path, Winding},
+    tessellation::FillRule,
+};
+use winit::dpi::PhysicalSize;
+
+use super::*;
+use crate::primitives::{ndc_to_abs, CoordinateSpace};
+
+/// Only matters for strokes of NDC shapes.
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(100, 100);
+
+fn shape(path: Path, fill_rule: FillRule) -> Shape {
+    Shape {
+        path,
+        coordinate_space: CoordinateSpace::Absolute,
+        fill_rule,
+        style: DrawStyle::Fill,
+        on_mouse_input: None,
+    }
+}
+
+fn square(min: f32, max: f32) -> Path {
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::new(Point2D::new(min, min), Point2D::new(max, max)),
+        Winding::Positive,
+    );
+    builder.build()
+}
+
+fn unit_circle() -> Path {
+    let mut builder = Path::builder();
+    builder.add_circle(Point2D::origin(), 1.0, Winding::Positive);
+    builder.build()
+}
+
+fn tessellate(shape: &Shape, tolerance: f32) -> VertexBuffers<Point2D<f32>, u32> {
+    tessellate_shape(&mut Tessellators::default(), shape, tolerance, SIZE, 1.0).unwrap()
+}
+
+/// Returns the signed area of every triangle (positive if its points run
+/// counter-clockwise with the y-axis pointing up).
+fn signed_areas(geometry: &VertexBuffers<Point2D<f32>, u32>) -> Vec<f32> {
+    geometry
+        .indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| geometry.vertices[triangle[i] as usize]);
+            (b - a).cross(c - a) / 2.0
+        })
+        .collect()
+}
+
+#[test]
+fn test_unit_square_golden() {
+    let geometry = tessellate(&shape(square(0.0, 1.0), FillRule::NonZero), 0.02);
+    assert_eq!(
+        geometry.vertices,
+        [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].map(Point2D::from)
+    );
+    assert_eq!(geometry.indices, [1, 0, 2, 1, 2, 3]);
+    assert_eq!(signed_areas(&geometry), [-0.5, -0.5]);
+}
+
+#[test]
+fn test_unit_circle_golden() {
+    let circle = shape(unit_circle(), FillRule::NonZero);
+    for (tolerance, vertex_count, index_count) in [(0.02, 24, 66), (0.1, 8, 18)] {
+        let geometry = tessellate(&circle, tolerance);
+        assert_eq!(geometry.vertices.len(), vertex_count, "{tolerance}");
+        assert_eq!(geometry.indices.len(), index_count, "{tolerance}");
+        // Flattening only adds points on (lyon's Bézier approximation of)
+        // the circle, starting at its top.
+        assert_eq!(geometry.vertices[0], Point2D::new(0.0, -1.0));
+        for vertex in &geometry.vertices {
+            assert!(
+                (vertex.to_vector().length() - 1.0).abs() < 1e-3,
+                "{vertex:?}"
+            );
+        }
+        // The triangles all face the same way, and together cover the circle
+        // (minus the slivers cut off by flattening).
+        let areas = signed_areas(&geometry);
+        assert!(areas.iter().all(|&area| area < 0.0));
+        let area = -areas.iter().sum::<f32>();
+        assert!(area < std::f32::consts::PI, "{tolerance}: {area}");
+        assert!(
+            area > std::f32::consts::PI * (1.0 - tolerance),
+            "{tolerance}: {area}"
+        );
+    }
+    // At the coarser tolerance, the circle becomes an octagon.
+    let octagon = tessellate(&circle, 0.1).vertices;
+    for vertex in octagon {
+        let angle = vertex.y.atan2(vertex.x).to_degrees();
+        assert!(
+            (angle / 45.0 - (angle / 45.0).round()).abs() < 1e-3,
+            "{angle}"
+        );
+    }
+}
+
+#[test]
+fn test_nested_squares_follow_the_fill_rule() {
+    let mut builder = Path::builder();
+    for (min, max) in [(0.0, 10.0), (2.0, 8.0)] {
+        builder.add_rectangle(
+            &Box2D::new(Point2D::new(min, min), Point2D::new(max, max)),
+            Winding::Positive,
+        );
+    }
+    let path = builder.build();
+    let area = |fill_rule| {
+        let geometry = tessellate(&shape(path.clone(), fill_rule), 0.02);
+        (
+            geometry.vertices.len(),
+            -signed_areas(&geometry).iter().sum::<f32>(),
+        )
+    };
+    // Both squares wind the same way, so the inner one only punches a hole
+    // into the outer one with the even-odd rule.
+    assert_eq!(area(FillRule::NonZero), (8, 100.0));
+    assert_eq!(area(FillRule::EvenOdd), (8, 64.0));
+}
+
+#[test]
+fn test_strokes_only_cover_the_outline() {
+    let stroked = Shape {
+        style: DrawStyle::stroke(2.0),
+        ..shape(square(0.0, 10.0), FillRule::NonZero)
+    };
+    let geometry = tessellate(&stroked, 0.02);
+    // Every vertex sits on the inner or the outer edge of the line, and the
+    // line is a 12x12 square with an 8x8 hole punched into it.
+    for vertex in &geometry.vertices {
+        let distance = (vertex.x - 5.0).abs().max((vertex.y - 5.0).abs());
+        assert!(distance == 4.0 || distance == 6.0, "{vertex:?}");
+    }
+    let area = signed_areas(&geometry).iter().sum::<f32>().abs();
+    assert!((area - (144.0 - 64.0)).abs() < 1e-3, "{area}");
+
+    for width in [0.0, -1.0, f32::NAN] {
+        let stroked = Shape {
+            style: DrawStyle::stroke(width),
+            ..shape(square(0.0, 10.0), FillRule::NonZero)
+        };
+        let geometry = tessellate(&stroked, 0.02);
+        assert!(geometry.vertices.is_empty(), "{width}");
+        assert!(geometry.indices.is_empty(), "{width}");
+    }
+}
+
+#[test]
+fn test_ndc_strokes_are_equally_thick_both_ways() {
+    let size = PhysicalSize::new(200, 100);
+    let segment = |from, to| {
+        let mut builder = Path::builder();
+        builder.begin(from);
+        builder.line_to(to);
+        builder.end(false);
+        Shape {
+            path: builder.build(),
+            coordinate_space: CoordinateSpace::Ndc,
+            fill_rule: FillRule::NonZero,
+            style: DrawStyle::stroke(4.0),
+            on_mouse_input: None,
+        }
+    };
+    let thickness = |shape: &Shape| {
+        let geometry =
+            tessellate_shape(&mut Tessellators::default(), shape, 0.02, size, 1.0).unwrap();
+        let bounds = Box2D::from_points(
+            geometry
+                .vertices
+                .iter()
+                .map(|&vertex| ndc_to_abs(vertex, size)),
+        );
+        bounds.width().min(bounds.height())
+    };
+    let horizontal = segment(Point2D::new(-0.5, 0.0), Point2D::new(0.5, 0.0));
+    let vertical = segment(Point2D::new(0.0, -0.5), Point2D::new(0.0, 0.5));
+    assert!((thickness(&horizontal) - 4.0).abs() < 1e-3);
+    assert!((thickness(&vertical) - 4.0).abs() < 1e-3);
+}
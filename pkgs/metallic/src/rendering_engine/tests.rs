@@ -0,0 +1,3000 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use euclid::{default::Point2D, Box2D, Size2D};
+use glyphon::{fontdb::Database, Buffer, FontSystem, Metrics, Resolution, Shaping};
+use image::DynamicImage;
+use lyon::{
+    path::{Path, Winding},
+    tessellation::FillRule,
+};
+use wgpu::{Color, CompositeAlphaMode, FilterMode, Limits, PresentMode};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, MouseButton, TouchPhase},
+};
+
+use super::{
+    benchmark::BenchmarkReport,
+    buffer_bundle::{new_buffer_bundle, prepare_geometry},
+    fps::{FpsBundle, FPS_HANDLE, FPS_WINDOW},
+    glyph_bundle::{convert_color, load_font_file, text_top, CachedBuffer},
+    io::MouseInput,
+    layout::object_bounds,
+    lifecycle::TickBundle,
+    offscreen::{linear_to_srgb, padded_bytes_per_row, read_back, to_rgba_image},
+    pacing::DEFAULT_MAX_FRAMES_IN_FLIGHT,
+    texture_pool::TextureKey,
+    wgpu_bundle::{
+        new_headless_wgpu_bundle, new_shape_pipeline, select_alpha_mode, select_present_mode,
+        select_sample_count, supported_sample_counts,
+    },
+    *,
+};
+use crate::{
+    primitives::{
+        to_vertex_color, CoordinateSpace, DrawStyle, Mesh, Shape, ShapeBuilder, TextAlign,
+        TextAnchor, Wrap,
+    },
+    MetallicError,
+};
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(100, 100);
+
+fn square(x: f32, y: f32, length: f32, color: Color) -> Object {
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::from_origin_and_size(Point2D::new(x, y), Size2D::new(length, length)),
+        Winding::Positive,
+    );
+    Object::shape(
+        Shape {
+            path: builder.build(),
+            coordinate_space: CoordinateSpace::Absolute,
+            fill_rule: FillRule::NonZero,
+            style: DrawStyle::Fill,
+            on_mouse_input: None,
+        },
+        Brush::Solid(color),
+    )
+}
+
+fn text(content: &str) -> Object {
+    Text::new(content).fill(Brush::Solid(Color::WHITE))
+}
+
+fn annulus(inner_winding: Winding, fill_rule: FillRule) -> Object {
+    let center = Point2D::new(50.0, 50.0);
+    let mut builder = Path::builder();
+    builder.add_circle(center, 40.0, Winding::Positive);
+    builder.add_circle(center, 20.0, inner_winding);
+    Object::shape(
+        Shape {
+            path: builder.build(),
+            coordinate_space: CoordinateSpace::Absolute,
+            fill_rule,
+            style: DrawStyle::Fill,
+            on_mouse_input: None,
+        },
+        Brush::Solid(Color::WHITE),
+    )
+}
+
+/// Checks whether any of the tessellated triangles covers the given point
+/// (in normalized device coordinates).
+fn covers(vertices: &[Vertex], indices: &[u32], [x, y]: [f32; 2]) -> bool {
+    indices.chunks_exact(3).any(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].point);
+        let edge =
+            |[x1, y1]: [f32; 2], [x2, y2]: [f32; 2]| (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+        let signs = [edge(a, b), edge(b, c), edge(c, a)];
+        signs.iter().all(|&sign| sign >= 0.0) || signs.iter().all(|&sign| sign <= 0.0)
+    })
+}
+
+#[test]
+fn test_benchmark_report_percentiles() {
+    let frame_times = (1..=100).rev().map(Duration::from_millis).collect();
+    let report = BenchmarkReport::new(PresentMode::Immediate, frame_times, 0);
+    assert_eq!(report.frames, 100);
+    assert_eq!(report.average, Duration::from_micros(50_500));
+    assert_eq!(report.p50, Duration::from_millis(50));
+    assert_eq!(report.p95, Duration::from_millis(95));
+    assert_eq!(report.p99, Duration::from_millis(99));
+    assert_eq!(report.max, Duration::from_millis(100));
+}
+
+#[test]
+fn test_benchmark_report_without_frames() {
+    let report = BenchmarkReport::new(PresentMode::Immediate, vec![], 0);
+    assert_eq!(report.frames, 0);
+    assert_eq!(report.average, Duration::ZERO);
+    assert_eq!(report.p99, Duration::ZERO);
+    assert_eq!(report.max, Duration::ZERO);
+}
+
+#[test]
+fn test_objects_are_ordered_by_layer_then_insertion() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let mut insert = |layer, content| {
+        scene_bundle.layer = layer;
+        scene_bundle.insert(Uuid::new_v4(), text(content));
+    };
+    insert(2, "c");
+    insert(0, "a");
+    insert(2, "d");
+    insert(5, "f");
+    insert(0, "b");
+    insert(2, "e");
+    let order = scene_bundle
+        .objects
+        .iter()
+        .map(|(_, object, layer)| match &object.kind {
+            ObjectKind::Text(text) => (*layer, text.content.as_str()),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        [(0, "a"), (0, "b"), (2, "c"), (2, "d"), (2, "e"), (5, "f")]
+    );
+}
+
+#[test]
+fn test_push_layer_past_the_maximum_fails() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    for _ in 1..MAX_LAYERS {
+        scene_bundle.push_layer().unwrap();
+    }
+    assert_eq!(scene_bundle.layer, MAX_LAYERS - 1);
+    assert!(matches!(
+        scene_bundle.push_layer(),
+        Err(MetallicError::TooManyLayersError {
+            max_layers: MAX_LAYERS
+        }),
+    ));
+    assert_eq!(scene_bundle.layer, MAX_LAYERS - 1);
+}
+
+#[test]
+fn test_swapping_layers_flips_render_order() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let contents = |scene_bundle: &SceneBundle| {
+        scene_bundle
+            .objects
+            .iter()
+            .map(|(_, object, layer)| match &object.kind {
+                ObjectKind::Text(text) => (*layer, text.content.clone()),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let expected = |contents: &[(usize, &str)]| {
+        contents
+            .iter()
+            .map(|&(layer, content)| (layer, content.to_string()))
+            .collect::<Vec<_>>()
+    };
+    for (layer, content) in [(0, "a"), (1, "b"), (1, "c"), (2, "d")] {
+        scene_bundle.layer = layer;
+        scene_bundle.insert(Uuid::new_v4(), text(content));
+    }
+    scene_bundle.hidden_layers.insert(0);
+
+    scene_bundle.remap_layers(|layer| swapped_layer(layer, 0, 1));
+    assert_eq!(
+        contents(&scene_bundle),
+        expected(&[(0, "b"), (0, "c"), (1, "a"), (2, "d")])
+    );
+    assert_eq!(scene_bundle.hidden_layers, HashSet::from([1]));
+
+    scene_bundle.remap_layers(|layer| moved_layer(layer, 2, 0));
+    assert_eq!(
+        contents(&scene_bundle),
+        expected(&[(0, "d"), (1, "b"), (1, "c"), (2, "a")])
+    );
+    scene_bundle.remap_layers(|layer| moved_layer(layer, 0, 2));
+    assert_eq!(
+        contents(&scene_bundle),
+        expected(&[(0, "b"), (0, "c"), (1, "a"), (2, "d")])
+    );
+
+    // The (squares') geometry is drawn in the new order as well.
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.layer = 1;
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::BLUE));
+    scene_bundle.remap_layers(|layer| swapped_layer(layer, 0, 1));
+    let Geometry { vertices, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(vertices[0].color, to_vertex_color(Color::BLUE));
+    assert_eq!(vertices.last().unwrap().color, to_vertex_color(Color::RED));
+}
+
+#[test]
+fn test_scene_bundle_is_only_dirty_after_changes() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    assert!(scene_bundle.dirty);
+
+    // What `render` does after presenting a frame.
+    scene_bundle.dirty = false;
+    scene_bundle.set_layer_visible(1, true);
+    scene_bundle.clear();
+    assert!(!scene_bundle.dirty);
+
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED));
+    assert!(scene_bundle.dirty);
+
+    scene_bundle.dirty = false;
+    scene_bundle.set_layer_visible(0, false);
+    assert!(scene_bundle.dirty);
+
+    scene_bundle.dirty = false;
+    scene_bundle.set_layer_visible(0, false);
+    assert!(!scene_bundle.dirty);
+    scene_bundle.clear();
+    assert!(scene_bundle.dirty);
+}
+
+#[test]
+fn test_remove_all_only_removes_the_given_objects() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let retained = Uuid::new_v4();
+    let immediate = Uuid::new_v4();
+    scene_bundle.insert(retained, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.insert(immediate, square(20.0, 0.0, 10.0, Color::BLUE));
+
+    scene_bundle.dirty = false;
+    scene_bundle.remove_all(&HashSet::from([immediate]));
+    assert!(scene_bundle.dirty);
+    let handles = scene_bundle
+        .objects
+        .iter()
+        .map(|&(handle, _, _)| handle)
+        .collect::<Vec<_>>();
+    assert_eq!(handles, [retained]);
+
+    // Nothing left to remove, so the next frame doesn't change.
+    scene_bundle.dirty = false;
+    scene_bundle.remove_all(&HashSet::from([immediate]));
+    assert!(!scene_bundle.dirty);
+}
+
+#[test]
+fn test_removing_an_object_keeps_the_others_in_order() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let mut handles = vec![];
+    for (index, color) in [Color::RED, Color::GREEN, Color::BLUE]
+        .into_iter()
+        .enumerate()
+    {
+        let handle = Uuid::new_v4();
+        scene_bundle.insert(handle, square(index as f32 * 20.0, 0.0, 10.0, color));
+        handles.push(handle);
+        scene_bundle.push_layer().unwrap();
+    }
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+
+    scene_bundle.dirty = false;
+    let removed = scene_bundle.remove(handles[1]).unwrap();
+    assert_eq!(removed.brush, Brush::Solid(Color::GREEN));
+    assert!(scene_bundle.dirty);
+    assert!(scene_bundle.remove(handles[1]).is_none());
+
+    let geometry = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    let drawn = geometry
+        .objects
+        .iter()
+        .map(|&(handle, _, _)| handle)
+        .collect::<Vec<_>>();
+    assert_eq!(drawn, [handles[0], handles[2]]);
+    let layers = geometry
+        .layers
+        .iter()
+        .map(|&(layer, _)| layer)
+        .collect::<Vec<_>>();
+    assert_eq!(layers, [0, 2]);
+    assert!(!scene_bundle.tessellations.contains_key(&handles[1]));
+
+    // The remaining handles still point at their objects.
+    let blue = scene_bundle.object_mut(handles[2]).unwrap();
+    assert_eq!(blue.brush, Brush::Solid(Color::BLUE));
+    *blue = square(40.0, 40.0, 10.0, Color::BLUE);
+    let misses = scene_bundle.tessellation_stats.misses;
+    let geometry = tessellate_scene_with(&mut scene_bundle, SIZE, to_unscaled_vertex).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, misses + 1);
+    let (_, vertices, _) = geometry.objects[1].clone();
+    assert!(
+        geometry.vertices[vertices.start as usize..vertices.end as usize]
+            .iter()
+            .all(|vertex| vertex.point[0] >= 40.0)
+    );
+}
+
+#[test]
+fn test_set_brush_recolors_the_next_frame() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle.insert(handle, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.dirty = false;
+
+    assert_eq!(
+        scene_bundle.set_brush(handle, Brush::Solid(Color::RED)),
+        Some(Brush::Solid(Color::RED))
+    );
+    assert!(!scene_bundle.dirty);
+    assert_eq!(
+        scene_bundle.set_brush(handle, Brush::Solid(Color::BLUE)),
+        Some(Brush::Solid(Color::RED))
+    );
+    assert!(scene_bundle.dirty);
+    assert_eq!(
+        scene_bundle.object(handle).map(|object| &object.brush),
+        Some(&Brush::Solid(Color::BLUE))
+    );
+    assert_eq!(
+        scene_bundle.set_brush(Uuid::new_v4(), Brush::Solid(Color::GREEN)),
+        None
+    );
+
+    let Geometry { vertices, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert!(vertices
+        .iter()
+        .all(|vertex| vertex.color == to_vertex_color(Color::BLUE)));
+}
+
+#[test]
+fn test_hidden_layers_are_not_tessellated() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle
+        .objects
+        .push((Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED), 0));
+    scene_bundle
+        .objects
+        .push((Uuid::new_v4(), square(20.0, 20.0, 10.0, Color::BLUE), 1));
+    let Geometry {
+        vertices: all_vertices,
+        indices: all_indices,
+        ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+
+    scene_bundle.hidden_layers.insert(1);
+    let Geometry {
+        vertices, indices, ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert!(vertices.len() < all_vertices.len());
+    assert!(indices.len() < all_indices.len());
+    assert!(vertices
+        .iter()
+        .all(|vertex| vertex.color == to_vertex_color(Color::RED)));
+
+    scene_bundle.hidden_layers.remove(&1);
+    let Geometry {
+        vertices, indices, ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(vertices, all_vertices);
+    assert_eq!(indices, all_indices);
+}
+
+#[test]
+fn test_hit_test_scene_returns_topmost_visible_shape() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let bottom = Uuid::new_v4();
+    let top = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((bottom, square(0.0, 0.0, 20.0, Color::RED), 0));
+    scene_bundle
+        .objects
+        .push((top, square(10.0, 10.0, 20.0, Color::BLUE), 1));
+
+    let hit = |x, y| hit_test_scene(&scene_bundle, PhysicalPosition::new(x, y), SIZE, 0.0);
+    assert_eq!(hit(5.0, 5.0), Some(bottom));
+    assert_eq!(hit(15.0, 15.0), Some(top));
+    assert_eq!(hit(50.0, 50.0), None);
+
+    scene_bundle.hidden_layers.insert(1);
+    let hit = |x, y| hit_test_scene(&scene_bundle, PhysicalPosition::new(x, y), SIZE, 0.0);
+    assert_eq!(hit(15.0, 15.0), Some(bottom));
+    assert_eq!(hit(25.0, 25.0), None);
+}
+
+#[test]
+fn test_scene_hits_follow_the_scene() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let hits = |scene_bundle: &SceneBundle, x, y, size| {
+        scene_hits(scene_bundle, PhysicalPosition::new(x, y), size, 0.0).collect::<Vec<_>>()
+    };
+    let bottom = Uuid::new_v4();
+    scene_bundle.insert(bottom, square(0.0, 0.0, 20.0, Color::RED));
+    assert_eq!(hits(&scene_bundle, 15.0, 15.0, SIZE), [bottom]);
+
+    // Objects added and removed after the index was built are kept track of.
+    let top = Uuid::new_v4();
+    scene_bundle.insert(top, square(10.0, 10.0, 20.0, Color::BLUE));
+    assert_eq!(hits(&scene_bundle, 15.0, 15.0, SIZE), [top, bottom]);
+    assert_eq!(hits(&scene_bundle, 25.0, 25.0, SIZE), [top]);
+    scene_bundle.remove(bottom);
+    assert_eq!(hits(&scene_bundle, 15.0, 15.0, SIZE), [top]);
+
+    // And so are objects which are changed in place.
+    scene_bundle.object_mut(top).unwrap().kind = square(50.0, 50.0, 20.0, Color::BLUE).kind;
+    assert!(hits(&scene_bundle, 15.0, 15.0, SIZE).is_empty());
+    assert_eq!(hits(&scene_bundle, 60.0, 60.0, SIZE), [top]);
+
+    // NDC objects move along with the size.
+    let ndc = Uuid::new_v4();
+    scene_bundle.insert(
+        ndc,
+        ShapeBuilder::new()
+            .coordinate_space(CoordinateSpace::Ndc)
+            .rect(Point2D::new(-1.0, 0.0), Size2D::new(1.0, 1.0))
+            .fill(Brush::Solid(Color::GREEN)),
+    );
+    assert_eq!(hits(&scene_bundle, 40.0, 40.0, SIZE), [ndc]);
+    let size = PhysicalSize::new(SIZE.width * 2, SIZE.height * 2);
+    assert!(hits(&scene_bundle, 60.0, 60.0, size).contains(&ndc));
+
+    scene_bundle.clear();
+    assert!(hits(&scene_bundle, 40.0, 40.0, SIZE).is_empty());
+}
+
+#[test]
+fn test_hit_tolerance_reaches_past_the_edges() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let rect = Uuid::new_v4();
+    let triangle = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((rect, square(10.0, 10.0, 20.0, Color::RED), 0));
+    scene_bundle.objects.push((
+        triangle,
+        Object::mesh(
+            Mesh::new(
+                vec![
+                    Point2D::new(60.0, 60.0),
+                    Point2D::new(80.0, 60.0),
+                    Point2D::new(60.0, 80.0),
+                ],
+                vec![0, 1, 2],
+            )
+            .unwrap(),
+            Brush::Solid(Color::BLUE),
+        ),
+        0,
+    ));
+
+    let hit = |x, y, hit_tolerance| {
+        hit_test_scene(
+            &scene_bundle,
+            PhysicalPosition::new(x, y),
+            SIZE,
+            hit_tolerance,
+        )
+    };
+    assert_eq!(hit(33.0, 20.0, 0.0), None);
+    assert_eq!(hit(33.0, 20.0, 5.0), Some(rect));
+    assert_eq!(hit(20.0, 7.0, 5.0), Some(rect));
+    assert_eq!(hit(37.0, 20.0, 5.0), None);
+    assert_eq!(hit(57.0, 70.0, 0.0), None);
+    assert_eq!(hit(57.0, 70.0, 5.0), Some(triangle));
+    assert_eq!(hit(75.0, 75.0, 5.0), None);
+}
+
+#[test]
+fn test_strokes_are_only_hit_on_their_line() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let outline = Uuid::new_v4();
+    scene_bundle.objects.push((
+        outline,
+        ShapeBuilder::new()
+            .rect(Point2D::new(10.0, 10.0), Size2D::new(40.0, 40.0))
+            .stroke(4.0, Brush::Solid(Color::RED)),
+        0,
+    ));
+
+    let hit = |x, y, hit_tolerance| {
+        hit_test_scene(
+            &scene_bundle,
+            PhysicalPosition::new(x, y),
+            SIZE,
+            hit_tolerance,
+        )
+    };
+    assert_eq!(hit(30.0, 30.0, 0.0), None);
+    assert_eq!(hit(11.5, 30.0, 0.0), Some(outline));
+    assert_eq!(hit(8.5, 30.0, 0.0), Some(outline));
+    assert_eq!(hit(6.0, 30.0, 0.0), None);
+    assert_eq!(hit(6.0, 30.0, 2.0), Some(outline));
+}
+
+#[test]
+fn test_images_are_hit_within_their_rect() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    let image = Image::new(
+        Arc::new(DynamicImage::new_rgba8(1, 1)),
+        Box2D::new(Point2D::new(10.0, 10.0), Point2D::new(30.0, 20.0)),
+    );
+    scene_bundle
+        .objects
+        .push((handle, image.fill(Brush::Solid(Color::WHITE)), 0));
+
+    let hit = |x, y, hit_tolerance| {
+        hit_test_scene(
+            &scene_bundle,
+            PhysicalPosition::new(x, y),
+            SIZE,
+            hit_tolerance,
+        )
+    };
+    assert_eq!(hit(20.0, 15.0, 0.0), Some(handle));
+    assert_eq!(hit(30.0, 20.0, 0.0), Some(handle));
+    assert_eq!(hit(33.0, 15.0, 0.0), None);
+    assert_eq!(hit(33.0, 15.0, 5.0), Some(handle));
+    let Geometry { vertices, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert!(vertices.is_empty());
+}
+
+#[test]
+fn test_io_bundle_records_interactions() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((handle, square(0.0, 0.0, 20.0, Color::RED), 0));
+    let mut io_bundle = IoBundle::default();
+
+    io_bundle.register(
+        IoEvent::MouseInput {
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    assert!(io_bundle.interactions().is_empty());
+
+    io_bundle.register(
+        IoEvent::CursorMoved {
+            position: PhysicalPosition::new(5.0, 5.0),
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    io_bundle.register(
+        IoEvent::MouseInput {
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    io_bundle.register(
+        IoEvent::CursorMoved {
+            position: PhysicalPosition::new(50.0, 50.0),
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    io_bundle.register(
+        IoEvent::MouseInput {
+            state: ElementState::Released,
+            button: MouseButton::Left,
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    assert_eq!(
+        io_bundle.interactions(),
+        [
+            (handle, InteractionKind::Entered),
+            (handle, InteractionKind::Hovered),
+            (handle, InteractionKind::Pressed(MouseButton::Left)),
+            (handle, InteractionKind::Exited),
+        ]
+    );
+
+    io_bundle.end_frame();
+    assert!(io_bundle.interactions().is_empty());
+}
+
+#[test]
+fn test_mouse_input_reaches_the_topmost_shapes_callback() {
+    let clicks = Rc::new(RefCell::new(vec![]));
+    let recorder = |name| {
+        let clicks = Rc::clone(&clicks);
+        move |mouse_input: &MouseInput| clicks.borrow_mut().push((name, *mouse_input))
+    };
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let bottom = Uuid::new_v4();
+    let top = Uuid::new_v4();
+    scene_bundle.insert(
+        bottom,
+        ShapeBuilder::new()
+            .rect(Point2D::origin(), Size2D::new(20.0, 20.0))
+            .on_mouse_input(recorder("bottom"))
+            .fill(Brush::Solid(Color::RED)),
+    );
+    scene_bundle.insert(
+        top,
+        ShapeBuilder::new()
+            .rect(Point2D::new(10.0, 10.0), Size2D::new(20.0, 20.0))
+            .on_mouse_input(recorder("top"))
+            .fill(Brush::Solid(Color::BLUE)),
+    );
+    let mut io_bundle = IoBundle::default();
+    let mut click = |x, y, state| {
+        let position = PhysicalPosition::new(x, y);
+        io_bundle.register(IoEvent::CursorMoved { position }, &scene_bundle, SIZE);
+        let button = MouseButton::Left;
+        io_bundle.register(IoEvent::MouseInput { state, button }, &scene_bundle, SIZE);
+        let mouse_input = io_bundle.take_mouse_input()?;
+        let callback = scene_bundle.mouse_input_callback(mouse_input.handle)?;
+        callback(&mouse_input);
+        Some(mouse_input)
+    };
+
+    let pressed = click(15.0, 15.0, ElementState::Pressed).unwrap();
+    assert_eq!(pressed.handle, top);
+    assert_eq!(pressed.position, PhysicalPosition::new(15.0, 15.0));
+    let released = click(5.0, 5.0, ElementState::Released).unwrap();
+    assert_eq!(released.handle, bottom);
+    assert_eq!(click(50.0, 50.0, ElementState::Pressed), None);
+    assert_eq!(*clicks.borrow(), [("top", pressed), ("bottom", released)]);
+}
+
+#[test]
+fn test_io_bundle_tracks_hover_enter_and_exit() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let bottom = Uuid::new_v4();
+    let top = Uuid::new_v4();
+    scene_bundle.objects.extend([
+        (bottom, square(0.0, 0.0, 20.0, Color::RED), 0),
+        (top, square(10.0, 10.0, 20.0, Color::BLUE), 1),
+    ]);
+    let mut io_bundle = IoBundle::default();
+    let mut move_to = |x, y| {
+        io_bundle.register(
+            IoEvent::CursorMoved {
+                position: PhysicalPosition::new(x, y),
+            },
+            &scene_bundle,
+            SIZE,
+        );
+        let transitions = io_bundle
+            .interactions()
+            .iter()
+            .copied()
+            .filter(|&(_, kind)| kind != InteractionKind::Hovered)
+            .collect::<Vec<_>>();
+        io_bundle.end_frame();
+        (io_bundle.hovered(), transitions)
+    };
+
+    assert_eq!(
+        move_to(5.0, 5.0),
+        (Some(bottom), vec![(bottom, InteractionKind::Entered)])
+    );
+    assert_eq!(move_to(6.0, 6.0), (Some(bottom), vec![]));
+    assert_eq!(
+        move_to(15.0, 15.0),
+        (
+            Some(top),
+            vec![
+                (bottom, InteractionKind::Exited),
+                (top, InteractionKind::Entered)
+            ]
+        )
+    );
+    assert_eq!(
+        move_to(50.0, 50.0),
+        (None, vec![(top, InteractionKind::Exited)])
+    );
+
+    move_to(5.0, 5.0);
+    io_bundle.register(IoEvent::CursorLeft, &scene_bundle, SIZE);
+    assert_eq!(io_bundle.hovered(), None);
+    assert_eq!(
+        io_bundle.interactions(),
+        [(bottom, InteractionKind::Exited)]
+    );
+}
+
+#[test]
+fn test_validate_size_against_limits() {
+    let limits = Limits::downlevel_defaults();
+    let max_dimension = limits.max_texture_dimension_2d;
+    assert!(validate_size(PhysicalSize::new(max_dimension, max_dimension), &limits).is_ok());
+    for size in [
+        PhysicalSize::new(max_dimension + 1, 10),
+        PhysicalSize::new(10, max_dimension + 1),
+    ] {
+        match validate_size(size, &limits) {
+            Err(MetallicError::SizeExceedsLimitsError {
+                width,
+                height,
+                max_dimension: actual_max_dimension,
+            }) => {
+                assert_eq!(PhysicalSize::new(width, height), size);
+                assert_eq!(actual_max_dimension, max_dimension);
+            }
+            result => panic!("Expected a size-exceeds-limits error, got {:?}", result),
+        };
+    }
+}
+
+#[test]
+fn test_select_alpha_mode() {
+    let opaque_first = [
+        CompositeAlphaMode::Opaque,
+        CompositeAlphaMode::PostMultiplied,
+        CompositeAlphaMode::PreMultiplied,
+    ];
+    assert_eq!(
+        select_alpha_mode(&opaque_first, false),
+        Some(CompositeAlphaMode::Opaque)
+    );
+    assert_eq!(
+        select_alpha_mode(&opaque_first, true),
+        Some(CompositeAlphaMode::PreMultiplied)
+    );
+    assert_eq!(
+        select_alpha_mode(&opaque_first[..2], true),
+        Some(CompositeAlphaMode::PostMultiplied)
+    );
+    assert_eq!(
+        select_alpha_mode(&[CompositeAlphaMode::Opaque], true),
+        Some(CompositeAlphaMode::Opaque)
+    );
+    assert_eq!(select_alpha_mode(&[], true), None);
+}
+
+#[test]
+fn test_select_present_mode() {
+    let fifo_last = [PresentMode::Immediate, PresentMode::Fifo];
+    assert_eq!(
+        select_present_mode(&fifo_last, PresentMode::Immediate),
+        Some(PresentMode::Immediate)
+    );
+    assert_eq!(
+        select_present_mode(&fifo_last, PresentMode::Mailbox),
+        Some(PresentMode::Fifo)
+    );
+    assert_eq!(
+        select_present_mode(&fifo_last, PresentMode::AutoNoVsync),
+        Some(PresentMode::AutoNoVsync)
+    );
+    // Without Fifo (e.g., on some headless setups), anything supported goes.
+    assert_eq!(
+        select_present_mode(&[PresentMode::Immediate], PresentMode::Mailbox),
+        Some(PresentMode::Immediate)
+    );
+    assert_eq!(select_present_mode(&[], PresentMode::AutoVsync), None);
+}
+
+#[test]
+fn test_select_sample_count() {
+    let features = |flags| wgpu::TextureFormatFeatures {
+        allowed_usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        flags,
+    };
+    let x4 = wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X4;
+    let resolvable = x4 | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+    assert_eq!(supported_sample_counts(&features(resolvable)), [1, 4]);
+    // Multisampled targets which can't be resolved are of no use.
+    assert_eq!(supported_sample_counts(&features(x4)), [1]);
+
+    assert_eq!(select_sample_count(&[1, 4], 4), 4);
+    assert_eq!(select_sample_count(&[1, 4], 8), 1);
+    assert_eq!(select_sample_count(&[1], 2), 1);
+}
+
+#[test]
+fn test_clear_color_is_premultiplied_for_transparent_surfaces() {
+    let background_color = Color {
+        r: 1.0,
+        g: 0.5,
+        b: 0.0,
+        a: 0.5,
+    };
+    assert_eq!(
+        clear_color(background_color, CompositeAlphaMode::PreMultiplied),
+        Color {
+            r: 0.5,
+            g: 0.25,
+            b: 0.0,
+            a: 0.5,
+        }
+    );
+    assert_eq!(
+        clear_color(background_color, CompositeAlphaMode::Opaque),
+        background_color
+    );
+    assert_eq!(
+        clear_color(Color::TRANSPARENT, CompositeAlphaMode::PreMultiplied),
+        Color::TRANSPARENT
+    );
+}
+
+#[test]
+fn test_annulus_leaves_its_center_empty() {
+    let center = [0.0, 0.0];
+    let ring = [0.6, 0.0];
+    for (inner_winding, fill_rule) in [
+        (Winding::Negative, FillRule::NonZero),
+        (Winding::Negative, FillRule::EvenOdd),
+        (Winding::Positive, FillRule::EvenOdd),
+    ] {
+        let mut scene_bundle = SceneBundle::new(Color::BLACK);
+        scene_bundle
+            .objects
+            .push((Uuid::new_v4(), annulus(inner_winding, fill_rule), 0));
+        let Geometry {
+            vertices, indices, ..
+        } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+        assert!(!covers(&vertices, &indices, center));
+        assert!(covers(&vertices, &indices, ring));
+    }
+}
+
+#[test]
+fn test_same_winding_contours_fill_under_non_zero() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.objects.push((
+        Uuid::new_v4(),
+        annulus(Winding::Positive, FillRule::NonZero),
+        0,
+    ));
+    let Geometry {
+        vertices, indices, ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert!(covers(&vertices, &indices, [0.0, 0.0]));
+    assert!(covers(&vertices, &indices, [0.6, 0.0]));
+}
+
+#[test]
+fn test_tessellate_scene_with_unscaled_vertices() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle
+        .objects
+        .push((Uuid::new_v4(), square(10.0, 20.0, 30.0, Color::RED), 0));
+    let Geometry {
+        vertices, indices, ..
+    } = tessellate_scene_with(&mut scene_bundle, SIZE, to_unscaled_vertex).unwrap();
+    assert_eq!(indices.len(), 6);
+    let mut points = vertices
+        .iter()
+        .map(|vertex| vertex.point)
+        .collect::<Vec<_>>();
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(
+        points,
+        [[10.0, 20.0], [10.0, 50.0], [40.0, 20.0], [40.0, 50.0]]
+    );
+}
+
+#[test]
+fn test_geometry_is_split_by_layer() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.objects.extend([
+        (Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED), 0),
+        (Uuid::new_v4(), text("middle"), 1),
+        (Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED), 2),
+        (Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED), 2),
+        (Uuid::new_v4(), text("top"), 2),
+    ]);
+    let Geometry {
+        indices, layers, ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(indices.len(), 18);
+    assert_eq!(layers, [(0, 0..6), (1, 6..6), (2, 6..18)]);
+
+    let contents = |layer| {
+        texts_in_layer(&scene_bundle, layer)
+            .into_iter()
+            .map(|(_, text, _)| text.content.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert!(contents(0).is_empty());
+    assert_eq!(contents(1), ["middle"]);
+    assert_eq!(contents(2), ["top"]);
+
+    scene_bundle.hidden_layers.insert(1);
+    let Geometry { layers, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(layers, [(0, 0..6), (2, 6..18)]);
+}
+
+#[test]
+fn test_text_is_added_to_the_current_layer() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), text("bottom"));
+    scene_bundle.push_layer().unwrap();
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.insert(Uuid::new_v4(), text("top"));
+    // Popping back down adds to the bottom layer again.
+    scene_bundle.layer -= 1;
+    scene_bundle.insert(Uuid::new_v4(), text("bottom again"));
+
+    let contents = |layer| {
+        texts_in_layer(&scene_bundle, layer)
+            .into_iter()
+            .map(|(_, text, _)| text.content.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(contents(0), ["bottom", "bottom again"]);
+    assert_eq!(contents(1), ["top"]);
+}
+
+#[test]
+fn test_empty_objects_produce_no_geometry() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let empty_shape = Object::shape(Shape::default(), Brush::Solid(Color::RED));
+    let empty_mesh = Object::mesh(Mesh::default(), Brush::Solid(Color::RED));
+    scene_bundle.insert(Uuid::new_v4(), empty_shape.clone());
+    scene_bundle.insert(Uuid::new_v4(), empty_mesh);
+    scene_bundle.layer = 1;
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.insert(Uuid::new_v4(), empty_shape);
+    let Geometry {
+        vertices,
+        indices,
+        layers,
+        ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(vertices.len(), 4);
+    assert_eq!(layers, [(0, 0..0), (1, 0..6)]);
+    assert!(indices
+        .iter()
+        .all(|&index| (index as usize) < vertices.len()));
+
+    // An empty scene still yields (empty) buffers, without any validation
+    // errors.
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    scene_bundle.layer = 0;
+    scene_bundle.clear();
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        Object::shape(Shape::default(), Brush::Solid(Color::RED)),
+    );
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let geometry = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+    assert!(pollster::block_on(device.pop_error_scope()).is_none());
+    assert_eq!(buffer_bundle.layers, [(0, 0..0)]);
+}
+
+#[test]
+fn test_meshes_bypass_the_tessellator() {
+    let mesh = Mesh::new(
+        vec![
+            Point2D::new(10.0, 10.0),
+            Point2D::new(30.0, 10.0),
+            Point2D::new(30.0, 30.0),
+            Point2D::new(10.0, 30.0),
+        ],
+        vec![0, 1, 2, 0, 2, 3],
+    )
+    .unwrap();
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 5.0, Color::RED));
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    let misses = scene_bundle.tessellation_stats.misses;
+
+    let handle = Uuid::new_v4();
+    scene_bundle.insert(
+        handle,
+        Object::mesh(mesh.clone(), Brush::Solid(Color::BLUE)),
+    );
+    let Geometry {
+        vertices, indices, ..
+    } = tessellate_scene_with(&mut scene_bundle, SIZE, to_unscaled_vertex).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, misses);
+    assert_eq!(
+        vertices[4..]
+            .iter()
+            .map(|vertex| vertex.point)
+            .collect::<Vec<_>>(),
+        mesh.vertices()
+            .iter()
+            .map(|point_2d| point_2d.to_array())
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(indices[6..], [4, 5, 6, 4, 6, 7]);
+
+    let hit = |x, y| hit_test_scene(&scene_bundle, PhysicalPosition::new(x, y), SIZE, 0.0);
+    assert_eq!(hit(25.0, 15.0), Some(handle));
+    assert_eq!(hit(15.0, 25.0), Some(handle));
+    assert_eq!(hit(40.0, 40.0), None);
+}
+
+#[test]
+fn test_ticks_measure_time_within_an_animation() {
+    let mut tick_bundle: TickBundle = TickBundle::default();
+    let start = Instant::now();
+    assert_eq!(tick_bundle.elapsed(start), Duration::ZERO);
+    tick_bundle.record(start, true);
+
+    let next = start + Duration::from_millis(16);
+    assert_eq!(tick_bundle.elapsed(next), Duration::from_millis(16));
+    tick_bundle.record(next, false);
+
+    // A new animation doesn't count the time spent idling.
+    let later = next + Duration::from_secs(10);
+    assert_eq!(tick_bundle.elapsed(later), Duration::ZERO);
+}
+
+#[test]
+fn test_tick_hooks_can_clear_themselves() {
+    #[derive(Default)]
+    struct Context {
+        tick_bundle: TickBundle<Context>,
+        ticks: usize,
+    }
+    let tick = |context: &mut Context| {
+        TickBundle::tick(context, |context| &mut context.tick_bundle, Instant::now())
+    };
+    let mut context = Context::default();
+    assert_eq!(tick(&mut context), None);
+
+    // Hooks are kept around from one tick to the next...
+    context.tick_bundle.set(Box::new(|context, _| {
+        context.ticks += 1;
+        true
+    }));
+    assert_eq!(tick(&mut context), Some(true));
+    assert_eq!(tick(&mut context), Some(true));
+    assert_eq!(context.ticks, 2);
+
+    // ...unless they clear themselves.
+    context.tick_bundle.set(Box::new(|context, _| {
+        context.ticks += 1;
+        context.tick_bundle.clear();
+        true
+    }));
+    assert_eq!(tick(&mut context), Some(true));
+    assert_eq!(tick(&mut context), None);
+    assert_eq!(context.ticks, 3);
+}
+
+#[test]
+fn test_fps_is_a_rolling_average_of_frame_times() {
+    let mut fps_bundle = FpsBundle::default();
+    let start = Instant::now();
+    fps_bundle.record(start);
+    assert_eq!(fps_bundle.frame_time(), None);
+
+    // Only the last `FPS_WINDOW` frames count: one slow frame, then a full
+    // window of fast ones.
+    let mut now = start + Duration::from_millis(100);
+    fps_bundle.record(now);
+    assert_eq!(fps_bundle.frame_time(), Some(Duration::from_millis(100)));
+    for _ in 0..FPS_WINDOW {
+        now += Duration::from_millis(10);
+        fps_bundle.record(now);
+    }
+    assert_eq!(fps_bundle.frame_time(), Some(Duration::from_millis(10)));
+    assert_eq!(fps_bundle.overlay().0.content, "100 FPS\n10.00 ms");
+
+    // Time spent paused isn't a frame.
+    fps_bundle.pause();
+    fps_bundle.record(now + Duration::from_secs(10));
+    assert_eq!(fps_bundle.frame_time(), Some(Duration::from_millis(10)));
+}
+
+#[test]
+fn test_pacing_bundle_tracks_frames_in_flight() {
+    let pacing_bundle = PacingBundle::default();
+    let frames_done = (0..DEFAULT_MAX_FRAMES_IN_FLIGHT)
+        .map(|_| {
+            assert!(pacing_bundle.has_free_slot());
+            pacing_bundle.frame_submitted()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pacing_bundle.frames_in_flight(),
+        DEFAULT_MAX_FRAMES_IN_FLIGHT
+    );
+    assert!(!pacing_bundle.has_free_slot());
+
+    frames_done.into_iter().for_each(|frame_done| frame_done());
+    assert_eq!(pacing_bundle.frames_in_flight(), 0);
+    assert!(pacing_bundle.has_free_slot());
+}
+
+#[test]
+fn test_ndc_shapes_are_not_scaled() {
+    let mut builder = Path::builder();
+    builder.begin(Point2D::new(-1.0, -1.0));
+    builder.line_to(Point2D::new(1.0, -1.0));
+    builder.line_to(Point2D::new(0.0, 1.0));
+    builder.end(true);
+    let shape = Shape {
+        path: builder.build(),
+        coordinate_space: CoordinateSpace::Ndc,
+        fill_rule: FillRule::NonZero,
+        style: DrawStyle::Fill,
+        on_mouse_input: None,
+    };
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((handle, Object::shape(shape, Brush::Solid(Color::RED)), 0));
+    let Geometry { vertices, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    let mut points = vertices
+        .iter()
+        .map(|vertex| vertex.point)
+        .collect::<Vec<_>>();
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(points, [[-1.0, -1.0], [0.0, 1.0], [1.0, -1.0]]);
+
+    // The triangle's apex is at the top of the window.
+    let hit = |x, y| hit_test_scene(&scene_bundle, PhysicalPosition::new(x, y), SIZE, 0.0);
+    assert_eq!(hit(50.0, 10.0), Some(handle));
+    assert_eq!(hit(10.0, 10.0), None);
+}
+
+#[test]
+fn test_measure_text() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let text = Text::new("Hello, metallic!")
+        .with_font_size(20.0)
+        .with_line_height(24.0);
+    let metrics = measure_text(&mut font_system, &text, size, 1.0);
+    assert_eq!(metrics.line_count, 1);
+    assert_eq!(metrics.height, 24.0);
+    assert!(metrics.width > 0.0);
+    assert!((metrics.height - (metrics.ascent + metrics.descent)).abs() < 0.1 * metrics.height);
+    assert!(metrics.ascent <= metrics.baseline && metrics.baseline <= metrics.height);
+
+    let metrics = measure_text(&mut font_system, &Text::new("one\ntwo\nthree"), size, 1.0);
+    assert_eq!(metrics.line_count, 3);
+    assert_eq!(metrics.height, 3.0 * Text::default().line_height);
+}
+
+#[test]
+fn test_wrap_width_wraps_text_into_a_column() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let text =
+        Text::new("The quick brown fox jumps over the lazy dog").with_shaping(Shaping::Advanced);
+    let unwrapped = measure_text(&mut font_system, &text, size, 1.0);
+    assert_eq!(unwrapped.line_count, 1);
+
+    let wrapped = measure_text(
+        &mut font_system,
+        &text.clone().with_wrap_width(100.0),
+        size,
+        1.0,
+    );
+    assert!(wrapped.line_count > 1);
+    assert!(wrapped.width <= 100.0);
+
+    // Changing the wrap width reshapes cached text.
+    let mut cached_buffer = CachedBuffer::new(&mut font_system);
+    let line_count = |buffer: &Buffer| buffer.layout_runs().count();
+    cached_buffer.update(&mut font_system, &text, size, 1.0);
+    assert_eq!(line_count(&cached_buffer.buffer), 1);
+    cached_buffer.update(&mut font_system, &text.with_wrap_width(100.0), size, 1.0);
+    assert_eq!(line_count(&cached_buffer.buffer), wrapped.line_count);
+}
+
+#[test]
+fn test_text_is_aligned_within_its_wrap_width() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let mut measure = |align| {
+        let text = Text::new("Hello, metallic!")
+            .with_wrap_width(400.0)
+            .with_align(align);
+        measure_text(&mut font_system, &text, size, 1.0)
+    };
+    let left = measure(TextAlign::Left);
+    assert_eq!(left.left, 0.0);
+    let center = measure(TextAlign::Center);
+    assert!((center.left - (400.0 - left.width) / 2.0).abs() < 1.0);
+    assert!((center.width - left.width).abs() < 1e-3);
+    let right = measure(TextAlign::Right);
+    assert!((right.left + right.width - 400.0).abs() < 1.0);
+
+    // Every line but the last is stretched to the full width.
+    let text = Text::new(&["word"; 40].join(" "))
+        .with_wrap_width(400.0)
+        .with_align(TextAlign::Justify);
+    let justified = measure_text(&mut font_system, &text, size, 1.0);
+    assert!(justified.line_count > 1);
+    assert!((justified.width - 400.0).abs() < 1.0);
+
+    // Without a wrap width, text is aligned within the window.
+    let text = Text::new("Hello, metallic!")
+        .with_align(TextAlign::Right)
+        .with_position(Point2D::new(0.0, 10.0));
+    let bounds = object_bounds(
+        &text.fill(Brush::Solid(Color::WHITE)),
+        &mut font_system,
+        size,
+        1.0,
+    );
+    assert!((bounds.max.x - 1000.0).abs() < 1.0);
+    assert!((bounds.width() - left.width).abs() < 1e-3);
+}
+
+#[test]
+fn test_baseline_anchored_text_shares_a_baseline() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let mut top = |font_size: f32, anchor| {
+        let text = Text::new("Hxg")
+            .with_font_size(font_size)
+            .with_line_height(font_size * 1.25)
+            .with_position(Point2D::new(0.0, 100.0))
+            .with_anchor(anchor);
+        let mut cached_buffer = CachedBuffer::new(&mut font_system);
+        cached_buffer.update(&mut font_system, &text, size, 1.0);
+        let baseline = measure_text(&mut font_system, &text, size, 1.0).baseline;
+        (text_top(&text, &cached_buffer.buffer), baseline)
+    };
+    assert_eq!(top(12.0, TextAnchor::Top).0, 100.0);
+    assert_eq!(top(24.0, TextAnchor::Top).0, 100.0);
+
+    let (small_top, small_baseline) = top(12.0, TextAnchor::Baseline);
+    let (large_top, large_baseline) = top(24.0, TextAnchor::Baseline);
+    assert!(large_top < small_top);
+    assert!((small_top + small_baseline - 100.0).abs() < 1e-3);
+    assert!((large_top + large_baseline - 100.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_long_words_only_wrap_under_glyph_wrapping() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(100, 1000);
+    let mut measure = |wrap| {
+        let text = Text::new("supercalifragilisticexpialidocious").with_wrap(wrap);
+        measure_text(&mut font_system, &text, size, 1.0)
+    };
+    let word = measure(Wrap::Word);
+    assert_eq!(word.line_count, 1);
+    assert!(word.width > size.width as f32);
+    let glyph = measure(Wrap::Glyph);
+    assert!(glyph.line_count > 1);
+    assert!(glyph.width <= size.width as f32);
+    assert_eq!(measure(Wrap::None).line_count, 1);
+}
+
+#[test]
+fn test_text_colors_are_clamped() {
+    let color = Color {
+        r: 1.0,
+        g: -0.1,
+        b: 1.5,
+        a: 0.5,
+    };
+    let glyphon_color = convert_color(color);
+    assert_eq!(
+        [
+            glyphon_color.r(),
+            glyphon_color.g(),
+            glyphon_color.b(),
+            glyphon_color.a()
+        ],
+        [255, 0, 255, 128]
+    );
+}
+
+#[test]
+fn test_text_is_shaped_with_the_selected_font() {
+    let mut font_system = FontSystem::new_with_locale_and_db("en-US".into(), Database::new());
+    for file in ["DejaVuSans.ttf", "DejaVuSansMono.ttf"] {
+        font_system
+            .db_mut()
+            .load_font_file(format!("/usr/share/fonts/truetype/dejavu/{file}"))
+            .unwrap();
+    }
+    let size = PhysicalSize::new(1000, 1000);
+    let mut shape = |family| {
+        let text = Text::new("iiiii").with_font(family);
+        let mut cached_buffer = CachedBuffer::new(&mut font_system);
+        cached_buffer.update(&mut font_system, &text, size, 1.0);
+        let font_ids = cached_buffer
+            .buffer
+            .layout_runs()
+            .flat_map(|layout_run| layout_run.glyphs.iter().map(|glyph| glyph.font_id))
+            .collect::<HashSet<_>>();
+        let families = font_ids
+            .into_iter()
+            .map(|font_id| {
+                font_system.db().face(font_id).unwrap().families[0]
+                    .0
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        (
+            families,
+            measure_text(&mut font_system, &text, size, 1.0).width,
+        )
+    };
+    let (sans_families, sans_width) = shape("DejaVu Sans");
+    let (mono_families, mono_width) = shape("DejaVu Sans Mono");
+    assert_eq!(sans_families, ["DejaVu Sans"]);
+    assert_eq!(mono_families, ["DejaVu Sans Mono"]);
+    assert!(sans_width < mono_width);
+}
+
+#[test]
+fn test_missing_font_files_report_their_path() {
+    let path = std::path::Path::new("/nonexistent/metallic/font.ttf");
+    let mut database = Database::new();
+    let error = load_font_file(&mut database, path).unwrap_err();
+    let MetallicError::FontLoadError {
+        path: error_path,
+        source,
+    } = &error
+    else {
+        panic!("Expected a font load error, got {error:?}");
+    };
+    assert_eq!(error_path, path);
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    assert!(error.to_string().contains("/nonexistent/metallic/font.ttf"));
+    assert_eq!(database.len(), 0);
+}
+
+#[test]
+fn test_cached_buffer_follows_text_changes() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let mut cached_buffer = CachedBuffer::new(&mut font_system);
+    let contents = |cached_buffer: &CachedBuffer| {
+        cached_buffer
+            .buffer
+            .layout_runs()
+            .map(|layout_run| layout_run.text.to_string())
+            .collect::<Vec<_>>()
+    };
+    for frame in 0..3 {
+        let text = Text::new(&format!("frame: {frame}"));
+        cached_buffer.update(&mut font_system, &text, size, 1.0);
+        cached_buffer.update(&mut font_system, &text, size, 1.0);
+        assert_eq!(contents(&cached_buffer), [format!("frame: {frame}")]);
+    }
+
+    let text = Text::new("frame: 2")
+        .with_font_size(32.0)
+        .with_line_height(40.0);
+    cached_buffer.update(&mut font_system, &text, size, 1.0);
+    assert_eq!(cached_buffer.buffer.metrics(), Metrics::new(32.0, 40.0));
+}
+
+#[test]
+fn test_gradient_text_colors_each_glyph_by_its_position() {
+    let mut font_system = FontSystem::new();
+    let size = PhysicalSize::new(1000, 1000);
+    let mut cached_buffer = CachedBuffer::new(&mut font_system);
+    let glyphs = |cached_buffer: &CachedBuffer| {
+        cached_buffer
+            .buffer
+            .layout_runs()
+            .flat_map(|layout_run| layout_run.glyphs.iter())
+            .map(|glyph| (glyph.x, glyph.w, glyph.color_opt))
+            .collect::<Vec<_>>()
+    };
+    let text = Text::new("gradient").with_position(Point2D::new(10.0, 10.0));
+    cached_buffer.update(&mut font_system, &text, size, 1.0);
+    let shaped = glyphs(&cached_buffer);
+    let (x, w, _) = shaped.last().copied().unwrap();
+    let brush = Brush::LinearGradient {
+        start: Point2D::new(10.0, 0.0),
+        end: Point2D::new(10.0 + x + w, 0.0),
+        stops: vec![(0.0, Color::RED), (1.0, Color::BLUE)],
+    };
+
+    cached_buffer.paint(&mut font_system, &text, &brush);
+    let painted = glyphs(&cached_buffer);
+    assert_eq!(painted.len(), shaped.len());
+    for (&(x, w, color), &(shaped_x, ..)) in painted.iter().zip(&shaped) {
+        // Glyphs keep their places, and take on the gradient's color at their
+        // centers.
+        assert_eq!(x, shaped_x);
+        let center = Point2D::new(10.0 + x + w / 2.0, 0.0);
+        assert_eq!(color, Some(convert_color(brush.color_at(center))));
+    }
+    let (first, last) = (painted[0].2.unwrap(), painted.last().unwrap().2.unwrap());
+    assert!(first.r() > first.b() && last.b() > last.r());
+
+    // Moving the text along the gradient recolors it...
+    let moved = text.clone().with_position(Point2D::new(50.0, 10.0));
+    cached_buffer.paint(&mut font_system, &moved, &brush);
+    assert_ne!(glyphs(&cached_buffer)[0].2, painted[0].2);
+    // ...while painting it solid takes the glyphs' colors away again.
+    cached_buffer.paint(&mut font_system, &moved, &Brush::Solid(Color::WHITE));
+    assert!(glyphs(&cached_buffer)
+        .iter()
+        .all(|&(_, _, color)| color.is_none()));
+}
+
+/// Set to make tests which need a GPU fail (rather than be skipped) on
+/// machines without an adapter, e.g., on CI runners which are meant to have
+/// one.
+const REQUIRE_GPU_VAR: &str = "METALLIC_REQUIRE_GPU";
+
+/// Reports that the current test is skipped since there is no GPU to run it
+/// on (for the given reason), or fails it if [`REQUIRE_GPU_VAR`] is set.
+///
+/// Skipped tests still pass, so the report goes to stderr, which `cargo test`
+/// shows for failing tests or with `--nocapture`.
+fn skip_without_gpu(reason: &str) {
+    let thread = std::thread::current();
+    let test = thread.name().unwrap_or("test");
+    assert!(
+        std::env::var_os(REQUIRE_GPU_VAR).is_none(),
+        "{test} needs a GPU, but {reason} ({REQUIRE_GPU_VAR} is set)"
+    );
+    eprintln!("skipping {test}: {reason} (set {REQUIRE_GPU_VAR} to fail instead)");
+}
+
+/// Creates a device on whichever adapter is available, if any, so that tests
+/// which need one can be skipped (see [`skip_without_gpu`]) on machines
+/// without.
+fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    headless_device_with_limits(|_| wgpu::Limits::default())
+}
+
+/// Like [`headless_device`], but with the adapter's limits adjusted by
+/// `limits`.
+fn headless_device_with_limits<F>(limits: F) -> Option<(wgpu::Device, wgpu::Queue)>
+where
+    F: FnOnce(wgpu::Limits) -> wgpu::Limits,
+{
+    let instance = wgpu::Instance::default();
+    let Some(adapter) = pollster::block_on(instance.request_adapter(&Default::default())) else {
+        skip_without_gpu("no adapter was found");
+        return None;
+    };
+    let descriptor = wgpu::DeviceDescriptor {
+        required_limits: limits(adapter.limits()),
+        ..Default::default()
+    };
+    match pollster::block_on(adapter.request_device(&descriptor, None)) {
+        Ok(device) => Some(device),
+        Err(error) => {
+            skip_without_gpu(&format!("the adapter has no suitable device ({error})"));
+            None
+        }
+    }
+}
+
+const OFFSCREEN_LENGTH: u32 = 64;
+const OFFSCREEN_SIZE: PhysicalSize<u32> = PhysicalSize::new(OFFSCREEN_LENGTH, OFFSCREEN_LENGTH);
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Creates an engine which renders into textures of [`OFFSCREEN_SIZE`]
+/// instead of a window, on whichever adapter is available, if any.
+fn headless_engine() -> Option<RenderingEngine> {
+    let (device, queue) = headless_device()?;
+    let wgpu_bundle = new_headless_wgpu_bundle(device, queue, OFFSCREEN_SIZE, OFFSCREEN_FORMAT);
+    Some(RenderingEngine::from_wgpu_bundle(wgpu_bundle, Color::BLACK))
+}
+
+#[test]
+fn test_every_render_ends_the_frame() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    engine.add_object(square(0.0, 0.0, 20.0, Color::RED));
+    engine.render().unwrap();
+    // The scene is unchanged, then hidden, then shown again; the latter two
+    // with an immediate-mode object.
+    for occluded in [None, Some(true), Some(false)] {
+        if let Some(occluded) = occluded {
+            engine.set_occluded(occluded);
+            engine.draw_rect(
+                Box2D::new(Point2D::new(30.0, 30.0), Point2D::new(40.0, 40.0)),
+                Color::BLUE,
+            );
+        };
+        for position in [(5.0, 5.0), (50.0, 50.0)] {
+            engine.register_io_event(IoEvent::CursorMoved {
+                position: PhysicalPosition::new(position.0, position.1),
+            });
+        }
+        assert!(!engine.events_this_frame().is_empty());
+
+        engine.render().unwrap();
+        assert!(engine.events_this_frame().is_empty());
+        assert!(engine.immediate_handles.is_empty());
+        assert_eq!(engine.scene_bundle.objects.len(), 1);
+    }
+}
+
+#[test]
+fn test_immediate_objects_dont_pile_up_while_nothing_is_presented() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    engine.set_occluded(true);
+    for _ in 0..2 {
+        engine.draw_circle(Point2D::new(32.0, 32.0), 10.0, Color::RED);
+        engine.draw_text("label", Point2D::new(0.0, 0.0), Color::WHITE);
+        assert_eq!(engine.scene_bundle.objects.len(), 2);
+        engine.render().unwrap();
+        assert!(engine.scene_bundle.objects.is_empty());
+    }
+    assert_eq!(engine.buffer_bundle.prepared_frames, 0);
+}
+
+#[test]
+fn test_unchanged_scenes_are_only_prepared_once() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    engine.add_object(square(0.0, 0.0, 20.0, Color::RED));
+    engine.render().unwrap();
+    engine.render().unwrap();
+    assert_eq!(engine.buffer_bundle.prepared_frames, 1);
+
+    engine.add_object(square(30.0, 30.0, 20.0, Color::BLUE));
+    engine.render().unwrap();
+    engine.render().unwrap();
+    assert_eq!(engine.buffer_bundle.prepared_frames, 2);
+}
+
+#[test]
+fn test_fps_overlay_is_drawn_on_top_until_hidden() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    let device = engine.wgpu_bundle.device.clone();
+    let queue = engine.wgpu_bundle.queue.clone();
+    engine.add_object(square(0.0, 0.0, 64.0, Color::RED));
+    engine.set_show_fps(true);
+    assert!(engine.show_fps());
+
+    // The overlay keeps frames coming, even though the scene is unchanged.
+    for _ in 0..3 {
+        engine.render().unwrap();
+        assert!(engine.redraw_pending);
+    }
+    assert_eq!(engine.buffer_bundle.prepared_frames, 3);
+    assert!(engine.fps().is_some());
+
+    let prepared_frame = engine.prepare().unwrap();
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        engine
+            .encode_frame(
+                encoder,
+                view,
+                prepared_frame.size,
+                CompositeAlphaMode::Opaque,
+                prepared_frame.fps_overlay,
+            )
+            .unwrap();
+    });
+    let is_green = |pixel: &[u8; 4]| pixel[1] > pixel[0];
+    let pixel = |x: u32, y: u32| &pixels[(y * OFFSCREEN_LENGTH + x) as usize];
+    assert!((8..24).any(|y| (8..40).any(|x| is_green(pixel(x, y)))));
+    assert_eq!(pixel(60, 60), &[255, 0, 0, 255]);
+    // It isn't part of the scene, though.
+    let image = engine.render_to_image(OFFSCREEN_SIZE).unwrap();
+    assert!(image.pixels().all(|pixel| pixel.0 == [255, 0, 0, 255]));
+
+    engine.set_show_fps(false);
+    engine.render().unwrap();
+    assert!(!engine.redraw_pending);
+    assert!(!engine.glyph_bundle.text_buffers.contains_key(&FPS_HANDLE));
+    assert_eq!(engine.fps(), None);
+}
+
+#[test]
+fn test_gradient_text_is_drawn_with_the_gradient() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    let text = Text::new("WWWWWWWW")
+        .with_font_size(32.0)
+        .with_line_height(40.0);
+    let width = engine.measure_text(&text).width;
+    engine.add_object(text.fill(Brush::LinearGradient {
+        start: Point2D::new(0.0, 0.0),
+        end: Point2D::new(width, 0.0),
+        stops: vec![(0.0, Color::RED), (1.0, Color::BLUE)],
+    }));
+
+    let image = engine
+        .render_to_image(PhysicalSize::new(width.ceil() as u32, 40))
+        .unwrap();
+    // The glyphs in the left quarter are mostly red, and those in the right
+    // one mostly blue.
+    let lit = image
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[0].max(pixel.0[2]) > 128)
+        .map(|(x, _, pixel)| (x, pixel.0))
+        .collect::<Vec<_>>();
+    let quarter = image.width() / 4;
+    let left = lit.iter().filter(|(x, _)| *x < quarter).collect::<Vec<_>>();
+    let right = lit
+        .iter()
+        .filter(|(x, _)| *x >= image.width() - quarter)
+        .collect::<Vec<_>>();
+    assert!(!left.is_empty() && !right.is_empty());
+    assert!(left.iter().all(|(_, pixel)| pixel[0] > pixel[2]));
+    assert!(right.iter().all(|(_, pixel)| pixel[2] > pixel[0]));
+}
+
+#[test]
+fn test_rotated_text_is_drawn_as_a_rotated_quad() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    let handle = engine.add_object(
+        Text::new("WW")
+            .with_position(Point2D::new(20.0, 44.0))
+            .with_angle(-FRAC_PI_2)
+            .fill(Brush::Solid(Color::WHITE)),
+    );
+    let lit_bounds = |engine: &mut RenderingEngine| {
+        let image = engine.render_to_image(OFFSCREEN_SIZE).unwrap();
+        Box2D::from_points(
+            image
+                .enumerate_pixels()
+                .filter(|(_, _, pixel)| pixel.0[0] > 128)
+                .map(|(x, y, _)| Point2D::new(x, y)),
+        )
+    };
+
+    // The text reads from bottom to top, to the right of its position.
+    let rotated = lit_bounds(&mut engine);
+    assert!(rotated.height() > 2 * rotated.width());
+    assert!(rotated.min.x >= 20 && rotated.max.y <= 44);
+    assert_eq!(engine.glyph_bundle.rotated_texts.len(), 1);
+
+    // Unrotated, it's drawn as regular text again, and its texture is freed.
+    let Some(ObjectKind::Text(text)) = engine.object_mut(handle).map(|object| &mut object.kind)
+    else {
+        panic!("The text is gone");
+    };
+    text.angle = 0.0;
+    let unrotated = lit_bounds(&mut engine);
+    assert!(unrotated.width() > 2 * unrotated.height());
+    assert!(engine.glyph_bundle.rotated_texts.is_empty());
+}
+
+#[test]
+fn test_rotated_text_bounds_are_rotated() {
+    let mut font_system = FontSystem::new();
+    let text = Text::new("rotated").with_position(Point2D::new(10.0, 10.0));
+    let bounds = object_bounds(
+        &text.clone().fill(Brush::Solid(Color::WHITE)),
+        &mut font_system,
+        SIZE,
+        1.0,
+    );
+    let rotated = object_bounds(
+        &text.with_angle(FRAC_PI_2).fill(Brush::Solid(Color::WHITE)),
+        &mut font_system,
+        SIZE,
+        1.0,
+    );
+    // Turned clockwise around its top-left corner, the text hangs down to
+    // its left.
+    let expected = Box2D::new(
+        Point2D::new(10.0 - bounds.height(), bounds.min.x),
+        Point2D::new(10.0, bounds.max.x),
+    );
+    assert!((rotated.min - expected.min).length() < 0.01);
+    assert!((rotated.max - expected.max).length() < 0.01);
+}
+
+#[test]
+fn test_readback_strips_padding_and_converts_pixels() {
+    assert_eq!(padded_bytes_per_row(1), 256);
+    assert_eq!(padded_bytes_per_row(64), 256);
+    assert_eq!(padded_bytes_per_row(65), 512);
+    let size = PhysicalSize::new(3, 2);
+    let bytes_per_row = padded_bytes_per_row(size.width);
+    let mut padded = vec![0xAB; (bytes_per_row * size.height) as usize];
+    // Opaque blue, half-transparent black, and fully transparent (BGRA).
+    let row = [0, 0, 255, 255, 0, 0, 0, 128, 9, 9, 9, 0];
+    for y in 0..size.height as usize {
+        let start = y * bytes_per_row as usize;
+        padded[start..start + row.len()].copy_from_slice(&row);
+    }
+    let image = to_rgba_image(
+        &padded,
+        size,
+        bytes_per_row,
+        wgpu::TextureFormat::Bgra8Unorm,
+    );
+    assert_eq!(image.dimensions(), (3, 2));
+    for y in 0..2 {
+        assert_eq!(image.get_pixel(0, y).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, y).0, [0, 0, 0, 128]);
+        assert_eq!(image.get_pixel(2, y).0, [0, 0, 0, 0]);
+    }
+    // Premultiplied sRGB colors are divided by alpha in linear space: linear
+    // 0.5 at half alpha is stored as linear 0.25, and comes back as 0.5.
+    let stored = (linear_to_srgb(0.25) * 255.0).round() as u8;
+    let image = to_rgba_image(
+        &[stored, stored, stored, 128],
+        PhysicalSize::new(1, 1),
+        4,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    );
+    let expected = (linear_to_srgb(0.25 * 255.0 / 128.0) * 255.0).round() as i32;
+    for channel in &image.get_pixel(0, 0).0[..3] {
+        assert!(
+            (*channel as i32 - expected).abs() <= 1,
+            "{channel} vs {expected}"
+        );
+    }
+}
+
+/// Has `draw` record into an [`OFFSCREEN_SIZE`]d texture (e.g., through
+/// [`begin_render_pass`]) and reads the resulting pixels back, row by row.
+fn render_offscreen<F>(device: &wgpu::Device, queue: &wgpu::Queue, draw: F) -> Vec<[u8; 4]>
+where
+    F: FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+{
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: OFFSCREEN_LENGTH,
+            height: OFFSCREEN_LENGTH,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (OFFSCREEN_LENGTH * OFFSCREEN_LENGTH * 4) as _,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let view = target.create_view(&Default::default());
+        draw(&mut encoder, &view);
+    };
+    encoder.copy_texture_to_buffer(
+        target.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(OFFSCREEN_LENGTH * 4),
+                rows_per_image: None,
+            },
+        },
+        target.size(),
+    );
+    queue.submit([encoder.finish()]);
+    readback
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let pixels = readback.slice(..).get_mapped_range();
+    pixels
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+        .collect()
+}
+
+#[test]
+fn test_shapes_render_with_msaa() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    // Every adapter supports 4 samples for this format.
+    let sample_count = 4;
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        ShapeBuilder::new()
+            .move_to(Point2D::new(0.0, 0.0))
+            .line_to(Point2D::new(64.0, 0.0))
+            .line_to(Point2D::new(0.0, 64.0))
+            .close()
+            .fill(Brush::Solid(Color::WHITE)),
+    );
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let geometry = tessellate_scene(&mut scene_bundle, OFFSCREEN_SIZE).unwrap();
+    prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+    let mut glyph_bundle = new_glyph_bundle(&device, &queue, OFFSCREEN_FORMAT);
+    glyph_bundle.set_sample_count(sample_count);
+    let image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, sample_count);
+    let render_pipeline = new_shape_pipeline(&device, OFFSCREEN_FORMAT, sample_count);
+    let msaa_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: OFFSCREEN_LENGTH,
+            height: OFFSCREEN_LENGTH,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let msaa_view = msaa_texture.create_view(&Default::default());
+
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass =
+            begin_render_pass(encoder, "test", view, Some(&msaa_view), Color::TRANSPARENT);
+        draw_layer(
+            &mut render_pass,
+            &render_pipeline,
+            &glyph_bundle,
+            &image_bundle,
+            &buffer_bundle,
+            0,
+        )
+        .unwrap();
+    });
+    let alpha = |x: u32, y: u32| pixels[(y * OFFSCREEN_LENGTH + x) as usize][3];
+    assert_eq!(alpha(10, 10), 255);
+    assert_eq!(alpha(50, 50), 0);
+    // The diagonal edge is blended into its surroundings rather than being
+    // either fully in or fully out.
+    assert!((0..OFFSCREEN_LENGTH)
+        .map(|x| alpha(x, OFFSCREEN_LENGTH - 1 - x))
+        .any(|alpha| alpha != 0 && alpha != 255));
+}
+
+#[test]
+fn test_layers_may_hold_more_vertices_than_16_bit_indices_reach() {
+    // Far more tiny, out-of-view triangles than 16 bits can index, followed
+    // by a square which covers the whole view.
+    let vertex_count = 3 * 25_000;
+    let mesh = Mesh::new(
+        vec![Point2D::new(-10.0, -10.0); vertex_count],
+        (0..vertex_count as u32).collect(),
+    )
+    .unwrap();
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), Object::mesh(mesh, Brush::Solid(Color::RED)));
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        square(0.0, 0.0, OFFSCREEN_LENGTH as f32, Color::BLUE),
+    );
+    let geometry = tessellate_scene(&mut scene_bundle, OFFSCREEN_SIZE).unwrap();
+    assert_eq!(geometry.vertices.len(), vertex_count + 4);
+    assert!(geometry.indices[vertex_count..]
+        .iter()
+        .all(|&index| index as usize >= vertex_count));
+
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+    let glyph_bundle = new_glyph_bundle(&device, &queue, OFFSCREEN_FORMAT);
+    let image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    let render_pipeline = new_shape_pipeline(&device, OFFSCREEN_FORMAT, 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        draw_layer(
+            &mut render_pass,
+            &render_pipeline,
+            &glyph_bundle,
+            &image_bundle,
+            &buffer_bundle,
+            0,
+        )
+        .unwrap();
+    });
+    assert!(pixels.iter().all(|&pixel| pixel == [0, 0, 255, 255]));
+}
+
+#[test]
+fn test_many_layers_are_drawn_in_order_within_one_pass() {
+    let Some(mut engine) = headless_engine() else {
+        return;
+    };
+    // A staircase of 50 overlapping squares, one per layer, which every layer
+    // drawn out of order would break.
+    let layer_count = 50;
+    for layer in 0..layer_count {
+        let color = match layer {
+            _ if layer == layer_count - 1 => Color::BLUE,
+            _ if layer % 2 == 0 => Color::RED,
+            _ => Color::GREEN,
+        };
+        let offset = layer as f32;
+        engine.add_object(square(offset, offset, OFFSCREEN_LENGTH as f32, color));
+        if layer + 1 < layer_count {
+            engine.push_layer().unwrap();
+        };
+    }
+    // Both go through the engine's own frame encoding; the image is then
+    // read back.
+    engine.render().unwrap();
+    assert_eq!(engine.buffer_bundle.layers.len(), layer_count);
+
+    let image = engine.render_to_image(OFFSCREEN_SIZE).unwrap();
+    let pixel = |xy: u32| image.get_pixel(xy, xy).0;
+    assert_eq!(pixel(0), [255, 0, 0, 255]);
+    for layer in 1..layer_count as u32 - 1 {
+        let expected = match layer % 2 == 0 {
+            true => [255, 0, 0, 255],
+            false => [0, 255, 0, 255],
+        };
+        assert_eq!(pixel(layer), expected, "layer {layer}");
+    }
+    assert_eq!(pixel(49), [0, 0, 255, 255]);
+    assert_eq!(pixel(60), [0, 0, 255, 255]);
+}
+
+/// Renders `text` offscreen and returns whether any pixel was drawn to.
+fn renders_text(
+    glyph_bundle: &mut GlyphBundle,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    text: &Text,
+) -> bool {
+    let brush = Brush::Solid(Color::WHITE);
+    glyph_bundle.resize(queue, OFFSCREEN_SIZE);
+    prepare_text(
+        glyph_bundle,
+        device,
+        queue,
+        OFFSCREEN_SIZE,
+        1.0,
+        &[vec![(Uuid::nil(), text, &brush)]],
+    );
+    let pixels = render_offscreen(device, queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        glyph_bundle.text_renderers[0]
+            .render(
+                &glyph_bundle.text_atlas,
+                &glyph_bundle.viewport,
+                &mut render_pass,
+            )
+            .unwrap();
+    });
+    pixels.iter().any(|pixel| pixel[3] != 0)
+}
+
+#[test]
+fn test_text_renders_after_clearing_the_glyph_cache() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut glyph_bundle = new_glyph_bundle(&device, &queue, OFFSCREEN_FORMAT);
+    let text = Text::new("Hi").with_font_size(32.0).with_line_height(40.0);
+    assert!(renders_text(&mut glyph_bundle, &device, &queue, &text));
+
+    glyph_bundle.clear_cache(&device, &queue);
+    assert!(glyph_bundle.text_renderers.is_empty());
+    assert!(renders_text(&mut glyph_bundle, &device, &queue, &text));
+}
+
+#[test]
+fn test_screenshots_are_tightly_packed_rgba() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    // Neither a multiple of the row alignment, nor in RGBA order.
+    let size = PhysicalSize::new(50, 30);
+    let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), square(15.0, 5.0, 20.0, Color::RED));
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let geometry = tessellate_scene(&mut scene_bundle, size).unwrap();
+    prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+    let glyph_bundle = new_glyph_bundle(&device, &queue, format);
+    let image_bundle = new_image_bundle(&device, format, 1);
+    let render_pipeline = new_shape_pipeline(&device, format, 1);
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let view = texture.create_view(&Default::default());
+        let mut render_pass =
+            begin_render_pass(&mut encoder, "test", &view, None, Color::TRANSPARENT);
+        draw_layer(
+            &mut render_pass,
+            &render_pipeline,
+            &glyph_bundle,
+            &image_bundle,
+            &buffer_bundle,
+            0,
+        )
+        .unwrap();
+    };
+    let pixels = read_back(&device, &queue, encoder, &texture)
+        .unwrap()
+        .into_raw();
+    assert_eq!(pixels.len(), (size.width * size.height * 4) as usize);
+    let pixel = |x: u32, y: u32| {
+        let start = ((y * size.width + x) * 4) as usize;
+        &pixels[start..start + 4]
+    };
+    assert_eq!(pixel(25, 15), [255, 0, 0, 255]);
+    assert_eq!(pixel(2, 2), [0, 0, 0, 0]);
+    assert_eq!(pixel(48, 28), [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_text_prepare_failure_still_draws_the_layers_shapes() {
+    // A tiny maximum texture size keeps the glyph atlas from growing large
+    // enough to fit a huge glyph, which fails the text's preparation.
+    let Some((device, queue)) = headless_device_with_limits(|limits| wgpu::Limits {
+        max_texture_dimension_2d: 256,
+        ..limits
+    }) else {
+        return;
+    };
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 32.0, Color::RED));
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let geometry = tessellate_scene(&mut scene_bundle, OFFSCREEN_SIZE).unwrap();
+    prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+
+    let mut glyph_bundle = new_glyph_bundle(&device, &queue, OFFSCREEN_FORMAT);
+    glyph_bundle.resize(&queue, OFFSCREEN_SIZE);
+    let text = Text::new("W").with_font_size(512.0).with_line_height(512.0);
+    let brush = Brush::Solid(Color::WHITE);
+    let layers = [vec![(Uuid::nil(), &text, &brush)]];
+    // Laid out in a larger area, since lines which don't fit aren't shaped.
+    let layout_size = PhysicalSize::new(1024, 1024);
+    prepare_text(
+        &mut glyph_bundle,
+        &device,
+        &queue,
+        layout_size,
+        1.0,
+        &layers,
+    );
+    assert_eq!(glyph_bundle.unprepared_layers, HashSet::from([0]));
+
+    let image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    let render_pipeline = new_shape_pipeline(&device, OFFSCREEN_FORMAT, 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        draw_layer(
+            &mut render_pass,
+            &render_pipeline,
+            &glyph_bundle,
+            &image_bundle,
+            &buffer_bundle,
+            0,
+        )
+        .unwrap();
+    });
+    let pixel = |x: u32, y: u32| pixels[(y * OFFSCREEN_LENGTH + x) as usize];
+    assert_eq!(pixel(16, 16), [255, 0, 0, 255]);
+    assert_eq!(pixel(48, 48), [0, 0, 0, 0]);
+
+    // Once the text fits again, it is drawn again.
+    let text = Text::new("W").with_font_size(32.0).with_line_height(40.0);
+    let layers = [vec![(Uuid::nil(), &text, &brush)]];
+    prepare_text(
+        &mut glyph_bundle,
+        &device,
+        &queue,
+        OFFSCREEN_SIZE,
+        1.0,
+        &layers,
+    );
+    assert!(glyph_bundle.unprepared_layers.is_empty());
+}
+
+#[test]
+fn test_texture_pool_reuses_textures_of_the_same_size() {
+    let Some((device, _)) = headless_device() else {
+        return;
+    };
+    let key = |width| TextureKey {
+        label: "test",
+        size: wgpu::Extent3d {
+            width,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        sample_count: 1,
+    };
+    let mut texture_pool = TexturePool::default();
+    let mut ids = |width, count| {
+        let ids = texture_pool
+            .textures(&device, key(width), count)
+            .iter()
+            .map(wgpu::Texture::global_id)
+            .collect::<Vec<_>>();
+        texture_pool.trim();
+        ids
+    };
+    let first = ids(16, 2);
+    assert_eq!(ids(16, 2), first);
+    assert_eq!(ids(16, 1), first[..1]);
+    assert_eq!(ids(16, 3)[..2], first);
+
+    // Textures of a size that is no longer used are dropped.
+    assert_ne!(ids(32, 2), first);
+    assert_ne!(ids(16, 2), first);
+}
+
+#[test]
+fn test_images_are_drawn_tinted_and_uploaded_once() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    let white = Arc::new(DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        4,
+        4,
+        image::Rgba([255, 255, 255, 255]),
+    )));
+    // Covers the left half of the target.
+    let left = Image::new(
+        white.clone(),
+        Box2D::new(
+            Point2D::new(0.0, 0.0),
+            Point2D::new(32.0, OFFSCREEN_LENGTH as _),
+        ),
+    );
+    let right = Image::new(
+        white.clone(),
+        Box2D::new(
+            Point2D::new(32.0, 0.0),
+            Point2D::new(OFFSCREEN_LENGTH as _, OFFSCREEN_LENGTH as _),
+        ),
+    );
+    let (red, blue) = (Brush::Solid(Color::RED), Brush::Solid(Color::BLUE));
+    prepare_images(
+        &mut image_bundle,
+        &device,
+        &queue,
+        (OFFSCREEN_SIZE, 1.0),
+        None,
+        &[vec![(&left, &red), (&right, &blue)]],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(image_bundle.uploaded_image_count(), 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        image_bundle.draw(&mut render_pass, 0);
+    });
+    let row = OFFSCREEN_LENGTH as usize * 10;
+    assert_eq!(pixels[row + 10], [255, 0, 0, 255]);
+    assert_eq!(pixels[row + 50], [0, 0, 255, 255]);
+
+    // Images which are no longer drawn are dropped.
+    prepare_images(
+        &mut image_bundle,
+        &device,
+        &queue,
+        (OFFSCREEN_SIZE, 1.0),
+        None,
+        &[vec![]],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(image_bundle.uploaded_image_count(), 0);
+}
+
+#[test]
+fn test_shapes_are_painted_with_loaded_textures() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    // A red pixel next to a blue one.
+    let rgba = [255, 0, 0, 255, 0, 0, 255, 255];
+    assert!(matches!(
+        image_bundle.load_texture(&device, &queue, &rgba, PhysicalSize::new(2, 2)),
+        Err(MetallicError::InvalidTextureError { len: 8, .. })
+    ));
+    let id = image_bundle
+        .load_texture(&device, &queue, &rgba, PhysicalSize::new(2, 1))
+        .unwrap();
+
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let uv_rect = Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(1.0, 1.0));
+    let textured = |id| Brush::Texture { id, uv_rect };
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        ShapeBuilder::new()
+            .rect(Point2D::origin(), Size2D::new(OFFSCREEN_LENGTH as _, 32.0))
+            .fill(textured(id)),
+    );
+    // Never loaded, so skipped.
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        ShapeBuilder::new()
+            .rect(
+                Point2D::new(0.0, 32.0),
+                Size2D::new(OFFSCREEN_LENGTH as _, 32.0),
+            )
+            .fill(textured(Uuid::new_v4())),
+    );
+    let geometry = tessellate_scene(&mut scene_bundle, OFFSCREEN_SIZE).unwrap();
+    // Textured shapes are left out of the shapes' buffers.
+    assert!(geometry.vertices.is_empty());
+    assert_eq!(geometry.textured[0].len(), 2);
+    prepare_images(
+        &mut image_bundle,
+        &device,
+        &queue,
+        (OFFSCREEN_SIZE, 1.0),
+        None,
+        &[vec![]],
+        &geometry.textured,
+    )
+    .unwrap();
+    // Loaded textures outlive frames which don't draw them.
+    assert_eq!(image_bundle.uploaded_image_count(), 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        image_bundle.draw(&mut render_pass, 0);
+    });
+    let pixel = |x: usize, y: usize| pixels[y * OFFSCREEN_LENGTH as usize + x];
+    assert_eq!(pixel(10, 16), [255, 0, 0, 255]);
+    assert_eq!(pixel(50, 16), [0, 0, 255, 255]);
+    assert_eq!(pixel(10, 48), [0, 0, 0, 0]);
+
+    image_bundle.clear();
+    assert_eq!(image_bundle.uploaded_image_count(), 1);
+    assert!(image_bundle.unload_texture(id));
+    assert!(!image_bundle.unload_texture(id));
+}
+
+#[test]
+fn test_textured_shapes_stretch_the_uv_rect_over_their_bounds() {
+    let points = [(10.0, 10.0), (30.0, 10.0), (10.0, 20.0), (30.0, 20.0)].map(Point2D::from);
+    let uv_rect = Box2D::new(Point2D::new(0.5, 0.0), Point2D::new(1.0, 0.5));
+    let triangles = textured_triangles(&points, &[0, 3, 1], uv_rect, SIZE);
+    let uvs = triangles.iter().map(|vertex| vertex.uv).collect::<Vec<_>>();
+    assert_eq!(uvs, [[0.5, 0.0], [1.0, 0.5], [1.0, 0.0]]);
+    assert_eq!(triangles[1].point, abs_to_ndc(points[3], SIZE).to_array());
+
+    // Flat shapes don't divide by zero.
+    let line = [Point2D::new(0.0, 5.0), Point2D::new(10.0, 5.0)];
+    let triangles = textured_triangles(&line, &[0, 1, 1], uv_rect, SIZE);
+    assert!(triangles.iter().all(|vertex| vertex.uv[1] == 0.0));
+}
+
+#[test]
+fn test_nearest_filtering_keeps_scaled_pixels_crisp() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    // A black pixel next to a white one.
+    let mut checker = image::RgbaImage::from_pixel(2, 1, image::Rgba([255, 255, 255, 255]));
+    checker.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+    let checker = Arc::new(DynamicImage::ImageRgba8(checker));
+    let rect = |y| {
+        Box2D::new(
+            Point2D::new(0.0, y),
+            Point2D::new(OFFSCREEN_LENGTH as _, y + 32.0),
+        )
+    };
+    let linear = Image::new(checker.clone(), rect(0.0));
+    let nearest = Image::new(checker, rect(32.0)).with_filter_mode(FilterMode::Nearest);
+    assert_eq!(linear.filter_mode, FilterMode::Linear);
+    let white = Brush::Solid(Color::WHITE);
+    prepare_images(
+        &mut image_bundle,
+        &device,
+        &queue,
+        (OFFSCREEN_SIZE, 1.0),
+        None,
+        &[vec![(&linear, &white), (&nearest, &white)]],
+        &[],
+    )
+    .unwrap();
+    // Both filter modes share the same upload.
+    assert_eq!(image_bundle.uploaded_image_count(), 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        image_bundle.draw(&mut render_pass, 0);
+    });
+    let row = |y: usize| &pixels[y * OFFSCREEN_LENGTH as usize..][..OFFSCREEN_LENGTH as usize];
+    // Just left of the middle, linear filtering blends into the white pixel.
+    let [gray, ..] = row(16)[28];
+    assert!(0 < gray && gray < 255, "{gray}");
+    assert!(row(48).iter().all(|&[r, ..]| r == 0 || r == 255));
+    assert_eq!(row(48)[28], [0, 0, 0, 255]);
+    assert_eq!(row(48)[36], [255, 255, 255, 255]);
+}
+
+#[test]
+fn test_background_image_covers_the_surface_underneath_the_layers() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut image_bundle = new_image_bundle(&device, OFFSCREEN_FORMAT, 1);
+    let green = Arc::new(DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        2,
+        2,
+        image::Rgba([0, 255, 0, 255]),
+    )));
+    let square = Image::new(
+        green.clone(),
+        Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(16.0, 16.0)),
+    );
+    let red = Brush::Solid(Color::RED);
+    prepare_images(
+        &mut image_bundle,
+        &device,
+        &queue,
+        (OFFSCREEN_SIZE, 1.0),
+        Some(&green),
+        &[vec![(&square, &red)]],
+        &[],
+    )
+    .unwrap();
+    // The background and the object share the same upload.
+    assert_eq!(image_bundle.uploaded_image_count(), 1);
+    let pixels = render_offscreen(&device, &queue, |encoder, view| {
+        let mut render_pass = begin_render_pass(encoder, "test", view, None, Color::TRANSPARENT);
+        image_bundle.draw_background(&mut render_pass);
+        image_bundle.draw(&mut render_pass, 0);
+    });
+    let row = OFFSCREEN_LENGTH as usize;
+    // Tinting green with red leaves nothing but black.
+    assert_eq!(pixels[row * 8 + 8], [0, 0, 0, 255]);
+    assert_eq!(pixels[row * 40 + 40], [0, 255, 0, 255]);
+    assert_eq!(pixels[row * 63 + 63], [0, 255, 0, 255]);
+}
+
+#[test]
+fn test_for_window_can_be_spawned() {
+    fn assert_send<T: Send + 'static>(_: T) {}
+    // Never called; this only has to compile.
+    let _ = |window| {
+        assert_send(RenderingEngine::for_window(
+            window,
+            Color::BLACK,
+            Features::empty(),
+        ))
+    };
+}
+
+#[test]
+fn test_adapter_features_match_the_adapter() {
+    let Some((device, _)) = headless_device() else {
+        return;
+    };
+    let (features, limits) =
+        pollster::block_on(crate::adapter_features(&Default::default())).unwrap();
+    // The device was created without any features or limits of its own, so
+    // the adapter supports at least as much as the device does.
+    assert!(features.contains(device.features()));
+    assert!(limits.max_texture_dimension_2d >= device.limits().max_texture_dimension_2d);
+}
+
+#[test]
+fn test_io_bundle_only_reports_changed_hits() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((handle, square(0.0, 0.0, 20.0, Color::RED), 0));
+    let mut io_bundle = IoBundle::default();
+    let mut register = |io_event| io_bundle.register(io_event, &scene_bundle, SIZE);
+    let move_to = |x, y| IoEvent::CursorMoved {
+        position: PhysicalPosition::new(x, y),
+    };
+    let press = IoEvent::MouseInput {
+        state: ElementState::Pressed,
+        button: MouseButton::Left,
+    };
+
+    assert!(!register(move_to(50.0, 50.0)));
+    assert!(!register(move_to(60.0, 60.0)));
+    assert!(!register(press));
+    assert!(register(move_to(5.0, 5.0)));
+    assert!(!register(move_to(6.0, 6.0)));
+    assert!(!register(move_to(7.0, 7.0)));
+    assert!(register(press));
+    assert!(register(IoEvent::CursorLeft));
+    assert!(!register(IoEvent::CursorLeft));
+
+    // Hovering over the same object over and over is only recorded once.
+    assert_eq!(
+        io_bundle.interactions(),
+        [
+            (handle, InteractionKind::Entered),
+            (handle, InteractionKind::Hovered),
+            (handle, InteractionKind::Pressed(MouseButton::Left)),
+            (handle, InteractionKind::Exited),
+        ]
+    );
+}
+
+#[test]
+fn test_io_bundle_accumulates_raw_mouse_motion() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle
+        .objects
+        .push((handle, square(0.0, 0.0, 20.0, Color::RED), 0));
+    let mut io_bundle = IoBundle::default();
+    let motion =
+        |x, y| IoEvent::from_device_event(&DeviceEvent::MouseMotion { delta: (x, y) }).unwrap();
+    assert_eq!(io_bundle.take_mouse_motion(), (0.0, 0.0));
+    assert!(!io_bundle.register(motion(3.0, -1.0), &scene_bundle, SIZE));
+    assert!(!io_bundle.register(motion(-1.5, -2.0), &scene_bundle, SIZE));
+    assert_eq!(io_bundle.take_mouse_motion(), (1.5, -3.0));
+    assert_eq!(io_bundle.take_mouse_motion(), (0.0, 0.0));
+
+    // Raw motion doesn't move the cursor, so nothing gets hit.
+    io_bundle.register(
+        IoEvent::CursorMoved {
+            position: PhysicalPosition::new(50.0, 50.0),
+        },
+        &scene_bundle,
+        SIZE,
+    );
+    io_bundle.register(motion(-45.0, -45.0), &scene_bundle, SIZE);
+    assert_eq!(io_bundle.hovered(), None);
+    assert!(io_bundle.interactions().is_empty());
+}
+
+#[test]
+fn test_io_bundle_tracks_touches_independently() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let left = Uuid::new_v4();
+    let right = Uuid::new_v4();
+    scene_bundle.objects.extend([
+        (left, square(0.0, 0.0, 20.0, Color::RED), 0),
+        (right, square(50.0, 0.0, 20.0, Color::BLUE), 0),
+    ]);
+    let mut io_bundle = IoBundle::default();
+    let touch = |io_bundle: &mut IoBundle, id, phase, x, y| {
+        io_bundle.register(
+            IoEvent::Touch {
+                id,
+                phase,
+                position: PhysicalPosition::new(x, y),
+            },
+            &scene_bundle,
+            SIZE,
+        )
+    };
+    assert!(touch(&mut io_bundle, 0, TouchPhase::Started, 5.0, 5.0));
+    assert!(touch(&mut io_bundle, 1, TouchPhase::Started, 55.0, 5.0));
+    assert!(!touch(&mut io_bundle, 0, TouchPhase::Moved, 6.0, 6.0));
+    assert!(!touch(&mut io_bundle, 0, TouchPhase::Moved, 7.0, 7.0));
+    assert!(touch(&mut io_bundle, 1, TouchPhase::Moved, 90.0, 90.0));
+    assert_eq!(io_bundle.touched(0), Some(left));
+    assert_eq!(io_bundle.touched(1), None);
+    assert!(touch(&mut io_bundle, 0, TouchPhase::Ended, 7.0, 7.0));
+    assert_eq!(io_bundle.touched(0), None);
+    // Touches don't move the cursor.
+    assert_eq!(io_bundle.hovered(), None);
+
+    let touched = |id, phase| InteractionKind::Touched { id, phase };
+    assert_eq!(
+        io_bundle.interactions(),
+        [
+            (left, touched(0, TouchPhase::Started)),
+            (right, touched(1, TouchPhase::Started)),
+            (left, touched(0, TouchPhase::Moved)),
+            (left, touched(0, TouchPhase::Ended)),
+        ]
+    );
+}
+
+#[test]
+fn test_buffer_bundle_only_rewrites_changed_objects() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handles = [0.0, 30.0, 60.0].map(|x| {
+        let handle = Uuid::new_v4();
+        scene_bundle.insert(handle, square(x, 0.0, 20.0, Color::RED));
+        handle
+    });
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let mut prepare = |scene_bundle: &mut SceneBundle| {
+        let geometry = tessellate_scene(scene_bundle, SIZE).unwrap();
+        prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+        buffer_bundle.written_bytes
+    };
+    let square_vertices = size_of::<Vertex>() as u64 * 4;
+    let square_indices = size_of::<u32>() as u64 * 6;
+    assert_eq!(
+        prepare(&mut scene_bundle),
+        3 * (square_vertices + square_indices)
+    );
+    assert_eq!(prepare(&mut scene_bundle), 0);
+
+    // Only the recolored square's vertices are rewritten, and nothing is
+    // re-tessellated.
+    scene_bundle.set_brush(handles[1], Brush::Solid(Color::BLUE));
+    assert_eq!(prepare(&mut scene_bundle), square_vertices);
+    assert_eq!(scene_bundle.tessellations.len(), 3);
+
+    // A new object changes the layout, so everything is rewritten.
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 30.0, 20.0, Color::RED));
+    assert_eq!(
+        prepare(&mut scene_bundle),
+        4 * (square_vertices + square_indices)
+    );
+
+    scene_bundle.clear();
+    assert!(scene_bundle.tessellations.is_empty());
+    assert_eq!(prepare(&mut scene_bundle), 0);
+}
+
+#[test]
+fn test_buffer_bundle_grows_geometrically() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut buffer_bundle = new_buffer_bundle(&device);
+    let capacity = buffer_bundle.vertex_buffer.size();
+    let mut prepare = |square_count: u64| {
+        let mut scene_bundle = SceneBundle::new(Color::BLACK);
+        for _ in 0..square_count {
+            scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 10.0, Color::RED));
+        }
+        let geometry = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+        prepare_geometry(&mut buffer_bundle, &device, &queue, geometry);
+        let vertex_buffer = &buffer_bundle.vertex_buffer;
+        (vertex_buffer.global_id(), vertex_buffer.size())
+    };
+    let fitting = capacity / (size_of::<Vertex>() as u64 * 4);
+    let (id, _) = prepare(1);
+    // Filling the buffer up to its capacity keeps it.
+    assert_eq!(prepare(fitting), (id, capacity));
+
+    // Going past it doubles the capacity, which then sticks around.
+    let (grown_id, grown_capacity) = prepare(fitting + 1);
+    assert_ne!(grown_id, id);
+    assert_eq!(grown_capacity, capacity * 2);
+    assert_eq!(prepare(1), (grown_id, grown_capacity));
+}
+
+#[test]
+fn test_fractional_z_inserts_between_layers() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let [first, second, between] = [(); 3].map(|_| Uuid::new_v4());
+    scene_bundle.layer = 1;
+    scene_bundle.insert(first, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.layer = 2;
+    scene_bundle.insert(second, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle
+        .insert_at(between, square(0.0, 0.0, 10.0, Color::BLUE), 1.5)
+        .unwrap();
+    let order = |scene_bundle: &SceneBundle| {
+        scene_bundle
+            .objects
+            .iter()
+            .map(|&(handle, _, layer)| (handle, layer))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        order(&scene_bundle),
+        [(first, 1), (between, 1), (second, 2)]
+    );
+    // The object is still part of layer 1, and drawn last within it.
+    let Geometry {
+        vertices, layers, ..
+    } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(layers, [(1, 0..12), (2, 12..18)]);
+    assert_eq!(vertices[4].color, to_vertex_color(Color::BLUE));
+
+    // Objects added to layer 1 afterwards still go below it.
+    scene_bundle.layer = 1;
+    let after = Uuid::new_v4();
+    scene_bundle.insert(after, square(0.0, 0.0, 10.0, Color::RED));
+    assert_eq!(
+        order(&scene_bundle),
+        [(first, 1), (after, 1), (between, 1), (second, 2)]
+    );
+
+    for z in [-1.0, f32::NAN, f32::INFINITY] {
+        let result = scene_bundle.insert_at(Uuid::new_v4(), square(0.0, 0.0, 1.0, Color::RED), z);
+        assert!(matches!(result, Err(MetallicError::InvalidZError { .. })));
+    }
+    let result = scene_bundle.insert_at(
+        Uuid::new_v4(),
+        square(0.0, 0.0, 1.0, Color::RED),
+        MAX_LAYERS as f32,
+    );
+    assert!(matches!(
+        result,
+        Err(MetallicError::TooManyLayersError { .. })
+    ));
+}
+
+#[test]
+fn test_z_bias_breaks_ties_regardless_of_insertion_order() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let [top, bottom, middle] = [(); 3].map(|_| Uuid::new_v4());
+    scene_bundle.insert(top, square(0.0, 0.0, 10.0, Color::RED).with_z_bias(5));
+    scene_bundle.insert(bottom, square(0.0, 0.0, 10.0, Color::GREEN).with_z_bias(-1));
+    scene_bundle.insert(middle, square(0.0, 0.0, 10.0, Color::BLUE));
+    let order = scene_bundle
+        .objects
+        .iter()
+        .map(|&(handle, _, _)| handle)
+        .collect::<Vec<_>>();
+    assert_eq!(order, [bottom, middle, top]);
+    let Geometry { vertices, .. } = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    let colors = vertices
+        .chunks(4)
+        .map(|square| square[0].color)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        colors,
+        [Color::GREEN, Color::BLUE, Color::RED].map(to_vertex_color)
+    );
+
+    // The bias only breaks ties; it never lifts an object out of its layer.
+    scene_bundle.layer = 1;
+    let above = Uuid::new_v4();
+    scene_bundle.insert(
+        above,
+        square(0.0, 0.0, 10.0, Color::WHITE).with_z_bias(-100),
+    );
+    assert_eq!(scene_bundle.objects.last().unwrap().0, above);
+}
+
+#[test]
+fn test_bulk_insertion_matches_individual_insertion() {
+    let objects = (0..1000)
+        .map(|index| {
+            let object = square(index as f32, 0.0, 10.0, Color::RED)
+                .with_z((index % 7) as f32)
+                .with_z_bias(index % 3 - 1);
+            (Uuid::new_v4(), object)
+        })
+        .collect::<Vec<_>>();
+    let scene_bundle = |bulk: bool| {
+        let mut scene_bundle = SceneBundle::new(Color::BLACK);
+        // Some objects which are already there, on both sides of the layer.
+        scene_bundle.insert(Uuid::nil(), square(0.0, 0.0, 1.0, Color::BLUE).with_z(3.0));
+        scene_bundle.push_layer().unwrap();
+        scene_bundle.insert(Uuid::max(), square(0.0, 0.0, 1.0, Color::BLUE).with_z(3.0));
+        scene_bundle.layer = 0;
+        scene_bundle.dirty = false;
+        match bulk {
+            true => scene_bundle.insert_all(objects.clone()),
+            false => {
+                for (handle, object) in objects.clone() {
+                    scene_bundle.insert(handle, object);
+                }
+            }
+        };
+        scene_bundle
+    };
+    let order = |scene_bundle: SceneBundle| {
+        assert!(scene_bundle.dirty);
+        scene_bundle
+            .objects
+            .iter()
+            .map(|&(handle, _, layer)| (handle, layer))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(order(scene_bundle(true)), order(scene_bundle(false)));
+}
+
+#[test]
+fn test_set_z_reorders_objects_within_a_layer() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let [red, green, blue] = [(); 3].map(|_| Uuid::new_v4());
+    scene_bundle.insert(red, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.insert(green, square(5.0, 5.0, 10.0, Color::GREEN));
+    scene_bundle.layer = 1;
+    scene_bundle.insert(blue, square(0.0, 0.0, 10.0, Color::BLUE));
+    let colors = |scene_bundle: &mut SceneBundle| {
+        let Geometry { vertices, .. } = tessellate_scene(scene_bundle, SIZE).unwrap();
+        vertices
+            .chunks(4)
+            .map(|square| square[0].color)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        colors(&mut scene_bundle),
+        [Color::RED, Color::GREEN, Color::BLUE].map(to_vertex_color)
+    );
+    let hit = |scene_bundle: &SceneBundle| {
+        hit_test_scene(scene_bundle, PhysicalPosition::new(7.0, 7.0), SIZE, 0.0)
+    };
+    scene_bundle.hidden_layers.insert(1);
+    assert_eq!(hit(&scene_bundle), Some(green));
+
+    // Raising the red square swaps it with the green one, but not with the
+    // blue one on the layer above.
+    assert_eq!(scene_bundle.set_z(red, 10.0), Some(0.0));
+    assert_eq!(hit(&scene_bundle), Some(red));
+    scene_bundle.hidden_layers.clear();
+    assert_eq!(
+        colors(&mut scene_bundle),
+        [Color::GREEN, Color::RED, Color::BLUE].map(to_vertex_color)
+    );
+    // Back at the same z, it stays on top of the green one, until it's
+    // lowered below it.
+    scene_bundle.set_z(red, 0.0);
+    assert_eq!(
+        colors(&mut scene_bundle),
+        [Color::GREEN, Color::RED, Color::BLUE].map(to_vertex_color)
+    );
+    scene_bundle.set_z(red, -1.0);
+    assert_eq!(
+        colors(&mut scene_bundle),
+        [Color::RED, Color::GREEN, Color::BLUE].map(to_vertex_color)
+    );
+    assert_eq!(scene_bundle.set_z(Uuid::new_v4(), 1.0), None);
+}
+
+#[test]
+fn test_absolute_coordinates_are_scaled_to_physical_pixels() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle.insert(handle, square(25.0, 25.0, 25.0, Color::RED));
+    let bounds = |scene_bundle: &mut SceneBundle| {
+        let Geometry { vertices, .. } = tessellate_scene(scene_bundle, SIZE).unwrap();
+        Box2D::from_points(vertices.iter().map(|vertex| Point2D::from(vertex.point)))
+    };
+    let hit = |scene_bundle: &SceneBundle, x, y| {
+        hit_test_scene(scene_bundle, PhysicalPosition::new(x, y), SIZE, 0.0)
+    };
+    assert_eq!(
+        bounds(&mut scene_bundle),
+        Box2D::new(Point2D::new(-0.5, 0.0), Point2D::new(0.0, 0.5))
+    );
+    assert_eq!(hit(&scene_bundle, 30.0, 30.0), Some(handle));
+
+    // At twice the scale, the same (logical) square covers the bottom-right
+    // quarter of the window, which is where it's hit too.
+    assert!(scene_bundle.set_scale_factor(2.0));
+    assert!(!scene_bundle.set_scale_factor(2.0));
+    assert_eq!(
+        bounds(&mut scene_bundle),
+        Box2D::new(Point2D::new(0.0, -1.0), Point2D::new(1.0, 0.0))
+    );
+    assert_eq!(hit(&scene_bundle, 30.0, 30.0), None);
+    assert_eq!(hit(&scene_bundle, 60.0, 60.0), Some(handle));
+}
+
+#[test]
+fn test_glyph_bundle_viewport_follows_resizes() {
+    let Some((device, queue)) = headless_device() else {
+        return;
+    };
+    let mut glyph_bundle = new_glyph_bundle(&device, &queue, OFFSCREEN_FORMAT);
+    let resolution = |glyph_bundle: &GlyphBundle| {
+        let Resolution { width, height } = glyph_bundle.viewport.resolution();
+        PhysicalSize::new(width, height)
+    };
+    glyph_bundle.resize(&queue, OFFSCREEN_SIZE);
+    assert_eq!(resolution(&glyph_bundle), OFFSCREEN_SIZE);
+
+    // Preparing text reuses the viewport as-is.
+    let text = Text::new("Hello");
+    let brush = Brush::Solid(Color::WHITE);
+    prepare_text(
+        &mut glyph_bundle,
+        &device,
+        &queue,
+        OFFSCREEN_SIZE,
+        1.0,
+        &[vec![(Uuid::nil(), &text, &brush)]],
+    );
+    assert_eq!(resolution(&glyph_bundle), OFFSCREEN_SIZE);
+
+    let new_size = PhysicalSize::new(200, 100);
+    glyph_bundle.resize(&queue, new_size);
+    assert_eq!(resolution(&glyph_bundle), new_size);
+}
+
+#[test]
+fn test_moving_objects_during_iteration_shows_up_next_frame() {
+    let mut font_system = FontSystem::new();
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let (moved, kept) = (Uuid::new_v4(), Uuid::new_v4());
+    scene_bundle.insert(moved, square(0.0, 0.0, 10.0, Color::RED));
+    scene_bundle.insert(kept, square(20.0, 0.0, 10.0, Color::BLUE));
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 2);
+
+    scene_bundle.dirty = false;
+    let mut bounds = vec![];
+    scene_bundle.for_each_object_mut(
+        |object| object_bounds(object, &mut font_system, SIZE, 1.0),
+        |handle, object, object_bounds| {
+            bounds.push(object_bounds);
+            if handle == moved {
+                *object = square(50.0, 50.0, 10.0, Color::RED);
+            };
+        },
+    );
+    assert!(scene_bundle.dirty);
+    let rect = |x, y| Box2D::new(Point2D::new(x, y), Point2D::new(x + 10.0, y + 10.0));
+    assert_eq!(bounds, [rect(0.0, 0.0), rect(20.0, 0.0)]);
+
+    // Only the moved square is re-tessellated.
+    let geometry = tessellate_scene_with(&mut scene_bundle, SIZE, to_unscaled_vertex).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 3);
+    let (_, vertices, _) = geometry.objects[0].clone();
+    assert!(
+        geometry.vertices[vertices.start as usize..vertices.end as usize]
+            .iter()
+            .all(|vertex| rect(50.0, 50.0).contains_inclusive(Point2D::from(vertex.point)))
+    );
+}
+
+#[test]
+fn test_object_bounds_are_absolute() {
+    let mut font_system = FontSystem::new();
+    let mut bounds = |object: &Object| object_bounds(object, &mut font_system, SIZE, 1.0);
+    let ndc = ShapeBuilder::new()
+        .coordinate_space(CoordinateSpace::Ndc)
+        .rect(Point2D::new(-1.0, 0.0), Size2D::new(1.0, 1.0))
+        .fill(Brush::Solid(Color::RED));
+    assert_eq!(
+        bounds(&ndc),
+        Box2D::new(Point2D::new(0.0, 0.0), Point2D::new(50.0, 50.0))
+    );
+    let text = Text::new("Hello")
+        .with_position(Point2D::new(10.0, 30.0))
+        .with_line_height(20.0)
+        .with_anchor(TextAnchor::Baseline)
+        .fill(Brush::Solid(Color::WHITE));
+    let text_bounds = bounds(&text);
+    assert!(text_bounds.min.y < 30.0 && 30.0 < text_bounds.max.y);
+    assert_eq!(text_bounds.height(), 20.0);
+    assert!(text_bounds.width() > 0.0);
+}
+
+#[test]
+fn test_object_vertex_count_follows_the_tolerance() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let circle = Uuid::new_v4();
+    scene_bundle.insert(
+        circle,
+        ShapeBuilder::new()
+            .circle(Point2D::new(50.0, 50.0), 40.0)
+            .fill(Brush::Solid(Color::RED)),
+    );
+    let label = Uuid::new_v4();
+    scene_bundle.insert(label, text("label"));
+
+    let coarse = scene_bundle
+        .object_vertex_count_at(circle, 1.0, SIZE)
+        .unwrap();
+    let fine = scene_bundle
+        .object_vertex_count_at(circle, 0.01, SIZE)
+        .unwrap();
+    assert!(coarse < fine, "{coarse:?} < {fine:?}");
+    assert_eq!(
+        scene_bundle.object_vertex_count(label, SIZE).unwrap(),
+        Some(0)
+    );
+    assert_eq!(
+        scene_bundle
+            .object_vertex_count(Uuid::new_v4(), SIZE)
+            .unwrap(),
+        None
+    );
+
+    // Once tessellated for a frame, the cached geometry is counted instead.
+    let geometry = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    let misses = scene_bundle.tessellation_stats.misses;
+    assert_eq!(
+        scene_bundle.object_vertex_count(circle, SIZE).unwrap(),
+        Some(geometry.vertices.len())
+    );
+    assert_eq!(scene_bundle.tessellation_stats.misses, misses);
+}
+
+#[test]
+fn test_gradients_color_each_vertex() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let gradient = Brush::LinearGradient {
+        start: Point2D::new(10.0, 0.0),
+        end: Point2D::new(30.0, 0.0),
+        stops: vec![(0.0, Color::RED), (1.0, Color::BLUE)],
+    };
+    scene_bundle.insert(
+        Uuid::new_v4(),
+        ShapeBuilder::new()
+            .rect(Point2D::new(10.0, 10.0), Size2D::new(20.0, 20.0))
+            .fill(gradient),
+    );
+    let geometry = tessellate_scene_with(&mut scene_bundle, SIZE, to_unscaled_vertex).unwrap();
+    assert_eq!(geometry.vertices.len(), 4);
+    for vertex in geometry.vertices {
+        let expected = match vertex.point[0] {
+            10.0 => Color::RED,
+            _ => Color::BLUE,
+        };
+        assert_eq!(vertex.color, to_vertex_color(expected));
+    }
+}
+
+#[test]
+fn test_tessellation_stats_count_hits_and_misses() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    for x in [0.0, 30.0] {
+        scene_bundle.insert(Uuid::new_v4(), square(x, 0.0, 20.0, Color::RED));
+    }
+    let mut stats_after = |size| {
+        tessellate_scene(&mut scene_bundle, size).unwrap();
+        let TessellationStats { hits, misses } = scene_bundle.tessellation_stats;
+        (hits, misses)
+    };
+    assert_eq!(stats_after(SIZE), (0, 2));
+    assert_eq!(stats_after(SIZE), (2, 2));
+    // Cached positions don't depend on the window's size.
+    assert_eq!(stats_after(PhysicalSize::new(200, 50)), (4, 2));
+
+    scene_bundle.clear();
+    assert!(scene_bundle.tessellations.is_empty());
+    scene_bundle.insert(Uuid::new_v4(), square(0.0, 0.0, 20.0, Color::RED));
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 3);
+}
+
+#[test]
+fn test_recoloring_reuses_the_cached_tessellation() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let handle = Uuid::new_v4();
+    scene_bundle.insert(handle, square(0.0, 0.0, 10.0, Color::RED));
+    let first = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 1);
+
+    scene_bundle.set_brush(handle, Brush::Solid(Color::BLUE));
+    let second = tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 1);
+    assert_eq!(first.indices, second.indices);
+    for (before, after) in first.vertices.iter().zip(&second.vertices) {
+        assert_eq!(before.point, after.point);
+        assert_eq!(after.color, to_vertex_color(Color::BLUE));
+    }
+}
+
+#[test]
+fn test_ndc_strokes_are_retessellated_on_resize() {
+    let mut scene_bundle = SceneBundle::new(Color::BLACK);
+    let fill = ShapeBuilder::new()
+        .coordinate_space(CoordinateSpace::Ndc)
+        .circle(Point2D::origin(), 0.5);
+    scene_bundle.insert(Uuid::new_v4(), fill.fill(Brush::Solid(Color::RED)));
+    let stroke = ShapeBuilder::new()
+        .coordinate_space(CoordinateSpace::Ndc)
+        .circle(Point2D::origin(), 0.5);
+    scene_bundle.insert(Uuid::new_v4(), stroke.stroke(2.0, Brush::Solid(Color::RED)));
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    tessellate_scene(&mut scene_bundle, SIZE).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 2);
+
+    // Only the stroke's width depends on the window's size.
+    tessellate_scene(&mut scene_bundle, PhysicalSize::new(200, 100)).unwrap();
+    assert_eq!(scene_bundle.tessellation_stats.misses, 3);
+}
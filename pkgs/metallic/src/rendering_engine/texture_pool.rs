@@ -0,0 +1,60 @@
+use std::collections::{HashMap, HashSet};
+
+use wgpu::{
+    Device, Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+/// Everything which decides whether a pooled texture can be reused for some
+/// purpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureKey {
+    /// What the textures are for (e.g., `"metallic.layer_texture"`); also used
+    /// as their label.
+    pub label: &'static str,
+    pub size: Extent3d,
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+    pub sample_count: u32,
+}
+
+/// Keeps offscreen textures (e.g., compositing targets) alive across frames,
+/// so that they are only ever (re)allocated once something about them, like
+/// the surface size, actually changes.
+#[derive(Default)]
+pub struct TexturePool {
+    textures: HashMap<TextureKey, Vec<Texture>>,
+    /// The keys which have been asked for since the last trim.
+    used: HashSet<TextureKey>,
+}
+
+impl TexturePool {
+    /// Returns `count` textures matching `key`, creating whichever are missing.
+    ///
+    /// The same key yields the same textures (in the same order) until they
+    /// are trimmed.
+    pub fn textures(&mut self, device: &Device, key: TextureKey, count: usize) -> &[Texture] {
+        self.used.insert(key);
+        let textures = self.textures.entry(key).or_default();
+        while textures.len() < count {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(key.label),
+                size: key.size,
+                mip_level_count: 1,
+                sample_count: key.sample_count,
+                dimension: TextureDimension::D2,
+                format: key.format,
+                usage: key.usage,
+                view_formats: &[],
+            });
+            textures.push(texture);
+        }
+        &textures[..count]
+    }
+
+    /// Drops every texture whose key hasn't been asked for since the last
+    /// trim (e.g., ones of a size that the surface no longer has).
+    pub fn trim(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.textures.retain(|key, _| used.contains(key));
+    }
+}
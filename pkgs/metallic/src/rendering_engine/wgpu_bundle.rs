@@ -1,41 +1,178 @@
-use std::mem::size_of;
+use std::{mem::size_of, sync::Arc};
 
 use wgpu::{
-    include_wgsl, BlendState, ColorTargetState, ColorWrites, Device, DeviceDescriptor, Face,
-    FragmentState, FrontFace, Instance, MultisampleState, PipelineCompilationOptions,
-    PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PresentMode, PrimitiveState,
+    include_wgsl, Adapter, BlendState, ColorTargetState, ColorWrites, CompositeAlphaMode, Device,
+    DeviceDescriptor, Features, FragmentState, FrontFace, Instance, Limits, MultisampleState,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PresentMode, PrimitiveState,
     PrimitiveTopology, Queue, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions,
-    ShaderModule, Surface, SurfaceConfiguration, TextureFormat, TextureUsages, VertexBufferLayout,
+    Surface, SurfaceConfiguration, SurfaceError, SurfaceTexture, Texture, TextureFormat,
+    TextureFormatFeatureFlags, TextureFormatFeatures, TextureUsages, VertexBufferLayout,
     VertexState, VertexStepMode,
 };
-use winit::{event_loop::ActiveEventLoop, window::Window};
+#[cfg(test)]
+use wgpu::{Extent3d, TextureDescriptor, TextureDimension};
+use winit::{
+    dpi::PhysicalSize,
+    error::ExternalError,
+    event_loop::ActiveEventLoop,
+    window::{CursorGrabMode, Window},
+};
 
-use crate::{rendering_engine::Vertex, InvalidConfigurationError, MetallicError, MetallicResult};
+use crate::{
+    rendering_engine::{pacing::DEFAULT_MAX_FRAMES_IN_FLIGHT, Vertex},
+    InvalidConfigurationError, MetallicError, MetallicResult,
+};
 
 pub struct WgpuBundle {
-    pub instance: Instance,
-    pub window: &'static Window,
-    pub surface: Surface<'static>,
-    pub device: Device,
-    pub queue: Queue,
+    pub target: RenderTarget,
+    /// Shared, so that an application which brings its own device (see
+    /// [`new_wgpu_bundle_from_parts`]) can keep using it.
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
     pub surface_configuration: SurfaceConfiguration,
-    pub shader: ShaderModule,
-    pub render_pipeline_layout: PipelineLayout,
+    pub present_modes: Vec<PresentMode>,
+    /// How many samples every pixel is rendered with; anything above 1
+    /// renders into a multisampled texture which is then resolved onto the
+    /// surface.
+    pub sample_count: u32,
+    /// The sample counts which the surface's format supports (always
+    /// including 1).
+    pub sample_counts: Vec<u32>,
     pub render_pipeline: RenderPipeline,
 }
 
-impl Drop for WgpuBundle {
-    fn drop(&mut self) {
-        let window = self.window as *const _ as *mut Window;
-        let _ = unsafe { Box::from_raw(window) };
+/// What frames are presented to.
+pub enum RenderTarget {
+    Window {
+        window: Arc<Window>,
+        surface: Surface<'static>,
+    },
+    /// Fresh textures of the configured size, which are dropped once drawn,
+    /// so that the engine can be driven without a window.
+    #[cfg(test)]
+    Headless,
+}
+
+/// The texture which the next frame is drawn into (see
+/// [`WgpuBundle::current_texture`]).
+pub enum FrameTexture {
+    Surface(SurfaceTexture),
+    #[cfg(test)]
+    Headless(Texture),
+}
+
+impl FrameTexture {
+    pub fn texture(&self) -> &Texture {
+        match self {
+            FrameTexture::Surface(surface_texture) => &surface_texture.texture,
+            #[cfg(test)]
+            FrameTexture::Headless(texture) => texture,
+        }
+    }
+
+    pub fn present(self) {
+        match self {
+            FrameTexture::Surface(surface_texture) => surface_texture.present(),
+            #[cfg(test)]
+            FrameTexture::Headless(_) => {}
+        };
+    }
+}
+
+impl WgpuBundle {
+    /// Returns the window's inner size (or, without a window, the configured
+    /// size).
+    pub fn inner_size(&self) -> PhysicalSize<u32> {
+        match &self.target {
+            RenderTarget::Window { window, .. } => window.inner_size(),
+            #[cfg(test)]
+            RenderTarget::Headless => PhysicalSize::new(
+                self.surface_configuration.width,
+                self.surface_configuration.height,
+            ),
+        }
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        match &self.target {
+            RenderTarget::Window { window, .. } => window.scale_factor(),
+            #[cfg(test)]
+            RenderTarget::Headless => 1.0,
+        }
+    }
+
+    pub fn request_redraw(&self) {
+        match &self.target {
+            RenderTarget::Window { window, .. } => window.request_redraw(),
+            #[cfg(test)]
+            RenderTarget::Headless => {}
+        };
+    }
+
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        match &self.target {
+            RenderTarget::Window { window, .. } => window.set_cursor_grab(mode),
+            #[cfg(test)]
+            RenderTarget::Headless => Ok(()),
+        }
+    }
+
+    /// Applies `surface_configuration` to the surface.
+    pub fn configure(&self) {
+        match &self.target {
+            RenderTarget::Window { surface, .. } => {
+                surface.configure(&self.device, &self.surface_configuration)
+            }
+            #[cfg(test)]
+            RenderTarget::Headless => {}
+        };
+    }
+
+    /// Acquires the texture which the next frame is drawn into.
+    pub fn current_texture(&self) -> Result<FrameTexture, SurfaceError> {
+        match &self.target {
+            RenderTarget::Window { surface, .. } => {
+                surface.get_current_texture().map(FrameTexture::Surface)
+            }
+            #[cfg(test)]
+            RenderTarget::Headless => Ok(FrameTexture::Headless(self.device.create_texture(
+                &TextureDescriptor {
+                    label: Some("metallic.headless_texture"),
+                    size: Extent3d {
+                        width: self.surface_configuration.width,
+                        height: self.surface_configuration.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: self.surface_configuration.format,
+                    usage: self.surface_configuration.usage,
+                    view_formats: &[],
+                },
+            ))),
+        }
     }
 }
 
-pub async fn new_wgpu_bundle(event_loop: &ActiveEventLoop) -> MetallicResult<WgpuBundle> {
+/// Creates a (possibly transparent) window.
+pub fn new_window(event_loop: &ActiveEventLoop, transparent: bool) -> MetallicResult<Arc<Window>> {
+    let window =
+        event_loop.create_window(Window::default_attributes().with_transparent(transparent))?;
+    Ok(Arc::new(window))
+}
+
+/// Sets up everything needed to render into `window`.
+///
+/// Nothing in here blocks: the adapter and device requests are simply
+/// awaited, so the future can be driven by any executor.
+pub async fn new_wgpu_bundle(
+    window: Arc<Window>,
+    transparent: bool,
+    required_features: Features,
+) -> MetallicResult<WgpuBundle> {
     let instance = Instance::default();
-    let window = event_loop.create_window(Window::default_attributes())?;
-    let window: &'static _ = Box::leak(Box::new(window));
-    let surface = instance.create_surface(window)?;
+    let surface = instance.create_surface(window.clone())?;
     let adapter = instance
         .request_adapter(&RequestAdapterOptions {
             compatible_surface: Some(&surface),
@@ -44,48 +181,152 @@ pub async fn new_wgpu_bundle(event_loop: &ActiveEventLoop) -> MetallicResult<Wgp
         .await
         .ok_or(MetallicError::NoAdapterFoundError)?;
     let (device, queue) = adapter
-        .request_device(&DeviceDescriptor::default(), None)
+        .request_device(
+            &DeviceDescriptor {
+                label: Some("metallic.device"),
+                required_features,
+                ..Default::default()
+            },
+            None,
+        )
         .await?;
-    let surface_configuration =
-        {
-            let size = window.inner_size();
-            let capabilities = surface.get_capabilities(&adapter);
-            let format = capabilities
-                .formats
-                .into_iter()
-                .find(TextureFormat::is_srgb)
-                .ok_or(MetallicError::InvalidConfigurationError(
-                    InvalidConfigurationError::NoTextureFormatFoundError,
-                ))?;
-            let present_mode = capabilities
-                .present_modes
-                .into_iter()
-                .find(|&present_mode| present_mode == PresentMode::Fifo)
-                .ok_or(MetallicError::InvalidConfigurationError(
-                    InvalidConfigurationError::NoFifoPresentModeFoundError,
-                ))?;
-            let &alpha_mode = capabilities.alpha_modes.first().ok_or(
-                MetallicError::InvalidConfigurationError(
-                    InvalidConfigurationError::NoAlphaModeFoundError,
-                ),
-            )?;
-            SurfaceConfiguration {
-                usage: TextureUsages::RENDER_ATTACHMENT,
-                format,
-                width: size.width,
-                height: size.height,
-                present_mode,
-                alpha_mode,
-                desired_maximum_frame_latency: 1,
-                view_formats: vec![],
-            }
-        };
+    let capabilities = surface.get_capabilities(&adapter);
+    let surface_configuration = {
+        let size = window.inner_size();
+        let format = capabilities
+            .formats
+            .into_iter()
+            .find(TextureFormat::is_srgb)
+            .ok_or(MetallicError::InvalidConfigurationError(
+                InvalidConfigurationError::NoTextureFormatFoundError,
+            ))?;
+        let present_mode = select_present_mode(&capabilities.present_modes, PresentMode::AutoVsync)
+            .ok_or(MetallicError::InvalidConfigurationError(
+                InvalidConfigurationError::NoPresentModeFoundError,
+            ))?;
+        let alpha_mode = select_alpha_mode(&capabilities.alpha_modes, transparent).ok_or(
+            MetallicError::InvalidConfigurationError(
+                InvalidConfigurationError::NoAlphaModeFoundError,
+            ),
+        )?;
+        SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode,
+            alpha_mode,
+            desired_maximum_frame_latency: DEFAULT_MAX_FRAMES_IN_FLIGHT,
+            view_formats: vec![],
+        }
+    };
+    new_wgpu_bundle_from_parts(
+        window,
+        &adapter,
+        Arc::new(device),
+        Arc::new(queue),
+        surface,
+        surface_configuration,
+    )
+}
+
+/// Assembles a bundle out of wgpu objects which have already been set up
+/// elsewhere, and configures `surface` with `surface_configuration`.
+///
+/// The surface has to belong to `window`, and both it and `device` have to
+/// have been created from the same instance as `adapter`. Its format has to
+/// be an sRGB one, since colors are written in linear space and rely on the
+/// surface to encode them.
+pub fn new_wgpu_bundle_from_parts(
+    window: Arc<Window>,
+    adapter: &Adapter,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    surface: Surface<'static>,
+    surface_configuration: SurfaceConfiguration,
+) -> MetallicResult<WgpuBundle> {
+    if !surface_configuration.format.is_srgb() {
+        return Err(MetallicError::InvalidConfigurationError(
+            InvalidConfigurationError::NoTextureFormatFoundError,
+        ));
+    };
+    validate_size(
+        PhysicalSize::new(surface_configuration.width, surface_configuration.height),
+        &device.limits(),
+    )?;
+    let present_modes = surface.get_capabilities(adapter).present_modes;
+    // Without this feature, the device only allows what every adapter
+    // guarantees, whatever this one would support.
+    let format_features = match device
+        .features()
+        .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        true => adapter.get_texture_format_features(surface_configuration.format),
+        false => surface_configuration
+            .format
+            .guaranteed_format_features(device.features()),
+    };
     surface.configure(&device, &surface_configuration);
+    let render_pipeline = new_shape_pipeline(&device, surface_configuration.format, 1);
+    Ok(WgpuBundle {
+        target: RenderTarget::Window { window, surface },
+        device,
+        queue,
+        surface_configuration,
+        present_modes,
+        sample_count: 1,
+        sample_counts: supported_sample_counts(&format_features),
+        render_pipeline,
+    })
+}
+
+/// Assembles a bundle which renders into textures of the given size and
+/// (sRGB) format instead of a window's surface.
+#[cfg(test)]
+pub fn new_headless_wgpu_bundle(
+    device: Device,
+    queue: Queue,
+    size: PhysicalSize<u32>,
+    format: TextureFormat,
+) -> WgpuBundle {
+    let surface_configuration = SurfaceConfiguration {
+        usage: TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: size.width,
+        height: size.height,
+        present_mode: PresentMode::Fifo,
+        alpha_mode: CompositeAlphaMode::Opaque,
+        desired_maximum_frame_latency: DEFAULT_MAX_FRAMES_IN_FLIGHT,
+        view_formats: vec![],
+    };
+    let format_features = format.guaranteed_format_features(device.features());
+    let render_pipeline = new_shape_pipeline(&device, format, 1);
+    WgpuBundle {
+        target: RenderTarget::Headless,
+        device: Arc::new(device),
+        queue: Arc::new(queue),
+        surface_configuration,
+        present_modes: vec![PresentMode::Fifo],
+        sample_count: 1,
+        sample_counts: supported_sample_counts(&format_features),
+        render_pipeline,
+    }
+}
+
+/// Creates the pipeline which draws the tessellated shapes (and meshes) into a
+/// target of the given format, with `sample_count` samples per pixel.
+pub fn new_shape_pipeline(
+    device: &Device,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
     let shader = device.create_shader_module(include_wgsl!("../shaders/main.wgsl"));
-    let render_pipeline_layout =
-        device.create_pipeline_layout(&PipelineLayoutDescriptor::default());
-    let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: None,
+    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("metallic.shape_pipeline_layout"),
+        ..Default::default()
+    });
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("metallic.shape_pipeline"),
         layout: Some(&render_pipeline_layout),
         vertex: VertexState {
             module: &shader,
@@ -101,34 +342,106 @@ pub async fn new_wgpu_bundle(event_loop: &ActiveEventLoop) -> MetallicResult<Wgp
             topology: PrimitiveTopology::TriangleList,
             strip_index_format: None,
             front_face: FrontFace::Ccw,
-            cull_mode: Some(Face::Back),
+            cull_mode: None,
             unclipped_depth: false,
             polygon_mode: PolygonMode::Fill,
             conservative: false,
         },
         depth_stencil: None,
-        multisample: MultisampleState::default(),
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
         fragment: Some(FragmentState {
             module: &shader,
             entry_point: "fs",
             compilation_options: PipelineCompilationOptions::default(),
             targets: &[Some(ColorTargetState {
-                format: surface_configuration.format,
-                blend: Some(BlendState::REPLACE),
+                format,
+                blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                 write_mask: ColorWrites::ALL,
             })],
         }),
         multiview: None,
-    });
-    Ok(WgpuBundle {
-        instance,
-        window,
-        surface,
-        device,
-        queue,
-        surface_configuration,
-        shader,
-        render_pipeline_layout,
-        render_pipeline,
+        cache: None,
     })
 }
+
+/// Checks that a texture of the given size can be created on a device with the
+/// given limits.
+///
+/// Exceeding `max_texture_dimension_2d` otherwise results in a wgpu validation
+/// panic when the surface (or any other render target) is configured.
+pub fn validate_size(size: PhysicalSize<u32>, limits: &Limits) -> MetallicResult<()> {
+    let max_dimension = limits.max_texture_dimension_2d;
+    match size.width <= max_dimension && size.height <= max_dimension {
+        true => Ok(()),
+        false => Err(MetallicError::SizeExceedsLimitsError {
+            width: size.width,
+            height: size.height,
+            max_dimension,
+        }),
+    }
+}
+
+/// Picks the surface's present mode, preferring `preferred`.
+///
+/// [`PresentMode::AutoVsync`] and [`PresentMode::AutoNoVsync`] are always
+/// available (wgpu resolves them to a supported mode itself). Any other mode
+/// which the surface doesn't support falls back to
+/// [`PresentMode::Fifo`], or else to the first supported mode.
+pub fn select_present_mode(
+    present_modes: &[PresentMode],
+    preferred: PresentMode,
+) -> Option<PresentMode> {
+    match preferred {
+        PresentMode::AutoVsync | PresentMode::AutoNoVsync if !present_modes.is_empty() => {
+            Some(preferred)
+        }
+        _ => [preferred, PresentMode::Fifo]
+            .into_iter()
+            .find(|present_mode| present_modes.contains(present_mode))
+            .or_else(|| present_modes.first().copied()),
+    }
+}
+
+/// Returns the sample counts which a format with the given features can be
+/// rendered (and resolved) with.
+pub fn supported_sample_counts(format_features: &TextureFormatFeatures) -> Vec<u32> {
+    let flags = format_features.flags;
+    let resolvable = flags.contains(TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&count| count == 1 || (resolvable && flags.sample_count_supported(count)))
+        .collect()
+}
+
+/// Picks the sample count to render with, falling back to 1 (i.e., no
+/// multisampling) if `preferred` isn't supported.
+pub fn select_sample_count(sample_counts: &[u32], preferred: u32) -> u32 {
+    match sample_counts.contains(&preferred) {
+        true => preferred,
+        false => 1,
+    }
+}
+
+/// Picks the surface's alpha mode.
+///
+/// Transparent windows need a mode which actually lets the surface's alpha
+/// through to the desktop; premultiplied alpha is preferred, since that's what
+/// the pipelines output. Otherwise (or if neither mode is supported), the
+/// first supported mode is used.
+pub fn select_alpha_mode(
+    alpha_modes: &[CompositeAlphaMode],
+    transparent: bool,
+) -> Option<CompositeAlphaMode> {
+    let preferred = [
+        CompositeAlphaMode::PreMultiplied,
+        CompositeAlphaMode::PostMultiplied,
+    ];
+    preferred
+        .into_iter()
+        .filter(|_| transparent)
+        .find(|alpha_mode| alpha_modes.contains(alpha_mode))
+        .or_else(|| alpha_modes.first().copied())
+}
@@ -0,0 +1,34 @@
+struct Out {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+@group(0) @binding(0)
+var layer: texture_2d<f32>;
+
+@group(0) @binding(1)
+var layer_sampler: sampler;
+
+// Only `x` is used; the rest is padding.
+@group(0) @binding(2)
+var<uniform> opacity: vec4f;
+
+// Draws a single triangle which covers the whole screen.
+@vertex
+fn vs(
+    @builtin(vertex_index) index: u32,
+) -> Out {
+    var out: Out;
+    out.uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    out.position = vec4f(out.uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs(
+    out: Out,
+) -> @location(0) vec4f {
+    // Layers are rendered with premultiplied alpha, so fading one out means
+    // scaling all of its channels.
+    return textureSample(layer, layer_sampler, out.uv) * opacity.x;
+}
@@ -0,0 +1,34 @@
+struct Out {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) tint: vec4f,
+}
+
+@group(0) @binding(0)
+var image: texture_2d<f32>;
+
+@group(0) @binding(1)
+var image_sampler: sampler;
+
+@vertex
+fn vs(
+    @location(0) vertex: vec2f,
+    @location(1) uv: vec2f,
+    @location(2) tint: vec4f,
+) -> Out {
+    var out: Out;
+    out.position = vec4f(vertex, 0.0, 1.0);
+    out.uv = uv;
+    out.tint = tint;
+    return out;
+}
+
+@fragment
+fn fs(
+    out: Out,
+) -> @location(0) vec4f {
+    // Images are stored with straight alpha, but everything is blended as
+    // premultiplied alpha.
+    let color = textureSample(image, image_sampler, out.uv) * out.tint;
+    return vec4f(color.rgb * color.a, color.a);
+}
@@ -18,5 +18,6 @@ fn vs(
 fn fs(
     out: Out,
 ) -> @location(0) vec4f {
-    return out.color;
+    // Everything is blended as premultiplied alpha.
+    return vec4f(out.color.rgb * out.color.a, out.color.a);
 }